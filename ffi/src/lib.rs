@@ -0,0 +1,12 @@
+//! Shared and static libraries of the bolt11 crate, kept out of its own manifest so that Rust
+//! users of `bolt11` only build an rlib.
+//!
+//! With the default `ffi` feature they export the C interface declared in
+//! `include/bolt11.h`, with the `wasm` feature the JavaScript bindings for `wasm-pack`.
+
+extern crate bolt11;
+
+#[cfg(feature = "ffi")]
+pub use bolt11::ffi::*;
+#[cfg(feature = "wasm")]
+pub use bolt11::wasm::*;
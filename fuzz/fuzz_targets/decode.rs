@@ -0,0 +1,12 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate bolt11;
+
+use bolt11::payment_request::PaymentRequest;
+
+fuzz_target!(|input: &str| {
+    if let Ok(pay_request) = PaymentRequest::decode(input) {
+        pay_request.encode().unwrap();
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate bolt11;
+
+use bolt11::codec::DecodeOptions;
+use bolt11::payment_request::PaymentRequest;
+
+fuzz_target!(|pay_request: PaymentRequest| {
+    let encoded = pay_request.encode().unwrap();
+    let options = DecodeOptions::unlimited();
+    let decoded = PaymentRequest::decode_with_options(&encoded, &options).unwrap();
+    assert_eq!(decoded, pay_request);
+});
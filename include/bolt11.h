@@ -0,0 +1,61 @@
+/*
+ * C interface of the bolt11 crate, built as libbolt11_ffi by the bolt11-ffi crate in ffi/.
+ *
+ * Strings returned by these functions are owned by the library and must be released with
+ * bolt11_string_free, decoded invoices with bolt11_free.
+ */
+
+#ifndef BOLT11_H
+#define BOLT11_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Bolt11Invoice {
+    /* Currency prefix, e.g. "lnbc". */
+    char *prefix;
+    /* Whether the invoice asks for a specific amount. */
+    bool has_amount;
+    /* Amount to pay in millisatoshis, 0 if has_amount is false. */
+    uint64_t amount_msat;
+    /* Creation time, in seconds since the UNIX epoch. */
+    uint64_t timestamp;
+    /* Seconds after timestamp the invoice expires, the default of one hour if not given. */
+    uint64_t expiry_seconds;
+    /* Compressed public key of the payee node. */
+    uint8_t node_id[33];
+    /* Payment hash. */
+    uint8_t payment_hash[32];
+    /* Description, NULL if the invoice commits to a description hash instead. */
+    char *description;
+    /* Private to the library. */
+    void *request;
+} Bolt11Invoice;
+
+/*
+ * Decode a payment request, checking it strictly. Returns NULL on failure, setting *error to
+ * a message to release with bolt11_string_free unless error is NULL.
+ */
+Bolt11Invoice *bolt11_decode(const char *input, char **error);
+
+/*
+ * Encode a decoded payment request back into a string, to release with bolt11_string_free.
+ * Returns NULL on failure, setting *error like bolt11_decode.
+ */
+char *bolt11_encode(const Bolt11Invoice *invoice, char **error);
+
+/* Release an invoice returned by bolt11_decode. Does nothing if invoice is NULL. */
+void bolt11_free(Bolt11Invoice *invoice);
+
+/* Release a string returned by this library. Does nothing if string is NULL. */
+void bolt11_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BOLT11_H */
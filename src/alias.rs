@@ -0,0 +1,21 @@
+//! Node alias resolution.
+
+use alloc::string::String;
+use secp256k1::PublicKey;
+
+/// Resolves node ids into display names, for instance from gossip data or an address book.
+///
+/// Closures taking a node id and returning an optional name implement this trait.
+pub trait AliasResolver {
+    /// Return the display name of the node, if known.
+    fn resolve(&self, node_id: &PublicKey) -> Option<String>;
+}
+
+impl<F> AliasResolver for F
+where
+    F: Fn(&PublicKey) -> Option<String>,
+{
+    fn resolve(&self, node_id: &PublicKey) -> Option<String> {
+        self(node_id)
+    }
+}
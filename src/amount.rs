@@ -1,3 +1,8 @@
+//! Amounts in the human readable part of payment requests.
+
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use msat::{MilliSatoshi, Rounding};
 use types::Error;
 /// Bitcoin subunits
 /// The following **multiplier** letters are defined:
@@ -9,14 +14,34 @@ use types::Error;
 ///
 pub struct Amount;
 
+/// How an amount is written in the human readable part of a payment request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AmountEncoding {
+    /// The shortest representation possible, as BOLT11 recommends.
+    Shortest,
+    /// Always use the given multiplier, `None` meaning whole bitcoins. Used to re-encode payment
+    /// requests exactly as written by implementations that don't pick the shortest form, since
+    /// the human readable part is covered by the signature.
+    Multiplier(Option<char>),
+}
+
+impl Default for AmountEncoding {
+    fn default() -> AmountEncoding {
+        AmountEncoding::Shortest
+    }
+}
+
 impl Amount {
-    /// the unit allowing for the shortest representation possible
-    fn unit(amount: u64) -> char {
-        match amount * 10 {
-            pico if pico % 1000 > 0 => 'p',
-            pico if pico % 1000_000 > 0 => 'n',
-            pico if pico % 1000_000_000 > 0 => 'u',
-            _ => 'm',
+    /// the unit allowing for the shortest representation possible, `None` for whole bitcoins
+    fn unit(amount: u64) -> Option<char> {
+        match amount {
+            msat if msat % 100 > 0 => Some('p'),
+            msat if msat % 100_000 > 0 => Some('n'),
+            msat if msat % 100_000_000 > 0 => Some('u'),
+            msat if msat % 100_000_000_000 > 0 => Some('m'),
+            _ => None,
         }
     }
 
@@ -24,20 +49,37 @@ impl Amount {
     /// BOLT #11:
     /// A reader SHOULD fail if `amount` contains a non-digit, or is followed by
     /// anything except a `multiplier` in the table above.
+    /// A reader MUST fail if the last decimal of a `p` amount is not 0, as it isn't a whole
+    /// number of millisatoshis.
     /// # Arguments
     /// * `amount` - A string that holds the amount to shorten
     pub fn decode(amount: &str) -> Result<u64, Error> {
-        match amount.chars().last() {
-            Some(a) if a == 'p' => amount[..amount.len() - 1].parse::<u64>().map(|v| v / 10),
-            Some(a) if a == 'n' => amount[..amount.len() - 1].parse::<u64>().map(|v| v * 100),
-            Some(a) if a == 'u' => amount[..amount.len() - 1]
-                .parse::<u64>()
-                .map(|v| v * 100_000),
-            Some(a) if a == 'm' => amount[..amount.len() - 1]
-                .parse::<u64>()
-                .map(|v| v * 100_000_000),
-            _ => amount.parse::<u64>(),
-        }.map_err(Error::ParseIntErr)
+        let (digits, multiplier) = match amount.chars().last() {
+            Some(last) if last.is_ascii_digit() => (amount, None),
+            Some(last) => (&amount[..amount.len() - last.len_utf8()], Some(last)),
+            None => return Err(Error::InvalidValue("empty amount".to_owned())),
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(Error::InvalidValue(format!("invalid amount `{}`", amount)));
+        }
+        let value = digits.parse::<u64>().map_err(Error::ParseIntErr)?;
+        let msat = match multiplier {
+            Some('p') if value % 10 != 0 => {
+                return Err(Error::SubMillisatoshiAmount(amount.to_owned()))
+            }
+            Some('p') => Some(value / 10),
+            Some('n') => value.checked_mul(100),
+            Some('u') => value.checked_mul(100_000),
+            Some('m') => value.checked_mul(100_000_000),
+            None => value.checked_mul(100_000_000_000),
+            Some(m) => {
+                return Err(Error::InvalidValue(format!(
+                    "invalid amount multiplier `{}`",
+                    m
+                )))
+            }
+        };
+        msat.ok_or_else(|| Error::InvalidValue(format!("amount `{}` is too large", amount)))
     }
 
     /// Given an amount in Bitcoin, shorten it
@@ -46,12 +88,130 @@ impl Amount {
     /// A writer MUST encode `amount` as a positive decimal integer with no
     /// leading zeroes, SHOULD use the shortest representation possible.
     pub fn encode(amount: u64) -> String {
-        match amount {
-            amt if Amount::unit(amt) == 'p' => format!("{}p", amt * 10),
-            amt if Amount::unit(amt) == 'n' => format!("{}n", amt / 100),
-            amt if Amount::unit(amt) == 'u' => format!("{}u", amt / 100_000),
-            amt if Amount::unit(amt) == 'm' => format!("{}m", amt / 100_000_000),
-            amt => amt.to_string(),
+        match Amount::unit(amount) {
+            Some('p') => format!("{}0p", amount),
+            Some('n') => format!("{}n", amount / 100),
+            Some('u') => format!("{}u", amount / 100_000),
+            Some('m') => format!("{}m", amount / 100_000_000),
+            _ => format!("{}", amount / 100_000_000_000),
+        }
+    }
+
+    /// Given an amount in Bitcoin, encode it as requested by `encoding`. Fails for a zero
+    /// amount.
+    /// # Arguments
+    /// * `amount` - The amount in millisatoshis
+    /// * `encoding` - The representation to use
+    pub fn encode_with(amount: u64, encoding: AmountEncoding) -> Result<String, Error> {
+        // BOLT #11: a writer MUST encode `amount` as a positive decimal integer
+        if amount == 0 {
+            return Err(Error::InvalidValue(
+                "amount must be positive, leave it out to accept any amount".to_owned(),
+            ));
+        }
+        let multiplier = match encoding {
+            AmountEncoding::Shortest => return Ok(Amount::encode(amount)),
+            AmountEncoding::Multiplier(multiplier) => multiplier,
+        };
+        let (msat_per_unit, suffix) = match multiplier {
+            Some('p') => return Ok(format!("{}0p", amount)),
+            Some('n') => (100, "n"),
+            Some('u') => (100_000, "u"),
+            Some('m') => (100_000_000, "m"),
+            None => (100_000_000_000, ""),
+            Some(m) => {
+                return Err(Error::InvalidParameter(format!(
+                    "invalid amount multiplier `{}`",
+                    m
+                )))
+            }
+        };
+        if amount % msat_per_unit == 0 {
+            Ok(format!("{}{}", amount / msat_per_unit, suffix))
+        } else {
+            Err(Error::InvalidValue(format!(
+                "{} msat can't be written with multiplier `{}`",
+                amount, suffix
+            )))
+        }
+    }
+
+    /// Parse a decimal amount of bitcoins, e.g. `"0.00025"`, into millisatoshis.
+    ///
+    /// Up to 12 decimals are accepted, the precision of the `p` multiplier, but since pico
+    /// amounts must be a multiple of 10 the amount must still be a whole number of
+    /// millisatoshis: sub-millisatoshi remainders are rejected rather than rounded.
+    /// # Arguments
+    /// * `btc` - The decimal amount of bitcoins
+    pub fn from_btc_str(btc: &str) -> Result<u64, Error> {
+        let invalid = || Error::InvalidValue(format!("invalid bitcoin amount `{}`", btc));
+        let (whole, fraction) = match btc.find('.') {
+            Some(dot) => (&btc[..dot], &btc[dot + 1..]),
+            None => (btc, ""),
+        };
+        if whole.is_empty() && fraction.is_empty()
+            || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > 11 {
+            return Err(Error::SubMillisatoshiAmount(btc.to_owned()));
+        }
+        let whole_msat = match whole {
+            "" => 0,
+            whole => whole
+                .parse::<u64>()
+                .map_err(Error::ParseIntErr)?
+                .checked_mul(100_000_000_000)
+                .ok_or_else(invalid)?,
+        };
+        let fraction_msat = match fraction {
+            "" => 0,
+            fraction => {
+                fraction.parse::<u64>().map_err(Error::ParseIntErr)?
+                    * 10u64.pow(11 - fraction.len() as u32)
+            }
+        };
+        whole_msat.checked_add(fraction_msat).ok_or_else(invalid)
+    }
+
+    /// Format an amount in millisatoshis as a decimal amount of bitcoins, e.g. `"0.00025"`.
+    /// # Arguments
+    /// * `amount` - The amount in millisatoshis
+    pub fn to_btc_string(amount: u64) -> String {
+        let (whole, fraction) = (amount / 100_000_000_000, amount % 100_000_000_000);
+        if fraction == 0 {
+            whole.to_string()
+        } else {
+            let fraction = format!("{:011}", fraction);
+            format!("{}.{}", whole, fraction.trim_end_matches('0'))
+        }
+    }
+
+    /// Convert an amount in satoshis into millisatoshis, failing on overflow.
+    /// # Arguments
+    /// * `sat` - The amount in satoshis
+    pub fn from_sat(sat: u64) -> Result<u64, Error> {
+        MilliSatoshi::from_sat(sat).map(|msat| msat.as_u64())
+    }
+
+    /// Convert an amount in millisatoshis into satoshis.
+    /// # Arguments
+    /// * `amount` - The amount in millisatoshis
+    /// * `rounding` - What to do with the sub-satoshi remainder
+    pub fn to_sat(amount: u64, rounding: Rounding) -> Result<u64, Error> {
+        MilliSatoshi::new(amount).to_sat(rounding)
+    }
+
+    /// The encoding preserving how `amount` is written, if it isn't the shortest one.
+    /// # Arguments
+    /// * `amount` - A valid encoded amount
+    pub fn encoding_of(amount: &str) -> AmountEncoding {
+        let multiplier = amount.chars().last().filter(|c| !c.is_digit(10));
+        match Amount::decode(amount) {
+            Ok(msat) if Amount::unit(msat) != multiplier => AmountEncoding::Multiplier(multiplier),
+            _ => AmountEncoding::Shortest,
         }
     }
 }
@@ -75,13 +235,95 @@ mod test {
 
         assert_eq!(Some('m'), Amount::encode(100_000_000).chars().last());
         assert_eq!(Some('m'), Amount::encode(1000_000_000).chars().last());
-        assert_eq!(Some('m'), Amount::encode(100_000_000_000).chars().last());
+        assert_eq!(Some('m'), Amount::encode(150_000_000_000).chars().last());
+        assert_eq!("1", Amount::encode(100_000_000_000));
+        assert_eq!("2500u", Amount::encode(250_000_000));
+    }
+
+    #[test]
+    fn encode_with_multiplier() {
+        let exact = |m| AmountEncoding::Multiplier(m);
+        assert_eq!("2500u", Amount::encode_with(250_000_000, AmountEncoding::Shortest).unwrap());
+        assert_eq!("2500000n", Amount::encode_with(250_000_000, exact(Some('n'))).unwrap());
+        assert_eq!("25000000p", Amount::encode_with(2_500_000, exact(Some('p'))).unwrap());
+        assert_eq!("1000m", Amount::encode_with(100_000_000_000, exact(Some('m'))).unwrap());
+        assert_eq!("1", Amount::encode_with(100_000_000_000, exact(None)).unwrap());
+        assert!(Amount::encode_with(250_000_000, exact(Some('m'))).is_err());
+        assert!(Amount::encode_with(250_000_000, exact(Some('x'))).is_err());
+
+        assert_eq!(Amount::encoding_of("2500u"), AmountEncoding::Shortest);
+        assert_eq!(Amount::encoding_of("2500000n"), exact(Some('n')));
+        assert_eq!(Amount::encoding_of("1000m"), exact(Some('m')));
+        assert_eq!(Amount::encoding_of("1"), AmountEncoding::Shortest);
     }
+    #[test]
+    fn decode_errors() {
+        assert_eq!(1u64, Amount::decode("10p").unwrap());
+        for amount in vec!["1p", "25p", "2500000001p"] {
+            match Amount::decode(amount) {
+                Err(Error::SubMillisatoshiAmount(ref a)) if a == amount => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        assert!(Amount::decode("").is_err());
+        assert!(Amount::decode("m").is_err());
+        assert!(Amount::decode("+1m").is_err());
+        assert!(Amount::decode("1x").is_err());
+        assert!(Amount::decode("1.5m").is_err());
+        assert!(Amount::decode("1000000000000m").is_err());
+        assert!(Amount::decode("18446744073709551616p").is_err());
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Amount::to_btc_string(25_000_000), "0.00025");
+        assert_eq!(Amount::to_btc_string(100_000_000_000), "1");
+        assert_eq!(Amount::to_btc_string(150_000_000_001), "1.50000000001");
+        assert_eq!(Amount::to_btc_string(0), "0");
+        for msat in vec![1, 25_000_000, 100_000_000_000, 150_000_000_001] {
+            assert_eq!(
+                Amount::from_btc_str(&Amount::to_btc_string(msat)).unwrap(),
+                msat
+            );
+        }
+        assert_eq!(Amount::from_sat(2500).unwrap(), 2_500_000);
+        assert_eq!(Amount::to_sat(2_500_999, Rounding::Floor).unwrap(), 2500);
+        assert!(Amount::to_sat(2_500_999, Rounding::Exact).is_err());
+        assert_eq!(Amount::encode(u64::max_value()), "184467440737095516150p");
+    }
+
+    #[test]
+    fn btc_str() {
+        assert_eq!(25_000_000u64, Amount::from_btc_str("0.00025").unwrap());
+        assert_eq!(25_000_000u64, Amount::from_btc_str(".00025000").unwrap());
+        assert_eq!(100_000_000_000u64, Amount::from_btc_str("1").unwrap());
+        assert_eq!(150_000_000_000u64, Amount::from_btc_str("1.5").unwrap());
+        assert_eq!(1u64, Amount::from_btc_str("0.00000000001").unwrap());
+        assert_eq!(1u64, Amount::from_btc_str("0.000000000010").unwrap());
+        assert_eq!(0u64, Amount::from_btc_str("0.").unwrap());
+
+        // sub-millisatoshi remainders
+        for btc in vec!["0.000000000001", "0.000000000015"] {
+            match Amount::from_btc_str(btc) {
+                Err(Error::SubMillisatoshiAmount(_)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        assert!(Amount::from_btc_str("").is_err());
+        assert!(Amount::from_btc_str(".").is_err());
+        assert!(Amount::from_btc_str("1,5").is_err());
+        assert!(Amount::from_btc_str("-1").is_err());
+        assert!(Amount::from_btc_str("1.2.3").is_err());
+        assert!(Amount::from_btc_str("1e3").is_err());
+        assert!(Amount::from_btc_str("999999999999").is_err());
+    }
+
     #[test]
     fn decode() {
         assert_eq!(100_000_000u64, Amount::decode("1m").unwrap());
         assert_eq!(100_000_000u64, Amount::decode("1000u").unwrap());
         assert_eq!(100_000_000u64, Amount::decode("1000000n").unwrap());
         assert_eq!(100_000_000u64, Amount::decode("1000000000p").unwrap());
+        assert_eq!(100_000_000_000u64, Amount::decode("1").unwrap());
     }
 }
@@ -51,7 +51,12 @@
 #![deny(unused_mut)]
 #![allow(dead_code)]
 
-use std::{error, fmt};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error;
+use u5::U5;
 
 /// Grouping structure for the human-readable part and the data part
 /// of decoded Bech32 string.
@@ -60,7 +65,7 @@ pub struct Bech32 {
     /// Human-readable part
     pub hrp: String,
     /// Data payload
-    pub data: Vec<u8>,
+    pub data: Vec<U5>,
 }
 
 type EncodeResult = Result<String, Error>;
@@ -73,14 +78,11 @@ impl Bech32 {
             return Err(Error::InvalidLength);
         }
         let hrp_bytes: Vec<u8> = self.hrp.clone().into_bytes();
-        let mut combined: Vec<u8> = self.data.clone();
+        let mut combined: Vec<U5> = self.data.clone();
         combined.extend_from_slice(&create_checksum(&hrp_bytes, &self.data));
         let mut encoded: String = format!("{}{}", self.hrp, SEP);
         for p in combined {
-            if p >= 32 {
-                return Err(Error::InvalidData(p));
-            }
-            encoded.push(CHARSET[p as usize]);
+            encoded.push(u5_to_char(p));
         }
         Ok(encoded)
     }
@@ -88,117 +90,118 @@ impl Bech32 {
     // btcutil, allowing strings to be more than 90 characters.
     /// Decode from a string
     pub fn from_string(s: String) -> DecodeResult {
-        // Ensure overall length is within bounds
-        let len: usize = s.len();
-        // The maximum allowed length for a bech32 string is 90. It must also
-        // be at least 8 characters, since it needs a non-empty HRP, a
-        // separator, and a 6 character checksum.
-        // NB: The 90 character check specified in BIP173 is skipped here, to
-        // allow strings longer than 90 characters.
-        if len < 8 {
-            return Err(Error::InvalidLength);
-        }
-        // Check for missing separator
-        if s.find(SEP).is_none() {
-            return Err(Error::MissingSeparator);
-        }
+        let mut hrp = String::new();
+        let mut data = Vec::new();
+        decode_into(&s, &mut hrp, &mut data)?;
+        Ok(Bech32 { hrp, data })
+    }
+}
 
-        // Split at separator and check for two pieces
-        let parts: Vec<&str> = s.rsplitn(2, SEP).collect();
-        let raw_hrp = parts[1];
-        let raw_data = parts[0];
-        if raw_hrp.len() < 1 || raw_data.len() < 6 {
-            return Err(Error::InvalidLength);
-        }
+/// Decode from a string into the given buffers, which are cleared first. On success `hrp`
+/// holds the lowercase human-readable part and `data` the data payload without checksum.
+pub fn decode_into(s: &str, hrp: &mut String, data: &mut Vec<U5>) -> Result<(), Error> {
+    hrp.clear();
+    data.clear();
+    // Ensure overall length is within bounds
+    let len: usize = s.len();
+    // The maximum allowed length for a bech32 string is 90. It must also
+    // be at least 8 characters, since it needs a non-empty HRP, a
+    // separator, and a 6 character checksum.
+    // NB: The 90 character check specified in BIP173 is skipped here, to
+    // allow strings longer than 90 characters.
+    if len < 8 {
+        return Err(Error::InvalidLength);
+    }
+    // Check for missing separator
+    if s.find(SEP).is_none() {
+        return Err(Error::MissingSeparator);
+    }
 
-        let mut has_lower: bool = false;
-        let mut has_upper: bool = false;
-        let mut hrp_bytes: Vec<u8> = Vec::new();
-        for b in raw_hrp.bytes() {
-            // Valid subset of ASCII
-            if b < 33 || b > 126 {
-                return Err(Error::InvalidChar(b));
-            }
-            let mut c = b;
-            // Lowercase
-            if b >= b'a' && b <= b'z' {
-                has_lower = true;
-            }
-            // Uppercase
-            if b >= b'A' && b <= b'Z' {
-                has_upper = true;
-                // Convert to lowercase
-                c = b + (b'a' - b'A');
-            }
-            hrp_bytes.push(c);
-        }
+    // Split at separator and check for two pieces
+    let parts: Vec<&str> = s.rsplitn(2, SEP).collect();
+    let raw_hrp = parts[1];
+    let raw_data = parts[0];
+    if raw_hrp.len() < 1 || raw_data.len() < 6 {
+        return Err(Error::InvalidLength);
+    }
 
-        // Check data payload
-        let mut data_bytes: Vec<u8> = Vec::new();
-        for b in raw_data.bytes() {
-            // Aphanumeric only
-            if !((b >= b'0' && b <= b'9') || (b >= b'A' && b <= b'Z') || (b >= b'a' && b <= b'z')) {
-                return Err(Error::InvalidChar(b));
-            }
-            // Excludes these characters: [1,b,i,o]
-            if b == b'1' || b == b'b' || b == b'i' || b == b'o' {
-                return Err(Error::InvalidChar(b));
-            }
-            // Lowercase
-            if b >= b'a' && b <= b'z' {
-                has_lower = true;
-            }
-            let mut c = b;
-            // Uppercase
-            if b >= b'A' && b <= b'Z' {
-                has_upper = true;
-                // Convert to lowercase
-                c = b + (b'a' - b'A');
-            }
-            data_bytes.push(CHARSET_REV[c as usize] as u8);
+    let mut has_lower: bool = false;
+    let mut has_upper: bool = false;
+    for b in raw_hrp.bytes() {
+        // Valid subset of ASCII
+        if b < 33 || b > 126 {
+            return Err(Error::InvalidChar(b));
         }
-
-        // Ensure no mixed case
-        if has_lower && has_upper {
-            return Err(Error::MixedCase);
+        let mut c = b;
+        // Lowercase
+        if b >= b'a' && b <= b'z' {
+            has_lower = true;
+        }
+        // Uppercase
+        if b >= b'A' && b <= b'Z' {
+            has_upper = true;
+            // Convert to lowercase
+            c = b + (b'a' - b'A');
         }
+        hrp.push(c as char);
+    }
 
-        // Ensure checksum
-        if !verify_checksum(&hrp_bytes, &data_bytes) {
-            return Err(Error::InvalidChecksum);
+    // Check data payload
+    for b in raw_data.bytes() {
+        // Aphanumeric only
+        if !((b >= b'0' && b <= b'9') || (b >= b'A' && b <= b'Z') || (b >= b'a' && b <= b'z')) {
+            return Err(Error::InvalidChar(b));
+        }
+        // Lowercase
+        if b >= b'a' && b <= b'z' {
+            has_lower = true;
         }
+        // Uppercase
+        if b >= b'A' && b <= b'Z' {
+            has_upper = true;
+        }
+        // Excludes these characters in either case: [1,b,i,o]
+        data.push(char_to_u5(b as char).ok_or(Error::InvalidChar(b))?);
+    }
 
-        // Remove checksum from data payload
-        let dbl: usize = data_bytes.len();
-        data_bytes.truncate(dbl - 6);
+    // Ensure no mixed case
+    if has_lower && has_upper {
+        return Err(Error::MixedCase);
+    }
 
-        Ok(Bech32 {
-            hrp: String::from_utf8(hrp_bytes).unwrap(),
-            data: data_bytes,
-        })
+    // Ensure checksum
+    if !verify_checksum(hrp.as_bytes(), data) {
+        return Err(Error::InvalidChecksum);
     }
+
+    // Remove checksum from data payload
+    let dbl: usize = data.len();
+    data.truncate(dbl - 6);
+    Ok(())
 }
-/// verify checksum
-pub fn create_checksum(hrp: &Vec<u8>, data: &Vec<u8>) -> Vec<u8> {
+
+/// Create the 6 word checksum of the lowercase human-readable part `hrp` and the data payload.
+pub fn create_checksum(hrp: &[u8], data: &[U5]) -> Vec<U5> {
     let mut values: Vec<u8> = hrp_expand(hrp);
-    values.extend_from_slice(data);
+    values.extend(data.iter().map(|word| word.to_u8()));
     // Pad with 6 zeros
     values.extend_from_slice(&[0u8; 6]);
     let plm: u32 = polymod(values) ^ 1;
-    let mut checksum: Vec<u8> = Vec::new();
+    let mut checksum: Vec<U5> = Vec::new();
     for p in 0..6 {
-        checksum.push(((plm >> 5 * (5 - p)) & 0x1f) as u8);
+        checksum.push(U5::from_low_bits((plm >> 5 * (5 - p)) as u8));
     }
     checksum
 }
 
-fn verify_checksum(hrp: &Vec<u8>, data: &Vec<u8>) -> bool {
+/// Verify the checksum at the end of `data` against the lowercase human-readable part `hrp`.
+pub fn verify_checksum(hrp: &[u8], data: &[U5]) -> bool {
     let mut exp = hrp_expand(hrp);
-    exp.extend_from_slice(data);
+    exp.extend(data.iter().map(|word| word.to_u8()));
     polymod(exp) == 1u32
 }
 
-fn hrp_expand(hrp: &Vec<u8>) -> Vec<u8> {
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
     let mut v: Vec<u8> = Vec::new();
     for b in hrp {
         v.push(*b >> 5);
@@ -244,6 +247,21 @@ const CHARSET_REV: [i8; 128] = [
     -1, -1, -1, -1,
 ];
 
+/// Character encoding `word`.
+pub const fn u5_to_char(word: U5) -> char {
+    CHARSET[word.to_u8() as usize]
+}
+
+/// Word encoded by the bech32 character `c`, in either case, if it is one.
+pub const fn char_to_u5(c: char) -> Option<U5> {
+    let value = if (c as u32) < 128 { CHARSET_REV[c as usize] } else { -1 };
+    if value < 0 {
+        None
+    } else {
+        Some(U5::from_low_bits(value as u8))
+    }
+}
+
 /// Generator coefficients
 const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
 
@@ -277,6 +295,7 @@ impl fmt::Display for Error {
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
@@ -319,6 +338,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn payment_request_checksum() {
+        let s = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxx\
+                 mmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9rn449\
+                 d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let mut hrp = String::new();
+        let mut data = Vec::new();
+        decode_into(s, &mut hrp, &mut data).unwrap();
+        let checksum = create_checksum(hrp.as_bytes(), &data);
+        let encoded: String = checksum.iter().map(|p| u5_to_char(*p)).collect();
+        assert!(s.ends_with(&encoded));
+
+        data.extend(checksum);
+        assert!(verify_checksum(hrp.as_bytes(), &data));
+        data[0] = U5::from_low_bits(data[0].to_u8() ^ 1);
+        assert!(!verify_checksum(hrp.as_bytes(), &data));
+    }
+
+    #[test]
+    fn uppercase_excluded_chars() {
+        let s = "LNBC1PVJLUEZPP5QQQSYQCYQ5RQWZQFQQQSYQCYQ5RQWZQFQQQSYQCYQ5RQWZQFQYPQDPL2PKX2CTNV5SXX\
+                 MMWWD5KGETJYPEH2URSDAE8G6TWVUS8G6RFWVS8QUN0DFJKXAQ8RKX3YF5TCSYZ3D73GAFNH3CAX9RN449\
+                 D9P5UXZ9EZHHYPD0ELX87SJLE52X86FUX2YPATGDDC6K63N7ERQZ25LE42C4U4ECKY03YLCQCA784W";
+        let mut hrp = String::new();
+        let mut data = Vec::new();
+        decode_into(s, &mut hrp, &mut data).unwrap();
+        // Replacing an 'L' (word 31) must not decode as the original payment request
+        let sep = s.rfind('1').unwrap();
+        let at = sep + 1 + s[sep + 1..].find('L').unwrap();
+        for c in &["B", "I", "O"] {
+            let mut replaced = s.to_string();
+            replaced.replace_range(at..at + 1, c);
+            let result = decode_into(&replaced, &mut String::new(), &mut Vec::new());
+            assert_eq!(result, Err(Error::InvalidChar(c.as_bytes()[0])));
+        }
+    }
+
+    #[test]
+    fn lookup_tables() {
+        for (value, c) in CHARSET.iter().enumerate() {
+            let word = U5::new(value as u8).unwrap();
+            assert_eq!(u5_to_char(word), *c);
+            assert_eq!(char_to_u5(*c), Some(word));
+            assert_eq!(char_to_u5(c.to_ascii_uppercase()), Some(word));
+        }
+        for c in &['1', 'b', 'i', 'o', ' ', '\u{e9}'] {
+            assert_eq!(char_to_u5(*c), None);
+        }
+    }
+
     #[test]
     fn invalid() {
         let pairs: Vec<(&str, Error)> = vec![
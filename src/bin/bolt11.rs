@@ -0,0 +1,203 @@
+//! Command line tool to inspect, create and check payment requests.
+//!
+//! ```text
+//! bolt11 decode <invoice>
+//! bolt11 encode [--secret-key-file <path>] --payment-hash <hex> --description <text> [options]
+//! bolt11 verify <invoice> [--node-id <hex>]
+//! ```
+//!
+//! `encode` reads the hex secret key from the given file, or from standard input, so it doesn't
+//! show up in the process list or the shell history.
+
+extern crate bolt11;
+extern crate secp256k1;
+extern crate zeroize;
+
+use bolt11::builder::PaymentRequestBuilder;
+use bolt11::codec::DecodeOptions;
+use bolt11::currency::Currency;
+use bolt11::expiry::ExpiryGrace;
+use bolt11::hex;
+use bolt11::msat::MilliSatoshi;
+use bolt11::payment_request::PaymentRequest;
+use bolt11::signer::LocalSigner;
+use bolt11::types::Error;
+use secp256k1::PublicKey;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+use zeroize::Zeroizing;
+
+const USAGE: &'static str = "usage:
+  bolt11 decode <invoice>
+  bolt11 encode [--secret-key-file <path>] --payment-hash <hex>
+                (--description <text> | --description-hash <hex>) [--currency <prefix>] [--amount <msat>] [--expiry <seconds>]
+                [--min-final-cltv <blocks>] [--payment-secret <hex>] [--fallback <address>]
+  bolt11 verify <invoice> [--node-id <hex>]";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let result = match args.split_first() {
+        Some((command, args)) if command == "decode" => decode(args),
+        Some((command, args)) if command == "encode" => encode(args),
+        Some((command, args)) if command == "verify" => verify(args),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Print the fields of a payment request, one per line.
+fn decode(args: &[String]) -> Result<(), Error> {
+    let (invoice, options) = invoice_and_options(args)?;
+    if let Some(&(ref name, _)) = options.first() {
+        return Err(Error::InvalidParameter(format!("unknown option `{}`", name)));
+    }
+    let pay_request = PaymentRequest::decode(&invoice)?;
+    println!("currency:  {}", pay_request.prefix);
+    match pay_request.amount {
+        Some(amount) => println!("amount:    {}", amount),
+        None => println!("amount:    any"),
+    }
+    println!("timestamp: {}", pay_request.timestamp);
+    println!("expires:   {}", pay_request.expires_at());
+    println!("node id:   {}", hex::encode(&pay_request.node_id.serialize_compressed()[..]));
+    for tag in &pay_request.tags {
+        println!("tag {}:     {}", tag.tag_char(), tag);
+    }
+    println!("signature: {}", hex::encode(&pay_request.signature));
+    println!("summary:   {}", pay_request.summary());
+    Ok(())
+}
+
+/// Build and sign a payment request, printing its encoding.
+fn encode(args: &[String]) -> Result<(), Error> {
+    let options = options(args)?;
+    let mut currency = Currency::Bitcoin;
+    let mut secret_key_file = None;
+    for &(ref name, ref value) in &options {
+        match name.as_str() {
+            "--currency" => currency = value.parse()?,
+            "--secret-key-file" => secret_key_file = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+    let mut builder = PaymentRequestBuilder::new(currency);
+    for (name, value) in options {
+        builder = match name.as_str() {
+            "--currency" | "--secret-key-file" => builder,
+            "--payment-hash" => builder.payment_hash(parse_hex(&value)?),
+            "--description" => builder.description_auto(value),
+            "--description-hash" => builder.description_hash(parse_hex(&value)?),
+            "--amount" => builder.amount(MilliSatoshi::new(parse_number(&name, &value)?)),
+            "--expiry" => builder.expiry_seconds(parse_number(&name, &value)?),
+            "--min-final-cltv" => builder.min_final_cltv_expiry(parse_number(&name, &value)?),
+            "--payment-secret" => builder.payment_secret(parse_hex32(&value)?),
+            "--fallback" => builder.fallback_address(value),
+            _ => return Err(Error::InvalidParameter(format!("unknown option `{}`", name))),
+        };
+    }
+    let pay_request = {
+        let signer = read_signer(secret_key_file)?;
+        builder.build_with(signer.node_id().clone(), &signer)?
+    };
+    println!("{}", pay_request.encode()?);
+    Ok(())
+}
+
+/// Check that a payment request is well formed, signed by the expected node and payable now.
+fn verify(args: &[String]) -> Result<(), Error> {
+    let (invoice, options) = invoice_and_options(args)?;
+    let pay_request = PaymentRequest::decode_with_options(&invoice, &DecodeOptions::strict())?;
+    for (name, value) in options {
+        match name.as_str() {
+            "--node-id" => {
+                let node_id = parse_node_id(&value)?;
+                if pay_request.verify_any(&[node_id])?.is_none() {
+                    return Err(Error::InvalidValue(
+                        "payment request is not signed by the given node".to_owned(),
+                    ));
+                }
+            }
+            _ => return Err(Error::InvalidParameter(format!("unknown option `{}`", name))),
+        }
+    }
+    pay_request.validate(&ExpiryGrace::none())?;
+    println!("valid: {}", pay_request.summary());
+    Ok(())
+}
+
+/// Split the arguments into the invoice and the options following it.
+fn invoice_and_options(args: &[String]) -> Result<(String, Vec<(String, String)>), Error> {
+    match args.split_first() {
+        Some((invoice, options)) if !invoice.starts_with("--") => {
+            Ok((invoice.trim().to_lowercase(), self::options(options)?))
+        }
+        _ => Err(Error::InvalidParameter("missing invoice".to_owned())),
+    }
+}
+
+/// Pair up `--name value` arguments.
+fn options(args: &[String]) -> Result<Vec<(String, String)>, Error> {
+    args.chunks(2)
+        .map(|option| match option {
+            [name, value] if name.starts_with("--") => Ok((name.to_owned(), value.to_owned())),
+            _ => Err(Error::InvalidParameter(format!(
+                "expected `--option value`, got `{}`",
+                option.join(" ")
+            ))),
+        })
+        .collect()
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(value).map_err(|_| Error::InvalidParameter(format!("invalid hex `{}`", value)))
+}
+
+fn parse_hex32(value: &str) -> Result<[u8; 32], Error> {
+    let mut array = [0u8; 32];
+    hex::decode_to_slice(value, &mut array)
+        .map_err(|e| Error::InvalidParameter(format!("invalid hex `{}`: {}", value, e)))?;
+    Ok(array)
+}
+
+/// Read a signer for the hex secret key in `path`, or on standard input if `None`.
+fn read_signer(path: Option<String>) -> Result<LocalSigner, Error> {
+    let mut contents = Zeroizing::new(String::new());
+    match path {
+        Some(path) => fs::File::open(path)?.read_to_string(&mut contents)?,
+        None => io::stdin().read_to_string(&mut contents)?,
+    };
+    parse_signer(contents.trim())
+}
+
+/// Parse a hex secret key into a signer, wiping the intermediate buffers.
+fn parse_signer(value: &str) -> Result<LocalSigner, Error> {
+    let invalid = || Error::InvalidParameter("invalid secret key".to_owned());
+    let mut key = Zeroizing::new([0u8; 32]);
+    hex::decode_to_slice(value, &mut *key).map_err(|_| invalid())?;
+    LocalSigner::new(key).map_err(|_| invalid())
+}
+
+fn parse_node_id(value: &str) -> Result<PublicKey, Error> {
+    let bytes = parse_hex(value)?;
+    if bytes.len() != 33 {
+        return Err(Error::InvalidParameter(format!("`{}` is not 33 bytes", value)));
+    }
+    let mut compressed = [0u8; 33];
+    compressed.copy_from_slice(&bytes);
+    PublicKey::parse_compressed(&compressed)
+        .map_err(|_| Error::InvalidParameter(format!("invalid node id `{}`", value)))
+}
+
+fn parse_number(name: &str, value: &str) -> Result<u64, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::InvalidParameter(format!("invalid number `{}` for {}", value, name)))
+}
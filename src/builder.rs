@@ -0,0 +1,425 @@
+//! Step by step construction of payment requests.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use amount::AmountEncoding;
+use consts::MAX_DESCRIPTION_LENGTH;
+use currency::Currency;
+use expiry;
+use features::Features;
+use msat::MilliSatoshi;
+use payment_request::PaymentRequest;
+use secp256k1::{PublicKey, SecretKey};
+use signer::Bolt11Signer;
+use tag::{ExtraHop, Tag};
+#[cfg(feature = "chrono")]
+use timestamp::Timestamp;
+use types::Error;
+use utils::sha256;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "chrono")]
+use core::convert::TryFrom;
+
+/// Builder of signed payment requests, an alternative to `PaymentRequest::new` that doesn't
+/// require spelling out every optional field.
+///
+/// # Examples
+/// ```
+/// use bolt11::builder::PaymentRequestBuilder;
+/// use bolt11::currency::Currency;
+///
+/// let builder = PaymentRequestBuilder::new(Currency::Bitcoin)
+///     .amount_btc_str("0.00025")
+///     .unwrap()
+///     .payment_hash(vec![0u8; 32])
+///     .description("1 cup coffee".to_owned())
+///     .expiry_seconds(60);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PaymentRequestBuilder {
+    currency: Currency,
+    amount: Option<MilliSatoshi>,
+    payment_hash: Option<Vec<u8>>,
+    payment_secret: Option<[u8; 32]>,
+    description: Option<String>,
+    description_hash: Option<Vec<u8>>,
+    fallback_addresses: Vec<String>,
+    expiry_seconds: Option<u64>,
+    extra_hops: Vec<ExtraHop>,
+    timestamp: Option<u64>,
+    min_final_cltv_expiry: Option<u64>,
+    features: Option<Features>,
+}
+
+impl PaymentRequestBuilder {
+    /// Start building a payment request for `currency`.
+    pub fn new(currency: Currency) -> PaymentRequestBuilder {
+        PaymentRequestBuilder {
+            currency,
+            amount: None,
+            payment_hash: None,
+            payment_secret: None,
+            description: None,
+            description_hash: None,
+            fallback_addresses: Vec::new(),
+            expiry_seconds: None,
+            extra_hops: Vec::new(),
+            timestamp: None,
+            min_final_cltv_expiry: None,
+            features: None,
+        }
+    }
+
+    /// Amount to pay.
+    pub fn amount(mut self, amount: MilliSatoshi) -> PaymentRequestBuilder {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// Amount to pay as a decimal string of bitcoins, e.g. `"0.00025"`. Fails if the amount
+    /// isn't a whole number of millisatoshis.
+    pub fn amount_btc_str(mut self, amount: &str) -> Result<PaymentRequestBuilder, Error> {
+        self.amount = Some(MilliSatoshi::from_btc_str(amount)?);
+        Ok(self)
+    }
+
+    /// SHA256 payment hash.
+    pub fn payment_hash(mut self, payment_hash: Vec<u8>) -> PaymentRequestBuilder {
+        self.payment_hash = Some(payment_hash);
+        self
+    }
+
+    /// Payment secret.
+    pub fn payment_secret(mut self, payment_secret: [u8; 32]) -> PaymentRequestBuilder {
+        self.payment_secret = Some(payment_secret);
+        self
+    }
+
+    /// Short description of purpose of payment, at most `MAX_DESCRIPTION_LENGTH` bytes.
+    /// Replaces any description hash.
+    pub fn description(mut self, description: String) -> PaymentRequestBuilder {
+        self.description = Some(description);
+        self.description_hash = None;
+        self
+    }
+
+    /// SHA256 hash of the description of purpose of payment. Replaces any description.
+    pub fn description_hash(mut self, hash: Vec<u8>) -> PaymentRequestBuilder {
+        self.description_hash = Some(hash);
+        self.description = None;
+        self
+    }
+
+    /// SHA256 hash of `description`, the full description of purpose of payment given to the
+    /// payer out of band. Replaces any description.
+    pub fn description_hash_of(self, description: &str) -> PaymentRequestBuilder {
+        self.description_hash(sha256(description.as_bytes()).to_vec())
+    }
+
+    /// Description of purpose of payment, replaced by its hash if it is longer than
+    /// `MAX_DESCRIPTION_LENGTH` bytes.
+    pub fn description_auto(self, description: String) -> PaymentRequestBuilder {
+        if description.len() > MAX_DESCRIPTION_LENGTH {
+            self.description_hash_of(&description)
+        } else {
+            self.description(description)
+        }
+    }
+
+    /// Fallback on chain address, may be called several times. Addresses are kept in order.
+    pub fn fallback_address(mut self, address: String) -> PaymentRequestBuilder {
+        self.fallback_addresses.push(address);
+        self
+    }
+
+    /// Expiry time of the payment request.
+    pub fn expiry_seconds(mut self, seconds: u64) -> PaymentRequestBuilder {
+        self.expiry_seconds = Some(seconds);
+        self
+    }
+
+    /// Extra routing information, may be called several times.
+    pub fn extra_hops(mut self, extra_hops: Vec<ExtraHop>) -> PaymentRequestBuilder {
+        self.extra_hops.extend(extra_hops);
+        self
+    }
+
+    /// Request timestamp, the current time if not set.
+    pub fn timestamp(mut self, timestamp: u64) -> PaymentRequestBuilder {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Request timestamp as a date, see `timestamp`. Fails if the date doesn't fit in the 35 bits
+    /// of a timestamp, e.g. if it is before the epoch.
+    #[cfg(feature = "chrono")]
+    pub fn created_at(
+        mut self,
+        created_at: DateTime<Utc>,
+    ) -> Result<PaymentRequestBuilder, Error> {
+        let timestamp = u64::try_from(created_at.timestamp())
+            .ok()
+            .filter(|timestamp| *timestamp <= Timestamp::MAX)
+            .ok_or_else(|| {
+                Error::InvalidParameter(format!("invalid payment request date {}", created_at))
+            })?;
+        self.timestamp = Some(timestamp);
+        Ok(self)
+    }
+
+    /// min_final_cltv_expiry.
+    pub fn min_final_cltv_expiry(mut self, blocks: u64) -> PaymentRequestBuilder {
+        self.min_final_cltv_expiry = Some(blocks);
+        self
+    }
+
+    /// Feature bits.
+    pub fn features(mut self, features: Features) -> PaymentRequestBuilder {
+        self.features = Some(features);
+        self
+    }
+
+    /// Build the payment request and sign it with `secret_key`.
+    /// # Params
+    /// `secret_key` Secret key of the node issuing the payment request.
+    pub fn build(self, secret_key: &SecretKey) -> Result<PaymentRequest, Error> {
+        self.build_with(PublicKey::from_secret_key(secret_key), secret_key)
+    }
+
+    /// Build the payment request and sign it with `signer`, e.g. a `LocalSigner` or an HSM, so
+    /// the node key never has to be handed to the builder.
+    /// # Params
+    /// `node_id` Id of the node issuing the payment request.
+    /// `signer` The signer holding the key of `node_id`.
+    pub fn build_with<S: Bolt11Signer + ?Sized>(
+        self,
+        node_id: PublicKey,
+        signer: &S,
+    ) -> Result<PaymentRequest, Error> {
+        let payment_hash = self.payment_hash
+            .ok_or(Error::InvalidParameter("missing payment hash".to_owned()))?;
+        let description = match (self.description, self.description_hash.is_some()) {
+            (Some(ref description), _) if description.len() > MAX_DESCRIPTION_LENGTH => {
+                return Err(Error::InvalidParameter(format!(
+                    "description exceeds {} bytes, use a description hash",
+                    MAX_DESCRIPTION_LENGTH
+                )))
+            }
+            (Some(description), _) => description,
+            (None, true) => String::new(),
+            (None, false) => {
+                return Err(Error::InvalidParameter("missing description".to_owned()))
+            }
+        };
+        let mut tags = vec![
+            Tag::PaymentHash { hash: payment_hash },
+            Tag::Description { description },
+        ];
+        if let Some(seconds) = self.expiry_seconds {
+            tags.push(Tag::Expiry { seconds })
+        }
+        if !self.extra_hops.is_empty() {
+            tags.push(Tag::RoutingInfo {
+                path: self.extra_hops,
+            })
+        }
+        if let Some(blocks) = self.min_final_cltv_expiry {
+            tags.push(Tag::MinFinalCltvExpiry { blocks })
+        }
+        let mut pay_request = PaymentRequest {
+            prefix: self.currency.prefix().to_owned(),
+            amount: self.amount,
+            amount_encoding: AmountEncoding::Shortest,
+            timestamp: match self.timestamp {
+                Some(time) => time,
+                None => expiry::now()?,
+            },
+            node_id,
+            tags,
+            signature: Vec::new(),
+        };
+        for address in self.fallback_addresses {
+            match PaymentRequest::tag_from_fallback_address(address.clone()) {
+                Some(Tag::FallbackAddress { version, hash }) => {
+                    pay_request.add_fallback_address(version, hash)
+                }
+                _ => {
+                    return Err(Error::InvalidParameter(format!(
+                        "invalid fallback address `{}`",
+                        address
+                    )))
+                }
+            }
+        }
+        if let Some(hash) = self.description_hash {
+            pay_request.update_description_hash(hash);
+        }
+        if let Some(secret) = self.payment_secret {
+            pay_request.update_payment_secret(secret);
+        }
+        if let Some(features) = self.features {
+            pay_request.update_features(features);
+        }
+        pay_request.sign_with(signer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use fixtures::{coffee, payment_hash, sec_key};
+    use signer::LocalSigner;
+    use zeroize::Zeroizing;
+
+    #[test]
+    fn build() {
+        let pay_request = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .amount_btc_str("0.00025")
+            .unwrap()
+            .payment_hash(payment_hash())
+            .description("1 cup coffee".to_owned())
+            .expiry_seconds(60)
+            .timestamp(1496314658)
+            .build(&sec_key())
+            .unwrap();
+        assert_eq!(pay_request, coffee(Some(MilliSatoshi::new(25_000_000)), Some(60)));
+        assert!(pay_request.encode().unwrap().starts_with("lnbc250u1"));
+        assert_eq!(pay_request.amount_msat(), Some(MilliSatoshi::new(25_000_000)));
+        let by_msat = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .amount(MilliSatoshi::new(25_000_000))
+            .payment_hash(vec![0u8; 32])
+            .description("1 cup coffee".to_owned())
+            .timestamp(1496314658)
+            .build(&sec_key())
+            .unwrap();
+        assert_eq!(by_msat.amount, pay_request.amount);
+    }
+
+    #[test]
+    fn build_with_signer() {
+        let signer = LocalSigner::new(Zeroizing::new(sec_key().serialize())).unwrap();
+        let pay_request = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .amount(MilliSatoshi::new(25_000_000))
+            .payment_hash(payment_hash())
+            .description("1 cup coffee".to_owned())
+            .expiry_seconds(60)
+            .timestamp(1496314658)
+            .build_with(signer.node_id().clone(), &signer)
+            .unwrap();
+        assert_eq!(pay_request, coffee(Some(MilliSatoshi::new(25_000_000)), Some(60)));
+    }
+
+    #[test]
+    fn build_with_payment_secret() {
+        let pay_request = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .payment_hash(vec![1u8; 32])
+            .payment_secret([2u8; 32])
+            .features(Features::from_bits(vec![8, 14]))
+            .description("coffee".to_owned())
+            .build(&sec_key())
+            .unwrap();
+        assert_eq!(pay_request.payment_secret(), Some([2u8; 32]));
+
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.payment_secret(), Some([2u8; 32]));
+        assert_eq!(decoded.features(), Some(Features::from_bits(vec![8, 14])));
+    }
+
+    #[test]
+    fn build_with_fallback_addresses() {
+        let addresses = vec![
+            "1RustyRX2oai4EYYDpQGWvEL62BBGqN9T".to_owned(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned(),
+            "3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX".to_owned(),
+        ];
+        let builder = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .payment_hash(vec![1u8; 32])
+            .description("coffee".to_owned());
+        let pay_request = addresses
+            .iter()
+            .fold(builder.clone(), |builder, address| {
+                builder.fallback_address(address.to_owned())
+            })
+            .build(&sec_key())
+            .unwrap();
+        assert_eq!(pay_request.fallback_addresses(), addresses);
+
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.fallback_addresses(), addresses);
+        assert_eq!(decoded.fallback_address(), Some(addresses[0].clone()));
+
+        assert!(builder
+            .fallback_address("not an address".to_owned())
+            .build(&sec_key())
+            .is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn build_created_at() {
+        use chrono::TimeZone;
+
+        let created_at = Utc.timestamp_opt(1496314658, 0).unwrap();
+        let pay_request = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .payment_hash(vec![1u8; 32])
+            .description("coffee".to_owned())
+            .created_at(created_at)
+            .unwrap()
+            .build(&sec_key())
+            .unwrap();
+        assert_eq!(pay_request.timestamp, 1496314658);
+        assert_eq!(pay_request.created_at(), Some(created_at));
+
+        let builder = PaymentRequestBuilder::new(Currency::Bitcoin);
+        assert!(builder.clone().created_at(Utc.timestamp_opt(-1, 0).unwrap()).is_err());
+        assert!(builder.created_at(Utc.timestamp_opt(1 << 35, 0).unwrap()).is_err());
+    }
+
+    #[test]
+    fn build_with_long_description() {
+        let builder = PaymentRequestBuilder::new(Currency::Bitcoin).payment_hash(vec![1u8; 32]);
+        let longest = "a".repeat(MAX_DESCRIPTION_LENGTH);
+        let pay_request = builder
+            .clone()
+            .description_auto(longest.clone())
+            .build(&sec_key())
+            .unwrap();
+        assert_eq!(pay_request.description(), Some(longest.clone()));
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+        assert_eq!(decoded, pay_request);
+
+        let too_long = longest + "a";
+        assert!(builder.clone().description(too_long.clone()).build(&sec_key()).is_err());
+        let pay_request = builder
+            .clone()
+            .description_auto(too_long.clone())
+            .build(&sec_key())
+            .unwrap();
+        assert_eq!(
+            pay_request.description_hash(),
+            Some(sha256(too_long.as_bytes()).to_vec())
+        );
+        assert!(pay_request.tag_set().is_ok());
+        assert!(pay_request.verify_description(&too_long));
+        assert!(!pay_request.verify_description("a"));
+
+        let pay_request = builder.description_hash_of(&too_long).build(&sec_key()).unwrap();
+        assert!(pay_request.verify_description(&too_long));
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+        assert!(decoded.verify_description(&too_long));
+        assert!(!decoded.verify_description(""));
+    }
+
+    #[test]
+    fn invalid_amount_or_missing_fields() {
+        let builder = PaymentRequestBuilder::new(Currency::Bitcoin);
+        assert!(builder.clone().amount_btc_str("0.000000000001").is_err());
+        assert!(builder.clone().amount_btc_str("0,1").is_err());
+        assert!(builder.clone().payment_hash(vec![0u8; 32]).build(&sec_key()).is_err());
+        assert!(builder.description("coffee".to_owned()).build(&sec_key()).is_err());
+    }
+}
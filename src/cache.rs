@@ -0,0 +1,189 @@
+//! Memoizing payment request decoder.
+
+use payment_request::PaymentRequest;
+use scan::normalize_scanned;
+use types::Error;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Bounded LRU cache of decoded payment requests, keyed by the normalized input string.
+///
+/// Only successfully decoded (and signature verified) payment requests are cached, so
+/// repeated lookups of the same invoice skip both parsing and signature recovery. Misses are
+/// decoded while holding the cache lock, so concurrent lookups of the same new invoice decode it
+/// once.
+pub struct InvoiceCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+/// Cached entries and their usage order.
+struct CacheState {
+    /// Entries along with the generation of their last use.
+    entries: HashMap<String, (Arc<PaymentRequest>, u64)>,
+    /// Keys by the generation of their last use, from least to most recently used.
+    order: BTreeMap<u64, String>,
+    /// Generation of the next use.
+    generation: u64,
+}
+
+impl InvoiceCache {
+    /// Default number of payment requests kept in the cache.
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    /// Create a cache holding at most `capacity` payment requests.
+    /// # Params
+    /// `capacity` Maximum number of cached entries; 0 disables caching.
+    pub fn new(capacity: usize) -> InvoiceCache {
+        InvoiceCache {
+            capacity,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                order: BTreeMap::new(),
+                generation: 0,
+            }),
+        }
+    }
+
+    /// Decode a payment request, returning the cached value if it was decoded before.
+    /// # Params
+    /// `input` The encoded payment request, as scanned (see `scan::normalize_scanned`).
+    pub fn decode(&self, input: &str) -> Result<Arc<PaymentRequest>, Error> {
+        let key = normalize_scanned(input)?;
+        let mut state = self.lock();
+        if let Some(hit) = state.get(&key) {
+            return Ok(hit);
+        }
+        let decoded = Arc::new(PaymentRequest::decode(&key)?);
+        state.insert(key, decoded.clone(), self.capacity);
+        Ok(decoded)
+    }
+
+    /// Number of cached payment requests.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Return true if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the cached payment requests.
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.order.clear();
+    }
+
+    // A panic while holding the lock can't leave the state inconsistent, ignore poisoning.
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for InvoiceCache {
+    fn default() -> InvoiceCache {
+        InvoiceCache::new(InvoiceCache::DEFAULT_CAPACITY)
+    }
+}
+
+impl CacheState {
+    /// Look up an entry marking it as the most recently used.
+    fn get(&mut self, key: &str) -> Option<Arc<PaymentRequest>> {
+        let generation = self.next_generation();
+        let &mut (ref value, ref mut used) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        self.order.insert(generation, key.to_owned());
+        *used = generation;
+        Some(value.clone())
+    }
+
+    /// Insert an entry, evicting the least recently used ones above `capacity`.
+    fn insert(&mut self, key: String, value: Arc<PaymentRequest>, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        let generation = self.next_generation();
+        if let Some((_, used)) = self.entries.insert(key.clone(), (value, generation)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(generation, key);
+        while self.entries.len() > capacity {
+            let oldest = match self.order.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+            if let Some(evicted) = self.order.remove(&oldest) {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    fn next_generation(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DONATION: &str = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypq\
+        dpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3\
+        cax9rn449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+    const COFFEE: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfq\
+        ypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vrua\
+        tfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+    #[test]
+    fn cache_hit() {
+        let cache = InvoiceCache::default();
+        let first = cache.decode(DONATION).unwrap();
+        let second = cache.decode(&format!("lightning:{}", DONATION.to_uppercase())).unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn cache_eviction() {
+        let cache = InvoiceCache::new(1);
+        let first = cache.decode(DONATION).unwrap();
+        cache.decode(COFFEE).unwrap();
+        assert_eq!(cache.len(), 1);
+
+        let again = cache.decode(DONATION).unwrap();
+        assert!(!Arc::ptr_eq(&first, &again));
+        assert_eq!(*first, *again);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.lock().order.is_empty());
+        assert!(InvoiceCache::new(0).decode(COFFEE).is_ok());
+    }
+
+    #[test]
+    fn least_recently_used() {
+        let cache = InvoiceCache::new(2);
+        let donation = cache.decode(DONATION).unwrap();
+        cache.decode(COFFEE).unwrap();
+        // the hit makes the coffee payment request the least recently used
+        assert!(Arc::ptr_eq(&donation, &cache.decode(DONATION).unwrap()));
+        cache.decode(&format!("lightning:{}", COFFEE)).unwrap();
+        assert!(Arc::ptr_eq(&donation, &cache.decode(DONATION).unwrap()));
+
+        let state = cache.lock();
+        assert_eq!(state.entries.len(), 2);
+        assert_eq!(state.order.len(), 2);
+        assert_eq!(state.order.values().last().map(String::as_str), Some(DONATION));
+    }
+
+    #[test]
+    fn cache_errors() {
+        let cache = InvoiceCache::default();
+        assert!(cache.decode("lnbc1invalid").is_err());
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+//! Route hints built from the channel listings of Lightning node implementations.
+//!
+//! Invoice issuers with private channels typically want to hint some of them; these
+//! constructors pick the requested channels out of the JSON output of their node and turn the
+//! policy of each peer into an `ExtraHop`.
+
+use msat::MilliSatoshi;
+use scid::ShortChannelId;
+use secp256k1::PublicKey;
+use serde_json::{self, Value};
+use std::convert::TryFrom;
+use tag::ExtraHop;
+use types::Error;
+use utils::{from_hex, to_hex};
+
+impl ExtraHop {
+    /// Build route hints from the output of c-lightning's `listchannels` for the channels
+    /// `scids`, which lead to `node_id`. Hints are returned in the order of `scids`.
+    ///
+    /// # Params
+    /// `json` Output of `lightning-cli listchannels`.
+    /// `node_id` Id of the node issuing the payment request.
+    /// `scids` Short channel ids of the hinted channels.
+    pub fn from_cln_listchannels(
+        json: &str,
+        node_id: &PublicKey,
+        scids: &[ShortChannelId],
+    ) -> Result<Vec<ExtraHop>, Error> {
+        let channels = parse_entries(json, "channels")?;
+        let node_id = to_hex(&node_id.serialize_compressed());
+        scids
+            .iter()
+            .map(|&scid| {
+                let channel = channels
+                    .iter()
+                    .find(|c| {
+                        c.get("destination").and_then(Value::as_str) == Some(&node_id)
+                            && c.get("short_channel_id")
+                                .and_then(Value::as_str)
+                                .and_then(|id| id.parse::<ShortChannelId>().ok())
+                                == Some(scid)
+                    })
+                    .ok_or_else(|| channel_not_found(scid))?;
+                Ok(ExtraHop {
+                    pub_key: pub_key_field(channel, "source")?,
+                    short_channel_id: scid,
+                    fee_base_msat: MilliSatoshi::from(int_field(
+                        channel,
+                        "base_fee_millisatoshi",
+                    )?),
+                    fee_proportional_millionths: narrow_int_field(channel, "fee_per_millionth")?,
+                    cltv_expiry_delta: narrow_int_field(channel, "delay")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Build route hints from lnd channel edges, as returned by `lncli getchaninfo` or in the
+    /// `edges` of `lncli describegraph`, for the channels `chan_ids`, which lead to `node_id`.
+    /// Hints are returned in the order of `chan_ids`.
+    ///
+    /// `lncli listchannels` doesn't include the routing policy of the peers, so its output
+    /// can't be used here.
+    ///
+    /// # Params
+    /// `json` A single edge, an array of edges or an object with an `edges` array.
+    /// `node_id` Id of the node issuing the payment request.
+    /// `chan_ids` Channel ids of the hinted channels.
+    pub fn from_lnd_channel_edges(
+        json: &str,
+        node_id: &PublicKey,
+        chan_ids: &[ShortChannelId],
+    ) -> Result<Vec<ExtraHop>, Error> {
+        let edges = parse_entries(json, "edges")?;
+        let node_id = to_hex(&node_id.serialize_compressed());
+        chan_ids
+            .iter()
+            .map(|&chan_id| {
+                let edge = edges
+                    .iter()
+                    .find(|e| int_field(e, "channel_id").ok() == Some(chan_id.as_u64()))
+                    .ok_or_else(|| channel_not_found(chan_id))?;
+                // the hop is the peer, forwarding with its own policy
+                let (peer, policy) = if edge.get("node2_pub").and_then(Value::as_str)
+                    == Some(&node_id)
+                {
+                    ("node1_pub", "node1_policy")
+                } else if edge.get("node1_pub").and_then(Value::as_str) == Some(&node_id) {
+                    ("node2_pub", "node2_policy")
+                } else {
+                    return Err(channel_not_found(chan_id));
+                };
+                let policy = edge.get(policy)
+                    .filter(|p| !p.is_null())
+                    .ok_or_else(|| missing_field(policy))?;
+                Ok(ExtraHop {
+                    pub_key: pub_key_field(edge, peer)?,
+                    short_channel_id: chan_id,
+                    fee_base_msat: MilliSatoshi::from(int_field(policy, "fee_base_msat")?),
+                    fee_proportional_millionths: narrow_int_field(policy, "fee_rate_milli_msat")?,
+                    cltv_expiry_delta: narrow_int_field(policy, "time_lock_delta")?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parse the JSON output of a node: either a single entry, an array of entries or an object
+/// listing them under `key`.
+fn parse_entries(json: &str, key: &str) -> Result<Vec<Value>, Error> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| Error::InvalidValue(e.to_string()))?;
+    match value.get(key).and_then(Value::as_array) {
+        Some(entries) => Ok(entries.to_owned()),
+        None => match value {
+            Value::Array(entries) => Ok(entries),
+            entry => Ok(vec![entry]),
+        },
+    }
+}
+
+/// Read an integer field; lnd encodes 64-bit integers as strings.
+fn int_field(entry: &Value, field: &str) -> Result<u64, Error> {
+    entry
+        .get(field)
+        .and_then(|v| {
+            v.as_u64()
+                .or_else(|| v.as_str().and_then(|s| s.parse::<u64>().ok()))
+        })
+        .ok_or_else(|| missing_field(field))
+}
+
+/// Read an integer field that must fit in `T`, e.g. a 16-bit cltv delta. Out of range values
+/// are rejected rather than truncated into a hint with other fees or delays.
+fn narrow_int_field<T: TryFrom<u64>>(entry: &Value, field: &str) -> Result<T, Error> {
+    let value = int_field(entry, field)?;
+    T::try_from(value)
+        .map_err(|_| Error::InvalidValue(format!("`{}` field out of range: {}", field, value)))
+}
+
+/// Read a hex encoded compressed public key field.
+fn pub_key_field(entry: &Value, field: &str) -> Result<PublicKey, Error> {
+    let pub_key = entry
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| missing_field(field))
+        .and_then(|hex| from_hex(hex).map_err(Error::HexErr))?;
+    ExtraHop::parse_pub_key(&pub_key).map_err(|_| {
+        Error::InvalidValue(format!("`{}` is not a compressed public key", field))
+    })
+}
+
+fn missing_field(field: &str) -> Error {
+    Error::InvalidValue(format!("missing or invalid `{}` field", field))
+}
+
+fn channel_not_found(scid: ShortChannelId) -> Error {
+    Error::InvalidValue(format!("channel {} leading to the node not found", scid))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use secp256k1::SecretKey;
+
+    const PEER: &str = "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255";
+
+    fn node_id() -> PublicKey {
+        PublicKey::from_secret_key(&SecretKey::parse(&[1u8; 32]).unwrap())
+    }
+
+    #[test]
+    fn cln_listchannels() {
+        let node_id = to_hex(&self::node_id().serialize_compressed());
+        let json = format!(
+            r#"{{"channels": [
+                {{"source": "{node}", "destination": "{peer}", "short_channel_id": "103x1x0",
+                  "base_fee_millisatoshi": 5, "fee_per_millionth": 50, "delay": 40}},
+                {{"source": "{peer}", "destination": "{node}", "short_channel_id": "103x1x0",
+                  "base_fee_millisatoshi": 1, "fee_per_millionth": 20, "delay": 3}}
+            ]}}"#,
+            node = node_id,
+            peer = PEER
+        );
+
+        let scid = ShortChannelId::from(113249697726464);
+        let hops = ExtraHop::from_cln_listchannels(&json, &self::node_id(), &[scid]).unwrap();
+        assert_eq!(
+            hops,
+            vec![ExtraHop {
+                pub_key: ExtraHop::parse_pub_key(&from_hex(PEER).unwrap()).unwrap(),
+                short_channel_id: scid,
+                fee_base_msat: MilliSatoshi::from(1),
+                fee_proportional_millionths: 20,
+                cltv_expiry_delta: 3,
+            }]
+        );
+        let unknown = ShortChannelId::from(1);
+        assert!(ExtraHop::from_cln_listchannels(&json, &self::node_id(), &[unknown]).is_err());
+    }
+
+    #[test]
+    fn lnd_channel_edges() {
+        let node_id = to_hex(&self::node_id().serialize_compressed());
+        let json = format!(
+            r#"{{"edges": [{{"channel_id": "113249697726464", "node1_pub": "{peer}",
+                "node2_pub": "{node}",
+                "node1_policy": {{"time_lock_delta": 3, "fee_base_msat": "1",
+                                  "fee_rate_milli_msat": "20"}},
+                "node2_policy": {{"time_lock_delta": 40, "fee_base_msat": "5",
+                                  "fee_rate_milli_msat": "50"}}}}]}}"#,
+            node = node_id,
+            peer = PEER
+        );
+
+        let scid = ShortChannelId::from(113249697726464);
+        let hops = ExtraHop::from_lnd_channel_edges(&json, &self::node_id(), &[scid]).unwrap();
+        assert_eq!(hops[0].pub_key_bytes(), from_hex(PEER).unwrap());
+        assert_eq!(hops[0].short_channel_id, scid);
+        assert_eq!(hops[0].fee_base_msat, MilliSatoshi::from(1));
+        assert_eq!(hops[0].fee_proportional_millionths, 20);
+        assert_eq!(hops[0].cltv_expiry_delta, 3);
+        let unknown = [ShortChannelId::from(1)];
+        assert!(ExtraHop::from_lnd_channel_edges("[]", &self::node_id(), &unknown).is_err());
+        assert!(ExtraHop::from_lnd_channel_edges("{", &self::node_id(), &unknown).is_err());
+    }
+
+    #[test]
+    fn out_of_range_policy() {
+        let node_id = to_hex(&self::node_id().serialize_compressed());
+        let scid = ShortChannelId::from(113249697726464);
+        for &(fee_rate, delay) in &[(20u64, 65536u64), (1 << 32, 3)] {
+            let json = format!(
+                r#"[{{"source": "{peer}", "destination": "{node}", "short_channel_id": "103x1x0",
+                     "base_fee_millisatoshi": 1, "fee_per_millionth": {fee_rate},
+                     "delay": {delay}}}]"#,
+                node = node_id,
+                peer = PEER,
+                fee_rate = fee_rate,
+                delay = delay
+            );
+            assert!(ExtraHop::from_cln_listchannels(&json, &self::node_id(), &[scid]).is_err());
+        }
+
+        let json = format!(
+            r#"{{"channel_id": "113249697726464", "node1_pub": "{peer}", "node2_pub": "{node}",
+                "node1_policy": {{"time_lock_delta": 65539, "fee_base_msat": "1",
+                                  "fee_rate_milli_msat": "20"}}}}"#,
+            node = node_id,
+            peer = PEER
+        );
+        assert!(ExtraHop::from_lnd_channel_edges(&json, &self::node_id(), &[scid]).is_err());
+    }
+}
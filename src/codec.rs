@@ -0,0 +1,451 @@
+//! Reusable payment request encoder and decoder.
+//!
+//! `PaymentRequest::decode` and `PaymentRequest::encode` allocate fresh working buffers on
+//! every call. Services processing many payment requests can keep an `Encoder` or a `Decoder`
+//! around instead, whose buffers are reused from one call to the next.
+//!
+//! `DecodeOptions` chooses how strictly decoded payment requests are checked, which currencies
+//! are accepted and bounds how much untrusted input can make the decoder parse and allocate.
+//!
+//! With the `rayon` feature, `par_decode` decodes large batches of payment requests in
+//! parallel, one `Decoder` per worker thread.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use consts::{TAG_DESCRIPTION, TAG_DESCRIPTION_HASH, TAG_PAYMENT_HASH};
+use currency::CurrencyRegistry;
+use features::Feature;
+use payment_request::PaymentRequest;
+use signature::is_low_s;
+use tag::{Tag, TagReader};
+use types::Error;
+use u5::U5;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Tags rejected when duplicated by strict decoding.
+const STRICT_SINGLE_TAGS: &[char] = &[TAG_PAYMENT_HASH, TAG_DESCRIPTION, TAG_DESCRIPTION_HASH];
+
+/// Default maximum number of characters of a decoded payment request. Large enough for any
+/// payment request that fits in a QR code, small enough that untrusted input can't make the
+/// decoder allocate arbitrarily large buffers.
+pub const DEFAULT_MAX_INPUT_LENGTH: usize = 7089;
+/// Default maximum number of tagged fields of a decoded payment request.
+pub const DEFAULT_MAX_TAGS: usize = 128;
+/// Default maximum number of route hint hops of a decoded payment request, over all of its
+/// `'r'` fields.
+pub const DEFAULT_MAX_ROUTE_HOPS: usize = 64;
+
+/// Options of payment request decoding. The default is lenient decoding of inputs up to
+/// `DEFAULT_MAX_INPUT_LENGTH` characters, `DEFAULT_MAX_TAGS` tagged fields and
+/// `DEFAULT_MAX_ROUTE_HOPS` route hint hops, of the built in currencies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Reject inputs longer than this many characters.
+    pub max_length: usize,
+    /// Reject payment requests with more tagged fields than this. Parsing stops as soon as the
+    /// limit is exceeded.
+    pub max_tags: usize,
+    /// Reject payment requests with more route hint hops than this, over all `'r'` fields.
+    pub max_route_hops: usize,
+    /// Reject payment requests with duplicated `'p'`, `'d'` or `'h'` fields, without a payment
+    /// hash, with both or none of `'d'` and `'h'`, requiring unknown features, advertising
+    /// `payment_secret` without an `'s'` field or with a high S signature. Lenient decoding
+    /// accepts them, which is handy to inspect payment requests produced by other
+    /// implementations.
+    pub strict: bool,
+    /// Currencies whose payment requests are accepted.
+    pub currencies: CurrencyRegistry,
+}
+
+impl DecodeOptions {
+    /// Strict decoding with the default limits.
+    pub fn strict() -> DecodeOptions {
+        DecodeOptions {
+            strict: true,
+            ..DecodeOptions::lenient()
+        }
+    }
+
+    /// Lenient decoding with the default limits.
+    pub fn lenient() -> DecodeOptions {
+        DecodeOptions {
+            max_length: DEFAULT_MAX_INPUT_LENGTH,
+            max_tags: DEFAULT_MAX_TAGS,
+            max_route_hops: DEFAULT_MAX_ROUTE_HOPS,
+            strict: false,
+            currencies: CurrencyRegistry::new(),
+        }
+    }
+
+    /// Lenient decoding without any limit, only meant for trusted input.
+    pub fn unlimited() -> DecodeOptions {
+        DecodeOptions {
+            max_length: usize::max_value(),
+            max_tags: usize::max_value(),
+            max_route_hops: usize::max_value(),
+            strict: false,
+            currencies: CurrencyRegistry::new(),
+        }
+    }
+
+    /// Check the length of an encoded payment request before anything is allocated for it.
+    pub(crate) fn check_length(&self, input: &str) -> Result<(), Error> {
+        if input.len() > self.max_length {
+            return Err(Error::InvalidLength(format!(
+                "payment request exceeds the maximum length of {} characters",
+                self.max_length
+            )));
+        }
+        Ok(())
+    }
+
+    /// Parse the tagged fields starting `offset` words into the data part, failing with
+    /// `Error::InvalidLength` as soon as there are too many fields or route hint hops.
+    pub(crate) fn parse_tags(&self, input: &[U5], offset: usize) -> Result<Vec<Tag>, Error> {
+        let mut tags = Vec::new();
+        let mut hops = 0usize;
+        for tag in TagReader::at(input, offset) {
+            let tag = tag?;
+            if tags.len() == self.max_tags {
+                return Err(Error::InvalidLength(format!(
+                    "payment request has more than {} tagged fields",
+                    self.max_tags
+                )));
+            }
+            if let Tag::RoutingInfo { ref path } = tag {
+                hops = hops.saturating_add(path.len());
+                if hops > self.max_route_hops {
+                    return Err(Error::InvalidLength(format!(
+                        "payment request has more than {} route hint hops",
+                        self.max_route_hops
+                    )));
+                }
+            }
+            tags.push(tag);
+        }
+        Ok(tags)
+    }
+
+    /// Check the tags of a decoded payment request.
+    pub(crate) fn check(&self, tags: &[Tag]) -> Result<(), Error> {
+        if !self.strict {
+            return Ok(());
+        }
+        // skipped fields, e.g. a `'p'` field of the wrong length, are unknown tags
+        let count = |c: char| {
+            tags.iter()
+                .filter(|t| match **t {
+                    Tag::UnknownTag { .. } => false,
+                    ref t => t.tag_char() == c,
+                })
+                .count()
+        };
+        if let Some(c) = STRICT_SINGLE_TAGS.iter().find(|c| count(**c) > 1) {
+            return Err(Error::InvalidValue(format!("duplicate `{}` field", c)));
+        }
+        if count(TAG_PAYMENT_HASH) == 0 {
+            return Err(Error::InvalidValue("missing payment hash".to_owned()));
+        }
+        if count(TAG_DESCRIPTION) + count(TAG_DESCRIPTION_HASH) != 1 {
+            return Err(Error::ConflictingDescription);
+        }
+        for tag in tags {
+            if let Tag::Features { ref features } = *tag {
+                // it's ok to be odd: only unknown even bits are fatal
+                if let Some(bit) = features.unknown_required_bits().first() {
+                    return Err(Error::UnknownRequiredFeature(*bit));
+                }
+                if features.supports(Feature::PaymentSecret)
+                    && !tags.iter().any(|t| matches!(*t, Tag::PaymentSecret { .. }))
+                {
+                    return Err(Error::MissingPaymentSecret);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the signature of a decoded payment request.
+    pub(crate) fn check_signature(&self, signature: &[u8]) -> Result<(), Error> {
+        if self.strict && !is_low_s(signature) {
+            Err(Error::HighS)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> DecodeOptions {
+        DecodeOptions::lenient()
+    }
+}
+
+/// Payment request decoder reusing its working buffers.
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    options: DecodeOptions,
+    hrp: String,
+    data: Vec<U5>,
+}
+
+impl Decoder {
+    /// Create a decoder accepting inputs up to `DEFAULT_MAX_INPUT_LENGTH` characters.
+    pub fn new() -> Decoder {
+        Decoder::with_options(DecodeOptions::default())
+    }
+
+    /// Create a decoder using `options`.
+    pub fn with_options(options: DecodeOptions) -> Decoder {
+        Decoder {
+            options,
+            hrp: String::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Decode a payment request, see `PaymentRequest::decode_with_options`.
+    /// # Params
+    /// `input` The encoded payment request.
+    pub fn decode(&mut self, input: &str) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode_buffers(input, &self.options, &mut self.hrp, &mut self.data)
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
+
+/// Payment request encoder reusing its working buffers.
+#[derive(Debug, Clone, Default)]
+pub struct Encoder {
+    stream: Vec<U5>,
+    output: String,
+}
+
+impl Encoder {
+    /// Create an encoder.
+    pub fn new() -> Encoder {
+        Encoder::default()
+    }
+
+    /// Encode a payment request, see `PaymentRequest::encode`. The returned string is borrowed
+    /// from the encoder and overwritten by the next call.
+    /// # Params
+    /// `payment_request` The payment request to encode.
+    pub fn encode(&mut self, payment_request: &PaymentRequest) -> Result<&str, Error> {
+        payment_request.encode_buffers(&mut self.stream, &mut self.output)?;
+        Ok(&self.output)
+    }
+}
+
+/// Decode `inputs` in parallel, returning one result per input in order.
+#[cfg(feature = "rayon")]
+pub fn par_decode(inputs: &[&str]) -> Vec<Result<PaymentRequest, Error>> {
+    par_decode_with_options(inputs, &DecodeOptions::default())
+}
+
+/// Decode `inputs` in parallel using `options`, returning one result per input in order.
+#[cfg(feature = "rayon")]
+pub fn par_decode_with_options(
+    inputs: &[&str],
+    options: &DecodeOptions,
+) -> Vec<Result<PaymentRequest, Error>> {
+    inputs
+        .par_iter()
+        .map_init(
+            || Decoder::with_options(options.clone()),
+            |decoder, input| decoder.decode(input),
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use currency::CustomCurrency;
+    use features::Features;
+    use tag::ExtraHop;
+
+    const DONATION: &str = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypq\
+        dpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3\
+        cax9rn449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+    const COFFEE: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfq\
+        ypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vrua\
+        tfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+    #[test]
+    fn reuse() {
+        let mut decoder = Decoder::new();
+        let mut encoder = Encoder::new();
+        for input in vec![COFFEE, DONATION, COFFEE] {
+            let decoded = decoder.decode(input).unwrap();
+            assert_eq!(decoded, PaymentRequest::decode(input).unwrap());
+            assert_eq!(encoder.encode(&decoded).unwrap(), input);
+        }
+        assert!(decoder.decode("lnbc1invalid").is_err());
+        assert!(decoder.decode(DONATION).is_ok());
+        let short = DecodeOptions {
+            max_length: 10,
+            ..DecodeOptions::default()
+        };
+        assert!(Decoder::with_options(short).decode(DONATION).is_err());
+        assert!(Decoder::with_options(DecodeOptions::strict()).decode(COFFEE).is_ok());
+    }
+
+    #[test]
+    fn strict() {
+        let hash = Tag::PaymentHash { hash: vec![0; 32] };
+        let description = Tag::Description {
+            description: "coffee".to_owned(),
+        };
+        let skipped_hash = Tag::UnknownTag {
+            tag: U5::new(1).unwrap(),
+            bytes: vec![U5::ZERO; 10],
+        };
+        let mut features = Features::new();
+        features.set(100);
+        let features = Tag::Features { features };
+        let strict = DecodeOptions::strict();
+        let lenient = DecodeOptions::lenient();
+
+        let description_hash = Tag::DescriptionHash { hash: vec![1; 32] };
+        let valid = vec![hash.clone(), description.clone(), skipped_hash.clone()];
+        assert!(strict.check(&valid).is_ok());
+        assert!(strict.check(&[hash.clone(), description_hash.clone()]).is_ok());
+        for tags in vec![
+            vec![hash.clone(), description.clone(), description_hash],
+            vec![hash.clone()],
+        ] {
+            match strict.check(&tags) {
+                Err(Error::ConflictingDescription) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+            assert!(lenient.check(&tags).is_ok());
+        }
+        let invalid = vec![
+            vec![hash.clone(), hash.clone(), description.clone()],
+            vec![hash.clone(), description.clone(), description.clone()],
+            vec![skipped_hash, description.clone()],
+        ];
+        for tags in invalid {
+            assert!(strict.check(&tags).is_err());
+            assert!(lenient.check(&tags).is_ok());
+        }
+
+        match strict.check(&[hash.clone(), description.clone(), features]) {
+            Err(Error::UnknownRequiredFeature(100)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut features = Features::new();
+        features.set(101);
+        features.set_optional(Feature::PaymentSecret);
+        let features = Tag::Features { features };
+        let secret = Tag::PaymentSecret { secret: [2; 32] };
+        let tags = vec![hash.clone(), description.clone(), features.clone()];
+        match strict.check(&tags) {
+            Err(Error::MissingPaymentSecret) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(lenient.check(&tags).is_ok());
+        assert!(strict.check(&[hash, description, features, secret]).is_ok());
+    }
+
+    #[test]
+    fn strict_payment_metadata() {
+        // BOLT11 test vector requiring var_onion_optin, payment_secret and
+        // option_payment_metadata
+        let encoded = "lnbc10m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdp9w\
+            pshjmt9de6zqmt9w3skgct5vysxjmnnd9jx2mq8q8a04uqsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg\
+            3zyg3zyg3zygs9q2gqqqqqqsgq7hf8he7ecf7n4ffphs6awl9t6676rrclv9ckg3d3ncn7fct63p6s365duk5wrk\
+            202cfy3aj5xnnp5gs3vrdvruverwwq7yzhkf5a3xqpd05wjc";
+        let pay_request =
+            PaymentRequest::decode_with_options(encoded, &DecodeOptions::strict()).unwrap();
+        let features = pay_request.features().unwrap();
+        assert!(features.requires(Feature::PaymentMetadata));
+        assert!(features.unknown_required_bits().is_empty());
+        assert_eq!(pay_request.metadata(), Some(vec![0x01, 0xfa, 0xfa, 0xf0]));
+    }
+
+    #[test]
+    fn registered_currency() {
+        let mut pay_request = PaymentRequest::decode(COFFEE).unwrap();
+        pay_request.prefix = "lnxmy".to_owned();
+        let encoded = pay_request.encode().unwrap();
+        assert!(PaymentRequest::decode(&encoded).is_err());
+
+        let mut options = DecodeOptions::default();
+        let currency = options
+            .currencies
+            .register(CustomCurrency {
+                prefix: "lnxmy",
+                base58_versions: (50, 55),
+                segwit_network: None,
+            })
+            .unwrap();
+        let decoded = PaymentRequest::decode_with_options(&encoded, &options).unwrap();
+        assert_eq!(decoded.prefix, "lnxmy");
+        assert!(decoded.is_for_network(currency));
+        assert!(Decoder::new().decode(&encoded).is_err());
+        assert!(Decoder::with_options(options).decode(&encoded).is_ok());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_decode() {
+        let inputs = vec![COFFEE, "lnbc1invalid", DONATION, COFFEE];
+        let decoded = super::par_decode(&inputs);
+        assert_eq!(decoded.len(), inputs.len());
+        for (input, result) in inputs.iter().zip(decoded) {
+            match PaymentRequest::decode(input) {
+                Ok(expected) => assert_eq!(result.unwrap(), expected),
+                Err(_) => assert!(result.is_err()),
+            }
+        }
+        let strict = par_decode_with_options(&[COFFEE], &DecodeOptions::strict());
+        assert!(strict[0].is_ok());
+    }
+
+    #[test]
+    fn limits() {
+        let words = PaymentRequest::decode(COFFEE)
+            .unwrap()
+            .tags
+            .iter()
+            .flat_map(|tag| tag.to_vec_u5().unwrap())
+            .collect::<Vec<U5>>();
+        let tags = DecodeOptions::default().parse_tags(&words, 0).unwrap();
+        assert_eq!(tags.len(), 3);
+        let options = DecodeOptions {
+            max_tags: 2,
+            ..DecodeOptions::default()
+        };
+        match options.parse_tags(&words, 0) {
+            Err(Error::InvalidLength(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(Decoder::with_options(options).decode(COFFEE).is_err());
+        assert!(Decoder::with_options(DecodeOptions::unlimited()).decode(COFFEE).is_ok());
+
+        let pub_key = PaymentRequest::decode(COFFEE).unwrap().node_id.serialize_compressed();
+        let hop = ExtraHop::from_raw(&pub_key, 1, 1, 1, 40).unwrap();
+        let routes = Tag::RoutingInfo {
+            path: vec![hop.clone(), hop],
+        };
+        let mut words = routes.to_vec_u5().unwrap();
+        words.extend(routes.to_vec_u5().unwrap());
+        let options = DecodeOptions {
+            max_route_hops: 3,
+            ..DecodeOptions::default()
+        };
+        assert_eq!(options.parse_tags(&words[..words.len() / 2], 0).unwrap().len(), 1);
+        match options.parse_tags(&words, 0) {
+            Err(Error::InvalidLength(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
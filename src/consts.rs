@@ -0,0 +1,67 @@
+//! Tag characters and field sizes defined by BOLT11.
+
+/// Payment hash tag.
+pub const TAG_PAYMENT_HASH: char = 'p';
+/// Payee node id tag.
+pub const TAG_PAYEE_NODE_ID: char = 'n';
+/// Payment secret tag.
+pub const TAG_PAYMENT_SECRET: char = 's';
+/// Description tag.
+pub const TAG_DESCRIPTION: char = 'd';
+/// Description hash tag.
+pub const TAG_DESCRIPTION_HASH: char = 'h';
+/// Fallback on-chain address tag.
+pub const TAG_FALLBACK_ADDRESS: char = 'f';
+/// Extra routing information tag.
+pub const TAG_ROUTING_INFO: char = 'r';
+/// Expiry tag.
+pub const TAG_EXPIRY: char = 'x';
+/// min_final_cltv_expiry tag.
+pub const TAG_MIN_FINAL_CLTV_EXPIRY: char = 'c';
+/// Payment metadata tag.
+pub const TAG_METADATA: char = 'm';
+/// Feature bits tag.
+pub const TAG_FEATURES: char = '9';
+
+/// Number of 5-bit words before the data of a tagged field: the tag and its data length.
+pub const TAG_HEADER_LENGTH: usize = 3;
+/// Number of 5-bit words of a 256-bit hash field (`'p'`, `'h'`, `'s'`).
+pub const HASH_FIELD_LENGTH: usize = 52;
+/// Number of 5-bit words of a 264-bit public key field (`'n'`).
+pub const PUBKEY_FIELD_LENGTH: usize = 53;
+/// Most 5-bit words of data a tagged field can hold, as its length is written in two words.
+pub const MAX_FIELD_LENGTH: usize = 1023;
+/// Number of bytes of the longest description (`'d'`), the most a tagged field can hold: 1023
+/// 5-bit words. Longer descriptions must be replaced by their hash (`'h'`).
+pub const MAX_DESCRIPTION_LENGTH: usize = 639;
+/// Number of bytes of a hop in a `'r'` field: 33 + 8 + 4 + 4 + 2.
+pub const EXTRA_HOP_LENGTH: usize = 51;
+/// Number of hops that fit in a single `'r'` field of at most 1023 5-bit words.
+pub const MAX_ROUTE_HOPS: usize = 12;
+
+/// Number of 5-bit words of the timestamp (35 bits).
+pub const TIMESTAMP_LENGTH: usize = 7;
+/// Number of bytes of the signature, including the recovery id.
+pub const SIGNATURE_BYTES: usize = 65;
+/// Number of 5-bit words of the signature: (65 + 7) * 8 / 5.
+pub const SIGNATURE_LENGTH: usize = 104;
+/// Number of 5-bit words of the bech32 checksum.
+pub const CHECKSUM_LENGTH: usize = 6;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tag::ExtraHop;
+
+    #[test]
+    fn field_lengths() {
+        assert_eq!(HASH_FIELD_LENGTH, (256 + 4) / 5);
+        assert_eq!(PUBKEY_FIELD_LENGTH, (264 + 4) / 5);
+        assert_eq!(SIGNATURE_LENGTH, (SIGNATURE_BYTES * 8 + 4) / 5);
+        assert_eq!(TIMESTAMP_LENGTH, 35 / 5);
+        assert_eq!(MAX_FIELD_LENGTH, 32 * 32 - 1);
+        assert_eq!(MAX_DESCRIPTION_LENGTH, MAX_FIELD_LENGTH * 5 / 8);
+        assert_eq!(ExtraHop::CHUNK_LENGTH, EXTRA_HOP_LENGTH);
+        assert_eq!(MAX_ROUTE_HOPS, MAX_DESCRIPTION_LENGTH / EXTRA_HOP_LENGTH);
+    }
+}
@@ -0,0 +1,282 @@
+//! Currencies payment requests can be issued for, identified by their prefix.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+#[cfg(feature = "std")]
+use bitcoin_bech32::constants::Network;
+use types::Error;
+
+/// Currency (chain) of a payment request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Currency {
+    /// Bitcoin mainnet, `lnbc`.
+    Bitcoin,
+    /// Bitcoin testnet, `lntb`.
+    BitcoinTestnet,
+    /// Bitcoin signet, `lntbs`.
+    BitcoinSignet,
+    /// Bitcoin regtest, `lnbcrt`.
+    BitcoinRegtest,
+    /// Bitcoin simnet, `lnsb` (as used by lnd).
+    BitcoinSimnet,
+    /// Litecoin mainnet, `lnltc`. Only decoded with the `litecoin` feature.
+    Litecoin,
+    /// Litecoin testnet, `lntltc`. Only decoded with the `litecoin` feature.
+    LitecoinTestnet,
+    /// A currency added at runtime with `CurrencyRegistry::register`.
+    Custom(CustomCurrency),
+}
+
+/// Parameters of a currency that isn't built into this crate, e.g. of an altcoin Lightning
+/// deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomCurrency {
+    /// Prefix of the human readable part: `ln` followed by lower case letters.
+    pub prefix: &'static str,
+    /// Base58check versions of pubkey hash and script hash fallback addresses.
+    pub base58_versions: (u8, u8),
+    /// Network used to render segwit fallback addresses, if supported.
+    #[cfg(feature = "std")]
+    pub segwit_network: Option<Network>,
+}
+
+impl Hash for CustomCurrency {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // registered prefixes are unique, this doesn't rely on `Network: Hash`
+        self.prefix.hash(state);
+        self.base58_versions.hash(state);
+    }
+}
+
+/// Every currency decoded without registering it.
+const CURRENCIES: &[Currency] = &[
+    Currency::Bitcoin,
+    Currency::BitcoinTestnet,
+    Currency::BitcoinSignet,
+    Currency::BitcoinRegtest,
+    Currency::BitcoinSimnet,
+    #[cfg(feature = "litecoin")]
+    Currency::Litecoin,
+    #[cfg(feature = "litecoin")]
+    Currency::LitecoinTestnet,
+];
+
+impl Currency {
+    /// Every currency decoded without registering it.
+    pub fn all() -> &'static [Currency] {
+        CURRENCIES
+    }
+
+    /// Return the built in currency using exactly this prefix.
+    pub fn from_prefix(prefix: &str) -> Option<Currency> {
+        CurrencyRegistry::new().from_prefix(prefix)
+    }
+
+    /// Split a human readable part into its built in currency and the (possibly empty) amount
+    /// that follows the prefix. The longest matching prefix wins.
+    pub fn split_hrp(hrp: &str) -> Option<(Currency, &str)> {
+        CurrencyRegistry::new().split_hrp(hrp)
+    }
+
+    /// Prefix of the human readable part, e.g. `lnbc`.
+    pub fn prefix(&self) -> &'static str {
+        match *self {
+            Currency::Bitcoin => "lnbc",
+            Currency::BitcoinTestnet => "lntb",
+            Currency::BitcoinSignet => "lntbs",
+            Currency::BitcoinRegtest => "lnbcrt",
+            Currency::BitcoinSimnet => "lnsb",
+            Currency::Litecoin => "lnltc",
+            Currency::LitecoinTestnet => "lntltc",
+            Currency::Custom(ref custom) => custom.prefix,
+        }
+    }
+
+    /// Base58check versions of pubkey hash and script hash fallback addresses.
+    pub fn base58_versions(&self) -> (u8, u8) {
+        match *self {
+            Currency::Bitcoin => (0, 5),
+            Currency::BitcoinTestnet | Currency::BitcoinSignet | Currency::BitcoinRegtest => {
+                (111, 196)
+            }
+            Currency::BitcoinSimnet => (63, 123),
+            Currency::Litecoin => (48, 50),
+            Currency::LitecoinTestnet => (111, 58),
+            Currency::Custom(ref custom) => custom.base58_versions,
+        }
+    }
+
+    /// Network used to render segwit fallback addresses, if supported.
+    #[cfg(feature = "std")]
+    pub fn segwit_network(&self) -> Option<Network> {
+        match *self {
+            Currency::Bitcoin => Some(Network::Bitcoin),
+            Currency::BitcoinTestnet | Currency::BitcoinSignet => Some(Network::Testnet),
+            Currency::BitcoinRegtest => Some(Network::Regtest),
+            Currency::Custom(ref custom) => custom.segwit_network,
+            _ => None,
+        }
+    }
+}
+
+/// Currencies known to the decoder: the built in ones and those added with `register`, e.g.
+/// of altcoin Lightning deployments. Passed to the decoder with `DecodeOptions::currencies`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CurrencyRegistry {
+    custom: Vec<CustomCurrency>,
+}
+
+impl CurrencyRegistry {
+    /// Create a registry of the built in currencies.
+    pub fn new() -> CurrencyRegistry {
+        CurrencyRegistry::default()
+    }
+
+    /// Make `currency` known to this registry, so its payment requests decode like those of
+    /// built in currencies. Fails if the prefix isn't `ln` followed by lower case letters or is
+    /// already known.
+    ///
+    /// # Params
+    /// `currency` Parameters of the currency to add.
+    pub fn register(&mut self, currency: CustomCurrency) -> Result<Currency, Error> {
+        let prefix = currency.prefix;
+        if !prefix.starts_with("ln")
+            || prefix.len() == 2
+            || !prefix.bytes().all(|b| b.is_ascii_lowercase())
+        {
+            return Err(Error::InvalidValue(format!(
+                "invalid currency prefix `{}`",
+                prefix
+            )));
+        }
+        if self.currencies().any(|c| c.prefix() == prefix) {
+            return Err(Error::InvalidValue(format!(
+                "currency prefix `{}` is already known",
+                prefix
+            )));
+        }
+        self.custom.push(currency);
+        Ok(Currency::Custom(currency))
+    }
+
+    /// Every registered currency, in order of registration.
+    pub fn registered(&self) -> Vec<Currency> {
+        self.custom.iter().cloned().map(Currency::Custom).collect()
+    }
+
+    /// Built in and registered currencies.
+    fn currencies<'a>(&'a self) -> impl Iterator<Item = Currency> + 'a {
+        CURRENCIES
+            .iter()
+            .cloned()
+            .chain(self.custom.iter().cloned().map(Currency::Custom))
+    }
+
+    /// Return the currency using exactly this prefix.
+    pub fn from_prefix(&self, prefix: &str) -> Option<Currency> {
+        self.currencies().find(|c| c.prefix() == prefix)
+    }
+
+    /// Split a human readable part into its currency and the (possibly empty) amount that
+    /// follows the prefix. The longest matching prefix wins.
+    pub fn split_hrp<'h>(&self, hrp: &'h str) -> Option<(Currency, &'h str)> {
+        self.currencies()
+            .filter(|c| hrp.starts_with(c.prefix()))
+            .max_by_key(|c| c.prefix().len())
+            .map(|c| (c, &hrp[c.prefix().len()..]))
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.prefix())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(prefix: &str) -> Result<Currency, Error> {
+        Currency::from_prefix(prefix)
+            .ok_or_else(|| Error::InvalidValue(format!("unknown currency prefix `{}`", prefix)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_hrp() {
+        assert_eq!(Currency::split_hrp("lnbc"), Some((Currency::Bitcoin, "")));
+        assert_eq!(
+            Currency::split_hrp("lntb20m"),
+            Some((Currency::BitcoinTestnet, "20m"))
+        );
+        assert_eq!(
+            Currency::split_hrp("lntbs20m"),
+            Some((Currency::BitcoinSignet, "20m"))
+        );
+        assert_eq!(Currency::split_hrp("lnsb"), Some((Currency::BitcoinSimnet, "")));
+        assert_eq!(
+            Currency::split_hrp("lnbcrt20m"),
+            Some((Currency::BitcoinRegtest, "20m"))
+        );
+        assert_eq!(Currency::split_hrp("lnxx20m"), None);
+        for currency in Currency::all() {
+            assert_eq!(Currency::from_prefix(currency.prefix()), Some(*currency));
+            assert_eq!(currency.to_string().parse::<Currency>().unwrap(), *currency);
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("lnbcrt".parse::<Currency>().unwrap(), Currency::BitcoinRegtest);
+        assert_eq!(Currency::BitcoinSignet.to_string(), "lntbs");
+        assert!("lnbc2500u".parse::<Currency>().is_err());
+    }
+
+    #[test]
+    fn register() {
+        let custom = CustomCurrency {
+            prefix: "lnxreg",
+            base58_versions: (30, 22),
+            segwit_network: None,
+        };
+        let mut registry = CurrencyRegistry::new();
+        assert_eq!(registry.split_hrp("lnxreg20m"), None);
+        let currency = registry.register(custom).unwrap();
+        assert_eq!(currency, Currency::Custom(custom));
+        assert_eq!(registry.registered(), vec![currency]);
+        assert_eq!(registry.split_hrp("lnxreg20m"), Some((currency, "20m")));
+        assert_eq!(registry.split_hrp("lnbc20m"), Some((Currency::Bitcoin, "20m")));
+        assert_eq!(registry.from_prefix("lnxreg"), Some(currency));
+        assert_eq!(currency.base58_versions(), (30, 22));
+        // other registries and the built in lookups don't know about it
+        assert_eq!(CurrencyRegistry::new().from_prefix("lnxreg"), None);
+        assert_eq!(Currency::split_hrp("lnxreg20m"), None);
+        assert!("lnxreg".parse::<Currency>().is_err());
+
+        assert!(registry.register(custom).is_err());
+        for prefix in vec!["lnbc", "ln", "xxbc", "lnx1", "lnXreg"] {
+            let custom = CustomCurrency { prefix, ..custom };
+            assert!(registry.register(custom).is_err());
+        }
+    }
+
+    #[cfg(feature = "litecoin")]
+    #[test]
+    fn litecoin_prefixes() {
+        assert_eq!(
+            Currency::split_hrp("lnltc2500u"),
+            Some((Currency::Litecoin, "2500u"))
+        );
+        assert_eq!(
+            Currency::split_hrp("lntltc1m"),
+            Some((Currency::LitecoinTestnet, "1m"))
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Typed values of tagged fields this crate doesn't define, e.g. experimental trampoline hints
+//! or LSP extensions. They are stored as `Tag::UnknownTag`, so payment requests carrying them
+//! still decode and encode without knowing their type.
+
+use alloc::vec::Vec;
+use bech32::char_to_u5;
+use consts::{TAG_DESCRIPTION, TAG_DESCRIPTION_HASH, TAG_EXPIRY, TAG_FALLBACK_ADDRESS,
+             TAG_FEATURES, TAG_METADATA, TAG_MIN_FINAL_CLTV_EXPIRY, TAG_PAYEE_NODE_ID,
+             TAG_PAYMENT_HASH, TAG_PAYMENT_SECRET, TAG_ROUTING_INFO};
+use payment_request::PaymentRequest;
+use tag::Tag;
+use types::Error;
+use u5::U5;
+
+/// Characters of the tags this crate defines, which custom tags can't use.
+const KNOWN_TAGS: &[char] = &[
+    TAG_PAYMENT_HASH,
+    TAG_PAYEE_NODE_ID,
+    TAG_PAYMENT_SECRET,
+    TAG_DESCRIPTION,
+    TAG_DESCRIPTION_HASH,
+    TAG_FALLBACK_ADDRESS,
+    TAG_ROUTING_INFO,
+    TAG_EXPIRY,
+    TAG_MIN_FINAL_CLTV_EXPIRY,
+    TAG_METADATA,
+    TAG_FEATURES,
+];
+
+/// A tagged field defined outside of this crate, converted to and from the 5-bit words of its
+/// data.
+pub trait CustomTag: Sized {
+    /// Character of the tag, a bech32 character no field of this crate uses.
+    const TAG: char;
+
+    /// Decode the value from the data of the field.
+    fn decode(data: &[U5]) -> Result<Self, Error>;
+
+    /// Encode the value as the data of the field.
+    fn encode(&self) -> Vec<U5>;
+}
+
+/// Return the word of the tag of `T`, failing if it isn't a bech32 character or is used by
+/// this crate.
+fn custom_tag_word<T: CustomTag>() -> Result<U5, Error> {
+    match char_to_u5(T::TAG) {
+        Some(word) if !KNOWN_TAGS.contains(&T::TAG.to_ascii_lowercase()) => Ok(word),
+        _ => Err(Error::InvalidParameter(format!(
+            "'{}' can't be the character of a custom tag",
+            T::TAG
+        ))),
+    }
+}
+
+impl Tag {
+    /// Tagged field holding `value`, failing if `T::TAG` can't be used or the encoded value
+    /// is too long.
+    pub fn custom<T: CustomTag>(value: &T) -> Result<Tag, Error> {
+        Tag::unknown(custom_tag_word::<T>()?, value.encode())
+    }
+
+    /// Decode this tag as a `T`, `None` if it is another tag.
+    pub fn to_custom<T: CustomTag>(&self) -> Option<Result<T, Error>> {
+        match *self {
+            Tag::UnknownTag { tag, ref bytes } if char_to_u5(T::TAG) == Some(tag) => {
+                Some(T::decode(bytes))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl PaymentRequest {
+    /// Return the first field of type `T` if any, decoded.
+    pub fn custom_tag<T: CustomTag>(&self) -> Option<Result<T, Error>> {
+        self.tags.iter().find_map(Tag::to_custom)
+    }
+
+    /// Replace the fields of type `T` with one holding `value`. The payment request has to be
+    /// signed again.
+    pub fn update_custom_tag<T: CustomTag>(&mut self, value: &T) -> Result<(), Error> {
+        let tag = Tag::custom(value)?;
+        self.tags.retain(|t| t.tag_char() != tag.tag_char());
+        self.tags.push(tag);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use fixtures::sec_key;
+    use utils::{U5Conversions, U8Conversions};
+
+    /// Hypothetical field carrying the id of an LSP.
+    #[derive(Debug, PartialEq)]
+    struct LspId(u32);
+
+    impl CustomTag for LspId {
+        const TAG: char = 'l';
+
+        fn decode(data: &[U5]) -> Result<LspId, Error> {
+            let bytes = data.to_u8_vec(false);
+            if bytes.len() != 4 {
+                return Err(Error::InvalidLength("LSP id isn't 4 bytes long".to_owned()));
+            }
+            Ok(LspId(bytes.iter().fold(0, |id, byte| id << 8 | u32::from(*byte))))
+        }
+
+        fn encode(&self) -> Vec<U5> {
+            self.0.to_be_bytes().to_u5_vec(true)
+        }
+    }
+
+    struct Shadowing;
+
+    impl CustomTag for Shadowing {
+        const TAG: char = 'p';
+
+        fn decode(_: &[U5]) -> Result<Shadowing, Error> {
+            Ok(Shadowing)
+        }
+
+        fn encode(&self) -> Vec<U5> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn custom_tags() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        assert!(pay_request.custom_tag::<LspId>().is_none());
+
+        pay_request.update_custom_tag(&LspId(7)).unwrap();
+        pay_request.update_custom_tag(&LspId(0xdead_beef)).unwrap();
+        let tag = pay_request.tags.last().unwrap().clone();
+        assert_eq!(tag.tag_char(), 'l');
+        assert!(pay_request.tags[0].to_custom::<LspId>().is_none());
+
+        let encoded = pay_request.sign(&sec_key()).unwrap().encode().unwrap();
+        let decoded = PaymentRequest::decode(&encoded).unwrap();
+        assert_eq!(decoded.custom_tag::<LspId>().unwrap().unwrap(), LspId(0xdead_beef));
+        assert_eq!(decoded.tags.iter().filter(|t| t.tag_char() == 'l').count(), 1);
+
+        assert!(Tag::custom(&Shadowing).is_err());
+        assert!(pay_request.update_custom_tag(&Shadowing).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Expiry checks of payment requests.
+
+use alloc::borrow::ToOwned;
+use types::Error;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Expiry time of payment requests without an `'x'` field, in seconds.
+pub const DEFAULT_EXPIRY_SECONDS: u64 = 3600;
+
+/// min_final_cltv_expiry of payment requests without a `'c'` field, in blocks.
+pub const DEFAULT_MIN_FINAL_CLTV_EXPIRY: u64 = 18;
+
+/// Allowances applied when checking whether a payment request is expired or valid, so payment
+/// processors don't race the expiry boundary and tolerate clocks that are slightly off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExpiryGrace {
+    /// Consider payment requests expired this many seconds before their actual expiry, e.g.
+    /// to leave enough time for a payment to complete.
+    pub early_seconds: u64,
+    /// Tolerate this many seconds of clock skew with the issuer: payment requests are
+    /// considered expired that much later and may have a timestamp that much in the future.
+    pub skew_seconds: u64,
+}
+
+impl ExpiryGrace {
+    /// No allowance at all.
+    pub fn none() -> ExpiryGrace {
+        ExpiryGrace::default()
+    }
+
+    /// Create an allowance.
+    /// # Params
+    /// `early_seconds` How long before their expiry payment requests are considered expired.
+    /// `skew_seconds` Tolerated clock skew.
+    pub fn new(early_seconds: u64, skew_seconds: u64) -> ExpiryGrace {
+        ExpiryGrace {
+            early_seconds,
+            skew_seconds,
+        }
+    }
+
+    /// Return true if a payment request expiring at `expires_at` is expired at `now`.
+    pub fn is_expired(&self, expires_at: u64, now: u64) -> bool {
+        now.saturating_add(self.early_seconds) > expires_at.saturating_add(self.skew_seconds)
+    }
+
+    /// Return true if `timestamp` is too far in the future to be trusted at `now`.
+    pub fn is_in_future(&self, timestamp: u64, now: u64) -> bool {
+        timestamp > now.saturating_add(self.skew_seconds)
+    }
+}
+
+/// Current UNIX time in seconds.
+#[cfg(feature = "std")]
+pub fn now() -> Result<u64, Error> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .map_err(|_| Error::InvalidValue("invalid system time".to_owned()))
+}
+
+/// Current UNIX time in seconds, unknown without the `std` feature: pass the time explicitly,
+/// e.g. to `PaymentRequest::validate_at`.
+#[cfg(not(feature = "std"))]
+pub fn now() -> Result<u64, Error> {
+    Err(Error::InvalidValue("the current time is unknown without std".to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grace() {
+        let none = ExpiryGrace::none();
+        assert!(!none.is_expired(1000, 1000));
+        assert!(none.is_expired(1000, 1001));
+
+        let early = ExpiryGrace::new(60, 0);
+        assert!(!early.is_expired(1000, 940));
+        assert!(early.is_expired(1000, 941));
+
+        let skew = ExpiryGrace::new(0, 30);
+        assert!(!skew.is_expired(1000, 1030));
+        assert!(skew.is_expired(1000, 1031));
+        assert!(!skew.is_in_future(1030, 1000));
+        assert!(skew.is_in_future(1031, 1000));
+        assert!(none.is_in_future(1001, 1000));
+
+        assert!(!ExpiryGrace::new(0, u64::max_value()).is_expired(1000, u64::max_value()));
+    }
+}
@@ -0,0 +1,345 @@
+//! Feature bits advertised in the `'9'` tagged field.
+
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use core::fmt;
+use u5::U5;
+
+/// Features defined by BOLT #9 that are relevant to payment requests. Each feature has a pair of
+/// bits: the even one means the feature is required, the odd one that it is optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// `var_onion_optin`, bits 8/9: variable length routing onion payloads.
+    VarOnionOptin,
+    /// `payment_secret`, bits 14/15: the payment secret field is used.
+    PaymentSecret,
+    /// `basic_mpp`, bits 16/17: the payment may be split in several parts.
+    BasicMpp,
+    /// `option_payment_metadata`, bits 48/49: the payment metadata field is used.
+    PaymentMetadata,
+}
+
+/// Every known feature.
+const FEATURES: &[Feature] = &[
+    Feature::VarOnionOptin,
+    Feature::PaymentSecret,
+    Feature::BasicMpp,
+    Feature::PaymentMetadata,
+];
+
+impl Feature {
+    /// Every known feature.
+    pub fn all() -> &'static [Feature] {
+        FEATURES
+    }
+
+    /// The even bit, set when the feature is required.
+    pub fn required_bit(&self) -> usize {
+        match *self {
+            Feature::VarOnionOptin => 8,
+            Feature::PaymentSecret => 14,
+            Feature::BasicMpp => 16,
+            Feature::PaymentMetadata => 48,
+        }
+    }
+
+    /// The odd bit, set when the feature is optional.
+    pub fn optional_bit(&self) -> usize {
+        self.required_bit() + 1
+    }
+
+    /// Return the feature using `bit`, whether it is the required or the optional one.
+    pub fn from_bit(bit: usize) -> Option<Feature> {
+        FEATURES
+            .iter()
+            .cloned()
+            .find(|f| f.required_bit() == bit & !1)
+    }
+
+    /// Return true if `bit` is a required (even) bit.
+    pub fn is_required_bit(bit: usize) -> bool {
+        bit % 2 == 0
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Feature::VarOnionOptin => "var_onion_optin",
+            Feature::PaymentSecret => "payment_secret",
+            Feature::BasicMpp => "basic_mpp",
+            Feature::PaymentMetadata => "option_payment_metadata",
+        })
+    }
+}
+
+/// Arbitrary-length feature bit vector.
+///
+/// Bit 0 is the least significant bit. New features keep being assigned high positions, so
+/// the vector grows as needed and is always kept in its canonical form: two vectors with the
+/// same bits set compare equal regardless of how they were built.
+///
+/// Bits unknown to this crate are kept like any other. A vector parsed from a `'9'` field also
+/// remembers the exact words it was parsed from, so that re-encoding a payment request whose
+/// features weren't changed reproduces the field bit for bit, even if the issuer didn't use
+/// the minimal encoding.
+#[derive(Debug, Clone, Default)]
+pub struct Features {
+    /// Little-endian bytes, without trailing zero bytes.
+    bytes: Vec<u8>,
+    /// Words this vector was parsed from, dropped as soon as a bit changes.
+    raw: Option<Vec<U5>>,
+}
+
+impl PartialEq for Features {
+    fn eq(&self, other: &Features) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for Features {}
+
+impl Features {
+    /// Create an empty feature vector.
+    pub fn new() -> Features {
+        Features {
+            bytes: Vec::new(),
+            raw: None,
+        }
+    }
+
+    /// Create a feature vector with the given bits set.
+    pub fn from_bits<I: IntoIterator<Item = usize>>(bits: I) -> Features {
+        let mut features = Features::new();
+        for bit in bits {
+            features.set(bit);
+        }
+        features
+    }
+
+    /// Return true if `bit` is set.
+    pub fn is_set(&self, bit: usize) -> bool {
+        self.bytes
+            .get(bit / 8)
+            .map_or(false, |byte| byte & (1 << (bit % 8)) != 0)
+    }
+
+    /// Set `bit`.
+    pub fn set(&mut self, bit: usize) {
+        if self.is_set(bit) {
+            return;
+        }
+        self.raw = None;
+        if self.bytes.len() <= bit / 8 {
+            self.bytes.resize(bit / 8 + 1, 0);
+        }
+        self.bytes[bit / 8] |= 1 << (bit % 8);
+    }
+
+    /// Unset `bit`.
+    pub fn unset(&mut self, bit: usize) {
+        if !self.is_set(bit) {
+            return;
+        }
+        self.raw = None;
+        if let Some(byte) = self.bytes.get_mut(bit / 8) {
+            *byte &= !(1 << (bit % 8));
+        }
+        self.trim();
+    }
+
+    /// Return true if either bit of `feature` is set.
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.is_set(feature.required_bit()) || self.is_set(feature.optional_bit())
+    }
+
+    /// Return true if the required bit of `feature` is set.
+    pub fn requires(&self, feature: Feature) -> bool {
+        self.is_set(feature.required_bit())
+    }
+
+    /// Mark `feature` as required, clearing its optional bit.
+    pub fn set_required(&mut self, feature: Feature) {
+        self.unset(feature.optional_bit());
+        self.set(feature.required_bit());
+    }
+
+    /// Mark `feature` as optional, clearing its required bit.
+    pub fn set_optional(&mut self, feature: Feature) {
+        self.unset(feature.required_bit());
+        self.set(feature.optional_bit());
+    }
+
+    /// Required bits set that don't belong to a known feature. A payer must not attempt to pay
+    /// a payment request with any of them.
+    pub fn unknown_required_bits(&self) -> Vec<usize> {
+        self.iter()
+            .filter(|bit| Feature::is_required_bit(*bit) && Feature::from_bit(*bit).is_none())
+            .collect()
+    }
+
+    /// Return true if no bit is set.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Iterate over the set bits in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.bytes.len() * 8).filter(move |bit| self.is_set(*bit))
+    }
+
+    /// Parse the big-endian 5-bit words of a `'9'` field.
+    pub fn from_u5(data: &[U5]) -> Features {
+        let mut features = Features::new();
+        for (index, word) in data.iter().rev().enumerate() {
+            for offset in 0..5 {
+                if word.to_u8() & (1 << offset) != 0 {
+                    features.set(index * 5 + offset);
+                }
+            }
+        }
+        features.raw = Some(data.to_vec());
+        features
+    }
+
+    /// The words this vector was parsed from, if no bit changed since.
+    pub fn raw_u5(&self) -> Option<&[U5]> {
+        self.raw.as_ref().map(|raw| raw.as_slice())
+    }
+
+    /// Encode as big-endian 5-bit words, reusing the words this vector was parsed from if no
+    /// bit changed since, or else using as few words as possible.
+    pub fn to_raw_u5_vec(&self) -> Vec<U5> {
+        self.raw.to_owned().unwrap_or_else(|| self.to_u5_vec())
+    }
+
+    /// Encode as big-endian 5-bit words, using as few words as possible.
+    pub fn to_u5_vec(&self) -> Vec<U5> {
+        let words = self.iter().last().map_or(0, |highest| highest / 5 + 1);
+        (0..words)
+            .rev()
+            .map(|index| {
+                let word = (0..5).fold(0u8, |acc, offset| {
+                    if self.is_set(index * 5 + offset) {
+                        acc | (1 << offset)
+                    } else {
+                        acc
+                    }
+                });
+                U5::from_low_bits(word)
+            })
+            .collect()
+    }
+
+    /// Remove trailing zero bytes to keep the representation canonical.
+    fn trim(&mut self) {
+        while self.bytes.last() == Some(&0) {
+            self.bytes.pop();
+        }
+    }
+}
+
+/// Serialized as the list of set bits, in increasing order.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Features {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Features {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Features, D::Error> {
+        <Vec<usize> as serde::Deserialize>::deserialize(deserializer).map(Features::from_bits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_and_unset() {
+        let mut features = Features::new();
+        assert!(features.is_empty());
+
+        features.set(8);
+        features.set(200);
+        assert!(features.is_set(8));
+        assert!(features.is_set(200));
+        assert!(!features.is_set(9));
+        assert!(!features.is_set(1000));
+        assert_eq!(features.iter().collect::<Vec<_>>(), vec![8, 200]);
+
+        features.unset(200);
+        assert_eq!(features, Features::from_bits(vec![8]));
+        features.unset(8);
+        assert!(features.is_empty());
+        assert_eq!(features, Features::new());
+    }
+
+    #[test]
+    fn known_features() {
+        // var_onion_optin required, payment_secret optional, unknown required bit 100
+        let mut features = Features::from_bits(vec![8, 15, 100]);
+        assert!(features.requires(Feature::VarOnionOptin));
+        assert!(features.supports(Feature::PaymentSecret));
+        assert!(!features.requires(Feature::PaymentSecret));
+        assert!(!features.supports(Feature::BasicMpp));
+        assert_eq!(features.unknown_required_bits(), vec![100]);
+
+        features.set_required(Feature::PaymentSecret);
+        features.set_optional(Feature::BasicMpp);
+        assert_eq!(features.iter().collect::<Vec<_>>(), vec![8, 14, 17, 100]);
+
+        for feature in Feature::all() {
+            assert_eq!(Feature::from_bit(feature.required_bit()), Some(*feature));
+            assert_eq!(Feature::from_bit(feature.optional_bit()), Some(*feature));
+            assert!(Feature::is_required_bit(feature.required_bit()));
+            assert!(!Feature::is_required_bit(feature.optional_bit()));
+        }
+        assert_eq!(Feature::from_bit(10), None);
+        assert_eq!(Feature::BasicMpp.to_string(), "basic_mpp");
+    }
+
+    fn u5s(values: &[u8]) -> Vec<U5> {
+        U5::from_slice(values).unwrap()
+    }
+
+    #[test]
+    fn u5_encoding() {
+        let features = Features::from_bits(vec![8, 14]);
+        assert_eq!(features.to_u5_vec(), u5s(&[16, 8, 0]));
+        assert_eq!(Features::from_u5(&u5s(&[16, 8, 0])), features);
+        // leading zero words are not part of the canonical encoding
+        let padded = Features::from_u5(&u5s(&[0, 0, 16, 8, 0]));
+        assert_eq!(padded.to_u5_vec(), u5s(&[16, 8, 0]));
+        assert_eq!(Features::new().to_u5_vec(), Vec::<U5>::new());
+        assert_eq!(Features::new().to_raw_u5_vec(), Vec::<U5>::new());
+
+        let high = Features::from_bits(vec![0, 99, 256]);
+        let encoded = high.to_u5_vec();
+        assert_eq!(encoded.len(), 52);
+        assert_eq!(Features::from_u5(&encoded), high);
+    }
+
+    #[test]
+    fn raw_encoding() {
+        // bit 8 set, bit 100 unknown, encoded with leading zero words
+        let mut words = vec![U5::ZERO; 3];
+        words.extend(Features::from_bits(vec![8, 100]).to_u5_vec());
+        let mut features = Features::from_u5(&words);
+        assert_eq!(features, Features::from_bits(vec![8, 100]));
+        assert_eq!(features.raw_u5(), Some(words.as_slice()));
+        assert_eq!(features.to_raw_u5_vec(), words);
+
+        // setting an already set bit keeps the encoding
+        features.set(100);
+        assert_eq!(features.to_raw_u5_vec(), words);
+
+        features.set(9);
+        assert_eq!(features.raw_u5(), None);
+        assert_eq!(features.to_raw_u5_vec(), features.to_u5_vec());
+        assert_eq!(features.iter().collect::<Vec<_>>(), vec![8, 9, 100]);
+    }
+}
@@ -0,0 +1,217 @@
+//! C bindings, so that wallets written in other languages (e.g. Swift or Kotlin) can link
+//! against this crate to parse payment requests. The matching declarations are in
+//! `include/bolt11.h`.
+//!
+//! Strings returned to C are owned by this crate and must be released with
+//! `bolt11_string_free`, decoded invoices with `bolt11_free`. Panics are caught at the boundary
+//! and reported like any other error, they never unwind into C.
+//!
+//! The shared and static libraries are built by the `bolt11-ffi` crate in `ffi/`.
+
+use codec::DecodeOptions;
+use expiry::DEFAULT_EXPIRY_SECONDS;
+use payment_request::PaymentRequest;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use types::Error;
+
+/// A decoded payment request.
+#[repr(C)]
+pub struct Bolt11Invoice {
+    /// Currency prefix, e.g. `"lnbc"`.
+    pub prefix: *mut c_char,
+    /// Whether the invoice asks for a specific amount.
+    pub has_amount: bool,
+    /// Amount to pay in millisatoshis, 0 if `has_amount` is false.
+    pub amount_msat: u64,
+    /// Creation time, in seconds since the UNIX epoch.
+    pub timestamp: u64,
+    /// Seconds after `timestamp` the invoice expires, the default of one hour if not given.
+    pub expiry_seconds: u64,
+    /// Compressed public key of the payee node.
+    pub node_id: [u8; 33],
+    /// Payment hash.
+    pub payment_hash: [u8; 32],
+    /// Description, null if the invoice commits to a description hash instead.
+    pub description: *mut c_char,
+    request: *mut PaymentRequest,
+}
+
+/// Decode a payment request, checking it strictly. Returns null on failure, setting `*error`
+/// to a message to release with `bolt11_string_free` unless `error` is null.
+///
+/// # Safety
+/// `input` must be a null terminated string, `error` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_decode(
+    input: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut Bolt11Invoice {
+    match catch_panic(|| decode(input)) {
+        Ok(invoice) => Box::into_raw(Box::new(invoice)),
+        Err(e) => {
+            set_error(error, &e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Encode a decoded payment request back into a string, to release with
+/// `bolt11_string_free`. Returns null on failure, setting `*error` like `bolt11_decode`.
+///
+/// # Safety
+/// `invoice` must have been returned by `bolt11_decode` and not freed yet, `error` null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_encode(
+    invoice: *const Bolt11Invoice,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let encoded = catch_panic(|| {
+        if invoice.is_null() {
+            Err(Error::InvalidValue("null invoice".to_owned()))
+        } else {
+            (*(*invoice).request).encode().and_then(c_string)
+        }
+    });
+    encoded.unwrap_or_else(|e| {
+        set_error(error, &e);
+        ptr::null_mut()
+    })
+}
+
+/// Release an invoice returned by `bolt11_decode`. Does nothing if `invoice` is null.
+///
+/// # Safety
+/// `invoice` must have been returned by `bolt11_decode` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_free(invoice: *mut Bolt11Invoice) {
+    if invoice.is_null() {
+        return;
+    }
+    let _ = panic::catch_unwind(|| {
+        let invoice = Box::from_raw(invoice);
+        bolt11_string_free(invoice.prefix);
+        bolt11_string_free(invoice.description);
+        drop(Box::from_raw(invoice.request));
+    });
+}
+
+/// Release a string returned by this library. Does nothing if `string` is null.
+///
+/// # Safety
+/// `string` must have been returned by this library and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bolt11_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        let _ = panic::catch_unwind(|| drop(CString::from_raw(string)));
+    }
+}
+
+/// Run `f`, turning a panic into an error since unwinding into C is undefined behavior.
+fn catch_panic<T, F: FnOnce() -> Result<T, Error>>(f: F) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(Error::InvalidValue("bolt11 panicked".to_owned())))
+}
+
+unsafe fn decode(input: *const c_char) -> Result<Bolt11Invoice, Error> {
+    if input.is_null() {
+        return Err(Error::InvalidValue("null input".to_owned()));
+    }
+    let input = CStr::from_ptr(input)
+        .to_str()
+        .map_err(|_| Error::InvalidValue("input is not UTF-8".to_owned()))?;
+    let pay_request = PaymentRequest::decode_with_options(input, &DecodeOptions::strict())?;
+
+    let mut payment_hash = [0u8; 32];
+    let hash = pay_request
+        .payment_hash()
+        .filter(|hash| hash.len() == payment_hash.len())
+        .ok_or_else(|| Error::InvalidValue("invalid payment hash".to_owned()))?;
+    payment_hash.copy_from_slice(&hash);
+    // converted before any of them is handed out, so that nothing leaks on failure
+    let prefix = CString::new(pay_request.prefix.as_str()).map_err(nul_error)?;
+    let description = match pay_request.description() {
+        Some(description) => Some(CString::new(description).map_err(nul_error)?),
+        None => None,
+    };
+    Ok(Bolt11Invoice {
+        prefix: prefix.into_raw(),
+        has_amount: pay_request.amount.is_some(),
+        amount_msat: pay_request.amount.map_or(0, |amount| amount.as_u64()),
+        timestamp: pay_request.timestamp,
+        expiry_seconds: pay_request.expiry().unwrap_or(DEFAULT_EXPIRY_SECONDS),
+        node_id: pay_request.node_id.serialize_compressed(),
+        payment_hash,
+        description: description.map_or(ptr::null_mut(), CString::into_raw),
+        request: Box::into_raw(Box::new(pay_request)),
+    })
+}
+
+fn c_string(string: String) -> Result<*mut c_char, Error> {
+    CString::new(string).map(CString::into_raw).map_err(nul_error)
+}
+
+fn nul_error<E>(_: E) -> Error {
+    Error::InvalidValue("string contains a null character".to_owned())
+}
+
+unsafe fn set_error(error: *mut *mut c_char, e: &Error) {
+    if !error.is_null() {
+        *error = c_string(e.to_string()).unwrap_or(ptr::null_mut());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use utils::to_hex;
+
+    #[test]
+    fn decode_encode() {
+        let encoded = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let input = CString::new(encoded).unwrap();
+        let mut error = ptr::null_mut();
+        unsafe {
+            let invoice = bolt11_decode(input.as_ptr(), &mut error);
+            assert!(!invoice.is_null());
+            assert!(error.is_null());
+            {
+                let invoice = &*invoice;
+                assert_eq!(CStr::from_ptr(invoice.prefix).to_str().unwrap(), "lnbc");
+                assert!(invoice.has_amount);
+                assert_eq!(invoice.amount_msat, 250_000_000);
+                assert_eq!(invoice.timestamp, 1496314658);
+                assert_eq!(invoice.expiry_seconds, 60);
+                assert_eq!(
+                    to_hex(&invoice.payment_hash),
+                    "0001020304050607080900010203040506070809000102030405060708090102"
+                );
+                assert_eq!(
+                    CStr::from_ptr(invoice.description).to_str().unwrap(),
+                    "1 cup coffee"
+                );
+            }
+
+            let reencoded = bolt11_encode(invoice, &mut error);
+            assert_eq!(CStr::from_ptr(reencoded).to_str().unwrap(), encoded);
+            bolt11_string_free(reencoded);
+            bolt11_free(invoice);
+
+            let input = CString::new("lnbc1invalid").unwrap();
+            assert!(bolt11_decode(input.as_ptr(), &mut error).is_null());
+            assert!(!error.is_null());
+            bolt11_string_free(error);
+            assert!(bolt11_decode(ptr::null(), ptr::null_mut()).is_null());
+        }
+    }
+
+    #[test]
+    fn panics_are_errors() {
+        let caught = catch_panic::<(), _>(|| panic!("boom"));
+        assert!(caught.is_err());
+        assert_eq!(catch_panic(|| Ok(1)).unwrap(), 1);
+    }
+}
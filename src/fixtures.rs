@@ -0,0 +1,45 @@
+//! Fixtures shared by the unit tests, taken from the BOLT11 examples.
+
+use msat::MilliSatoshi;
+use payment_request::PaymentRequest;
+use secp256k1::{PublicKey, SecretKey};
+use utils::from_hex;
+
+/// Private key of the node signing the BOLT11 examples.
+pub fn sec_key() -> SecretKey {
+    let key = from_hex("e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734")
+        .unwrap();
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&key);
+    SecretKey::parse(&bytes).unwrap()
+}
+
+/// Node id of `sec_key`.
+pub fn node_key() -> PublicKey {
+    PublicKey::from_secret_key(&sec_key())
+}
+
+/// Payment hash of the BOLT11 examples.
+pub fn payment_hash() -> Vec<u8> {
+    from_hex("0001020304050607080900010203040506070809000102030405060708090102").unwrap()
+}
+
+/// The "1 cup coffee" payment request of the BOLT11 examples, signed with `sec_key`.
+///
+/// # Params
+/// `amount` Amount to pay.
+/// `expiry_seconds` Expiry field, if any.
+pub fn coffee(amount: Option<MilliSatoshi>, expiry_seconds: Option<u64>) -> PaymentRequest {
+    PaymentRequest::new(
+        "lnbc".to_owned(),
+        amount,
+        payment_hash(),
+        &sec_key(),
+        "1 cup coffee".to_owned(),
+        None,
+        expiry_seconds,
+        vec![],
+        Some(1496314658),
+        None,
+    ).unwrap()
+}
@@ -0,0 +1,197 @@
+//! `Arbitrary` implementations, so payment requests and their fields can be fuzzed structurally.
+//!
+//! Generated values are always encodable: fields have the lengths BOLT11 expects and generated
+//! payment requests carry a payment hash and a valid signature of their node id.
+
+use arbitrary::{Arbitrary, Error as ArbitraryError, Result, Unstructured};
+use bech32::char_to_u5;
+use codec::DecodeOptions;
+use consts::{MAX_DESCRIPTION_LENGTH, MAX_FIELD_LENGTH, MAX_ROUTE_HOPS};
+use currency::Currency;
+use features::Features;
+use msat::MilliSatoshi;
+use payment_request::PaymentRequest;
+use scid::ShortChannelId;
+use secp256k1::{PublicKey, SecretKey};
+use tag::{ExtraHop, Tag};
+use timestamp::Timestamp;
+use u5::U5;
+
+/// Tag characters this crate doesn't know about, for `Tag::UnknownTag`.
+const UNKNOWN_TAGS: &str = "qzy8g2tvw03j54ke6ua7l";
+
+impl<'a> Arbitrary<'a> for U5 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<U5> {
+        Ok(U5::from_low_bits(u.arbitrary()?))
+    }
+}
+
+fn secret_key(u: &mut Unstructured) -> Result<SecretKey> {
+    let bytes: [u8; 32] = u.arbitrary()?;
+    SecretKey::parse(&bytes).map_err(|_| ArbitraryError::IncorrectFormat)
+}
+
+fn pub_key(u: &mut Unstructured) -> Result<PublicKey> {
+    Ok(PublicKey::from_secret_key(&secret_key(u)?))
+}
+
+impl<'a> Arbitrary<'a> for ExtraHop {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<ExtraHop> {
+        Ok(ExtraHop {
+            pub_key: pub_key(u)?,
+            short_channel_id: ShortChannelId::new(u.arbitrary()?),
+            fee_base_msat: MilliSatoshi::new(u64::from(u.arbitrary::<u32>()?)),
+            fee_proportional_millionths: u.arbitrary()?,
+            cltv_expiry_delta: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Tag {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Tag> {
+        let tag = match u.int_in_range(0..=11u8)? {
+            0 => Tag::PaymentHash {
+                hash: u.arbitrary::<[u8; 32]>()?.to_vec(),
+            },
+            1 => Tag::PaymentSecret {
+                secret: u.arbitrary()?,
+            },
+            2 => Tag::PayeeNodeId {
+                pubkey: pub_key(u)?,
+            },
+            3 => {
+                let mut description: String = u.arbitrary()?;
+                while description.len() > MAX_DESCRIPTION_LENGTH {
+                    description.pop();
+                }
+                Tag::Description { description }
+            }
+            4 => Tag::DescriptionHash {
+                hash: u.arbitrary::<[u8; 32]>()?.to_vec(),
+            },
+            5 => {
+                let version = u.int_in_range(0..=18u8)?;
+                // pubkey and script hashes are 20 bytes, witness programs 20 or 32 bytes
+                let len = if version == 0 && u.arbitrary()? { 32 } else { 20 };
+                Tag::FallbackAddress {
+                    version,
+                    hash: u.bytes(len)?.to_vec(),
+                }
+            }
+            6 => Tag::Expiry {
+                seconds: u.arbitrary()?,
+            },
+            7 => Tag::MinFinalCltvExpiry {
+                blocks: u.arbitrary()?,
+            },
+            8 => {
+                let len = u.int_in_range(1..=MAX_ROUTE_HOPS)?;
+                let path = (0..len)
+                    .map(|_| u.arbitrary())
+                    .collect::<Result<Vec<ExtraHop>>>()?;
+                Tag::RoutingInfo { path }
+            }
+            9 => {
+                let mut bytes: Vec<u8> = u.arbitrary()?;
+                bytes.truncate(MAX_DESCRIPTION_LENGTH);
+                Tag::Metadata { bytes }
+            }
+            10 => Tag::Features {
+                features: u.arbitrary()?,
+            },
+            _ => {
+                let tag = *u.choose(UNKNOWN_TAGS.as_bytes())? as char;
+                let tag = char_to_u5(tag).unwrap_or(U5::ZERO);
+                let mut bytes: Vec<U5> = u.arbitrary()?;
+                bytes.truncate(MAX_FIELD_LENGTH);
+                Tag::UnknownTag {
+                    tag,
+                    bytes,
+                }
+            }
+        };
+        Ok(tag)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Features {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Features> {
+        let bits: Vec<u8> = u.arbitrary()?;
+        Ok(Features::from_bits(bits.into_iter().map(usize::from)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for PaymentRequest {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<PaymentRequest> {
+        let currency = *u.choose(Currency::all())?;
+        let amount = if u.arbitrary()? {
+            // at most 21 million bitcoins
+            Some(MilliSatoshi::new(
+                u.int_in_range(1..=2_100_000_000_000_000_000u64)?,
+            ))
+        } else {
+            None
+        };
+        let secret_key = secret_key(u)?;
+        let node_id = PublicKey::from_secret_key(&secret_key);
+        let mut tags: Vec<Tag> = u.arbitrary()?;
+        // an explicit payee node id must be the one that signs
+        for tag in &mut tags {
+            if let Tag::PayeeNodeId { ref mut pubkey } = *tag {
+                *pubkey = node_id.clone();
+            }
+        }
+        if !tags.iter().any(|tag| matches!(*tag, Tag::PaymentHash { .. })) {
+            tags.insert(
+                0,
+                Tag::PaymentHash {
+                    hash: u.arbitrary::<[u8; 32]>()?.to_vec(),
+                },
+            );
+        }
+        let pay_request = PaymentRequest {
+            prefix: currency.prefix().to_owned(),
+            amount,
+            amount_encoding: Default::default(),
+            timestamp: u.int_in_range(0..=Timestamp::MAX)?,
+            node_id,
+            tags,
+            signature: Vec::new(),
+        };
+        pay_request
+            .sign(&secret_key)
+            .map_err(|_| ArbitraryError::IncorrectFormat)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        // xorshift, so failures are reproducible
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut checked = 0;
+        for _ in 0..64 {
+            let bytes = (0..4096)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect::<Vec<u8>>();
+            let pay_request = match PaymentRequest::arbitrary(&mut Unstructured::new(&bytes)) {
+                Ok(pay_request) => pay_request,
+                Err(_) => continue,
+            };
+            let encoded = pay_request.encode().unwrap();
+            let decoded_request =
+                PaymentRequest::decode_with_options(&encoded, &DecodeOptions::unlimited()).unwrap();
+            assert_eq!(decoded_request, pay_request);
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Hex encoding of hashes, public keys and other bytes.
+//!
+//! Encoding and decoding only use `core` and `alloc`. Digits are decoded in either case and
+//! encoded in lower case; nothing is trimmed or padded.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Digits in encoding order.
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Why a hex string couldn't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexError {
+    /// The string has an odd number of digits, which is its length.
+    OddLength(usize),
+    /// A character isn't a hex digit.
+    InvalidCharacter {
+        /// The character.
+        character: char,
+        /// Its byte offset in the string.
+        index: usize,
+    },
+    /// The string doesn't decode to the number of bytes expected.
+    InvalidLength {
+        /// Number of bytes expected.
+        expected: usize,
+        /// Number of bytes the string decodes to.
+        actual: usize,
+    },
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HexError::OddLength(length) => {
+                write!(f, "hex string has an odd number of digits ({})", length)
+            }
+            HexError::InvalidCharacter { character, index } => write!(
+                f,
+                "invalid hex character {:?} at offset {}",
+                character, index
+            ),
+            HexError::InvalidLength { expected, actual } => write!(
+                f,
+                "hex string decodes to {} bytes instead of {}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for HexError {}
+
+/// Encode `bytes` as lower case hex.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(DIGITS[usize::from(byte >> 4)] as char);
+        hex.push(DIGITS[usize::from(byte & 0xf)] as char);
+    }
+    hex
+}
+
+/// Decode a hex string of either case.
+pub fn decode(hex: &str) -> Result<Vec<u8>, HexError> {
+    let mut bytes = vec![0; checked_length(hex)?];
+    decode_into(hex, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decode a hex string into `out`, failing unless it decodes to exactly `out.len()` bytes,
+/// e.g. for 32 byte hashes.
+pub fn decode_to_slice(hex: &str, out: &mut [u8]) -> Result<(), HexError> {
+    let length = checked_length(hex)?;
+    if length != out.len() {
+        return Err(HexError::InvalidLength {
+            expected: out.len(),
+            actual: length,
+        });
+    }
+    decode_into(hex, out)
+}
+
+/// Number of bytes `hex` decodes to.
+fn checked_length(hex: &str) -> Result<usize, HexError> {
+    if hex.len() % 2 != 0 {
+        return Err(HexError::OddLength(hex.len()));
+    }
+    Ok(hex.len() / 2)
+}
+
+/// Decode `hex`, of even length and twice as long as `out`, into `out`.
+fn decode_into(hex: &str, out: &mut [u8]) -> Result<(), HexError> {
+    let digit = |index: usize| {
+        let byte = hex.as_bytes()[index];
+        match byte {
+            b'0'..=b'9' => Ok(byte - b'0'),
+            b'a'..=b'f' => Ok(byte - b'a' + 10),
+            b'A'..=b'F' => Ok(byte - b'A' + 10),
+            // report the whole character rather than one of its bytes
+            _ => Err(HexError::InvalidCharacter {
+                character: hex[index..].chars().next().unwrap_or('\u{fffd}'),
+                index,
+            }),
+        }
+    };
+    for (position, byte) in out.iter_mut().enumerate() {
+        *byte = digit(2 * position)? << 4 | digit(2 * position + 1)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(encode(&[]), "");
+        assert_eq!(encode(&[0, 10, 171, 255]), "000aabff");
+        assert_eq!(decode("000aabff").unwrap(), vec![0, 10, 171, 255]);
+        assert_eq!(decode("000AABFF").unwrap(), vec![0, 10, 171, 255]);
+        assert_eq!(decode("").unwrap(), Vec::<u8>::new());
+
+        let mut hash = [0u8; 4];
+        decode_to_slice("01020304", &mut hash).unwrap();
+        assert_eq!(hash, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decode("abc"), Err(HexError::OddLength(3)));
+        assert_eq!(
+            decode("0g"),
+            Err(HexError::InvalidCharacter {
+                character: 'g',
+                index: 1,
+            })
+        );
+        assert_eq!(
+            decode("é"),
+            Err(HexError::InvalidCharacter {
+                character: 'é',
+                index: 0,
+            })
+        );
+        assert_eq!(decode("00 "), Err(HexError::OddLength(3)));
+        assert_eq!(
+            decode(" 00 "),
+            Err(HexError::InvalidCharacter {
+                character: ' ',
+                index: 0,
+            })
+        );
+        assert_eq!(
+            decode_to_slice("0102", &mut [0u8; 4]),
+            Err(HexError::InvalidLength {
+                expected: 4,
+                actual: 2,
+            })
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Serde helpers writing byte fields and public keys as hex strings, for use with
+//! `#[serde(with = "...")]`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use secp256k1::PublicKey;
+use serde::de::Error as DeError;
+use serde::{Deserialize, Deserializer, Serializer};
+use tag::ExtraHop;
+use utils::{from_hex, to_hex};
+
+/// Decode an even length hex string.
+fn decode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    from_hex(&hex).map_err(D::Error::custom)
+}
+
+/// `Vec<u8>` as a hex string.
+pub mod bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        decode(deserializer)
+    }
+}
+
+/// `[u8; 32]` as a hex string.
+pub mod bytes32 {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let bytes = decode(deserializer)?;
+        if bytes.len() != 32 {
+            return Err(D::Error::custom("expected 32 bytes"));
+        }
+        let mut array = [0u8; 32];
+        array.copy_from_slice(&bytes);
+        Ok(array)
+    }
+}
+
+/// `PublicKey` as the hex string of its compressed form.
+pub mod pub_key {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(pub_key: &PublicKey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(&pub_key.serialize_compressed()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PublicKey, D::Error> {
+        let bytes = decode(deserializer)?;
+        ExtraHop::parse_pub_key(&bytes).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,123 @@
+//! The human readable part of payment requests, e.g. `lnbc2500u`.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use core::str::FromStr;
+
+use amount::{Amount, AmountEncoding};
+use currency::{Currency, CurrencyRegistry};
+use msat::MilliSatoshi;
+use types::Error;
+
+/// Human readable part of a payment request: a currency prefix optionally followed by an amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hrp {
+    /// Currency identified by the prefix.
+    pub currency: Currency,
+    /// Amount to pay, `None` if any amount may be paid.
+    pub amount: Option<MilliSatoshi>,
+    /// How the amount is written.
+    pub amount_encoding: AmountEncoding,
+}
+
+impl Hrp {
+    /// Human readable part using the shortest encoding of `amount`.
+    ///
+    /// # Params
+    /// `currency` Currency of the payment request.
+    /// `amount` Amount to pay.
+    pub fn new(currency: Currency, amount: Option<MilliSatoshi>) -> Hrp {
+        Hrp {
+            currency,
+            amount,
+            amount_encoding: AmountEncoding::Shortest,
+        }
+    }
+
+    /// Parse a lower case human readable part of a built in currency, remembering how its
+    /// amount was written.
+    ///
+    /// # Params
+    /// `hrp` The human readable part, without the `1` separator.
+    pub fn parse(hrp: &str) -> Result<Hrp, Error> {
+        Hrp::parse_with(hrp, &CurrencyRegistry::new())
+    }
+
+    /// Parse a lower case human readable part of any currency of `currencies`.
+    ///
+    /// # Params
+    /// `hrp` The human readable part, without the `1` separator.
+    /// `currencies` Currencies accepted.
+    pub fn parse_with(hrp: &str, currencies: &CurrencyRegistry) -> Result<Hrp, Error> {
+        let (currency, hr_amount) = currencies
+            .split_hrp(hrp)
+            .ok_or(Error::InvalidValue("unknown currency prefix".to_owned()))?;
+        let amount = match hr_amount {
+            "" => None,
+            hr_amount => Some(MilliSatoshi::new(Amount::decode(hr_amount)?)),
+        };
+        Ok(Hrp {
+            currency,
+            amount,
+            amount_encoding: Amount::encoding_of(hr_amount),
+        })
+    }
+
+    /// Serialize the human readable part, failing if the amount can not be written with
+    /// `amount_encoding`.
+    pub fn encode(&self) -> Result<String, Error> {
+        match self.amount {
+            Some(amount) => Ok(self.currency.prefix().to_owned()
+                + &Amount::encode_with(amount.as_u64(), self.amount_encoding)?),
+            None => Ok(self.currency.prefix().to_owned()),
+        }
+    }
+}
+
+impl FromStr for Hrp {
+    type Err = Error;
+
+    fn from_str(hrp: &str) -> Result<Hrp, Error> {
+        Hrp::parse(hrp)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let hrp = Hrp::parse("lnbc2500u").unwrap();
+        assert_eq!(hrp.currency, Currency::Bitcoin);
+        assert_eq!(hrp.amount, Some(MilliSatoshi::new(250_000_000)));
+        assert_eq!(hrp.encode().unwrap(), "lnbc2500u");
+
+        let hrp: Hrp = "lntbs".parse().unwrap();
+        assert_eq!(hrp, Hrp::new(Currency::BitcoinSignet, None));
+        assert_eq!(hrp.encode().unwrap(), "lntbs");
+
+        assert!(Hrp::parse("lnxx2500u").is_err());
+        assert!(Hrp::parse("lnbc25x").is_err());
+        assert!(Hrp::parse("lnbc1p").is_err());
+    }
+
+    #[test]
+    fn zero_or_any_amount() {
+        assert_eq!(Hrp::parse("lnbc").unwrap().amount, None);
+        assert_eq!(Hrp::parse("lnbc0").unwrap().amount, Some(MilliSatoshi::new(0)));
+        assert_eq!(Hrp::new(Currency::Bitcoin, None).encode().unwrap(), "lnbc");
+        assert!(Hrp::new(Currency::Bitcoin, Some(MilliSatoshi::new(0))).encode().is_err());
+        assert!(Hrp::parse("lnbc0").unwrap().encode().is_err());
+    }
+
+    #[test]
+    fn encoding_preserved() {
+        let hrp = Hrp::parse("lnbc2500000n").unwrap();
+        assert_eq!(hrp.encode().unwrap(), "lnbc2500000n");
+        assert_eq!(
+            Hrp::new(Currency::Bitcoin, hrp.amount).encode().unwrap(),
+            "lnbc2500u"
+        );
+    }
+}
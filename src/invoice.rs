@@ -0,0 +1,192 @@
+//! The signing lifecycle of payment requests as two types: `RawInvoice`, which is unsigned, and
+//! `SignedInvoice`, which can only be obtained with a valid signature of its node id, so
+//! unsigned or mis-signed payment requests can't be emitted by accident.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+
+use payment_request::PaymentRequest;
+use secp256k1;
+use signer::Bolt11Signer;
+use types::Error;
+use u5::U5;
+use utils::U5Conversions;
+
+/// An unsigned payment request. It can compute the digest to sign and its encoding without the
+/// signature, but can't be encoded as a payment request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawInvoice {
+    // always has an empty signature
+    request: PaymentRequest,
+}
+
+impl RawInvoice {
+    /// Return the payment request, without signature.
+    pub fn payment_request(&self) -> &PaymentRequest {
+        &self.request
+    }
+
+    /// Return the payment request to change it; signing it again is needed to emit it.
+    pub fn into_payment_request(self) -> PaymentRequest {
+        self.request
+    }
+
+    /// Return the human readable part, e.g. `lnbc2500u`.
+    pub fn hrp(&self) -> Result<String, Error> {
+        self.request.hrp()
+    }
+
+    /// Return the words of the data part without the signature: timestamp and tags.
+    pub fn data_part(&self) -> Result<Vec<U5>, Error> {
+        let mut stream = Vec::new();
+        self.request.write_stream(&mut stream)?;
+        Ok(stream)
+    }
+
+    /// Return the exact bytes whose SHA256 is signed: the human readable part followed by the
+    /// data part without signature, padded with zero bits to a byte boundary. External tooling
+    /// signing these bytes (not a message prefixed like `signmessage` does) produces a
+    /// signature for `attach_signature`.
+    pub fn signing_data(&self) -> Result<Vec<u8>, Error> {
+        let mut data = self.hrp()?.into_bytes();
+        data.extend(self.data_part()?.to_u8_vec(true));
+        Ok(data)
+    }
+
+    /// Return the SHA256 digest of `signing_data`, which the payee node signs.
+    pub fn signing_hash(&self) -> Result<[u8; 32], Error> {
+        self.request.signing_hash()
+    }
+
+    /// Sign with `signer`, failing if it doesn't hold the key of the node id.
+    /// # Params
+    /// `signer` The signer holding the payee node key.
+    pub fn sign_with<S: Bolt11Signer + ?Sized>(
+        &self,
+        signer: &S,
+    ) -> Result<SignedInvoice, Error> {
+        SignedInvoice::try_from(self.request.sign_with(signer)?)
+    }
+
+    /// Attach a signature made elsewhere, failing unless it is a valid signature of the node
+    /// id.
+    /// # Params
+    /// `signature` 64 bytes of compact signature followed by the recovery id.
+    pub fn attach_signature(self, signature: Vec<u8>) -> Result<SignedInvoice, Error> {
+        let mut request = self.request;
+        request.signature = signature;
+        SignedInvoice::try_from(request)
+    }
+}
+
+/// Drops the signature of `request`, if any.
+impl From<PaymentRequest> for RawInvoice {
+    fn from(mut request: PaymentRequest) -> RawInvoice {
+        request.signature = Vec::new();
+        RawInvoice { request }
+    }
+}
+
+/// A payment request carrying a valid signature of its node id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedInvoice {
+    request: PaymentRequest,
+}
+
+impl SignedInvoice {
+    /// Decode a payment request and check its signature.
+    pub fn decode(input: &str) -> Result<SignedInvoice, Error> {
+        SignedInvoice::try_from(PaymentRequest::decode(input)?)
+    }
+
+    /// Return the bech32 encoding of the payment request.
+    pub fn encode(&self) -> Result<String, Error> {
+        self.request.encode()
+    }
+
+    /// Return the signed payment request.
+    pub fn payment_request(&self) -> &PaymentRequest {
+        &self.request
+    }
+
+    /// Return the payment request without its signature, e.g. to change and sign it again.
+    pub fn into_raw(self) -> RawInvoice {
+        RawInvoice::from(self.request)
+    }
+}
+
+/// Fails with `Error::SignatureError` unless `request` is signed by its node id.
+impl TryFrom<PaymentRequest> for SignedInvoice {
+    type Error = Error;
+
+    fn try_from(request: PaymentRequest) -> Result<SignedInvoice, Error> {
+        if request.verify_any(&[request.node_id.clone()])?.is_none() {
+            return Err(Error::SignatureError(secp256k1::Error::InvalidSignature));
+        }
+        Ok(SignedInvoice { request })
+    }
+}
+
+impl From<SignedInvoice> for PaymentRequest {
+    fn from(invoice: SignedInvoice) -> PaymentRequest {
+        invoice.request
+    }
+}
+
+impl fmt::Display for SignedInvoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.request, f)
+    }
+}
+
+impl FromStr for SignedInvoice {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<SignedInvoice, Error> {
+        SignedInvoice::decode(input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bech32::u5_to_char;
+    use fixtures::sec_key;
+    use secp256k1::SecretKey;
+    use utils::sha256;
+
+    const TX_REF: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqyp\
+        qdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq7\
+        7w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+    #[test]
+    fn sign_and_attach() {
+        let signed = SignedInvoice::decode(TX_REF).unwrap();
+        let raw = signed.clone().into_raw();
+        assert!(raw.payment_request().signature.is_empty());
+        assert_eq!(
+            raw.signing_hash().unwrap().to_vec(),
+            signed.payment_request().hash().unwrap()
+        );
+        assert_eq!(sha256(&raw.signing_data().unwrap()), raw.signing_hash().unwrap());
+        assert!(raw.signing_data().unwrap().starts_with(b"lnbc2500u"));
+
+        let prefix = raw.hrp().unwrap()
+            + "1"
+            + &raw.data_part().unwrap().into_iter().map(u5_to_char).collect::<String>();
+        let resigned = raw.sign_with(&sec_key()).unwrap();
+        assert!(resigned.encode().unwrap().starts_with(&prefix));
+        assert_eq!(SignedInvoice::decode(&resigned.to_string()).unwrap(), resigned);
+
+        let other_key = SecretKey::parse(&[0x22; 32]).unwrap();
+        assert!(raw.sign_with(&other_key).is_err());
+
+        let signature = signed.payment_request().signature.clone();
+        assert_eq!(raw.clone().attach_signature(signature).unwrap(), signed);
+        assert!(raw.clone().attach_signature(vec![0; 65]).is_err());
+        assert!(raw.attach_signature(Vec::new()).is_err());
+    }
+}
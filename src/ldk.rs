@@ -0,0 +1,466 @@
+//! Conversions between payment requests and the types of rust-lightning's `lightning-invoice`
+//! crate, for projects moving from one library to the other. Fields are converted one by one,
+//! nothing is encoded to a string and parsed again.
+//!
+//! Route hints convert to `RouteHint`s on their own, so decoded payment requests can be fed to
+//! LDK's router directly, see `PaymentRequest::ldk_route_hints`.
+//!
+//! `lightning-invoice` only builds invoices it considers semantically valid, e.g. with a payment
+//! secret, so converting a `PaymentRequest` can fail where encoding it wouldn't.
+//!
+//! `lightning-invoice` keeps features as flags and always writes them minimally, so the raw
+//! 5-bit words kept by `Features` are lost. Converting a payment request whose features are
+//! padded with leading zero words fails, as its signature wouldn't match the re-encoded data.
+
+use amount::Amount;
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use bitcoin::secp256k1::PublicKey as LdkPublicKey;
+use bitcoin::util::address::WitnessVersion;
+use bitcoin::{PubkeyHash, ScriptHash};
+use core::convert::TryFrom;
+use currency::Currency;
+use features::Features;
+use lightning::ln::features::Bolt11InvoiceFeatures;
+use lightning::ln::PaymentSecret;
+use lightning::routing::gossip::RoutingFees;
+use lightning::routing::router::{RouteHint, RouteHintHop};
+use lightning_invoice::{
+    Bolt11Invoice, Currency as LdkCurrency, Description, ExpiryTime, Fallback,
+    MinFinalCltvExpiryDelta, PayeeNodeId, PositiveTimestamp, PrivateRoute, RawBolt11Invoice,
+    RawDataPart, RawHrp, RawTaggedField, Sha256, SiPrefix, TaggedField,
+};
+use msat::MilliSatoshi;
+use payment_request::PaymentRequest;
+use route::RoutingInfo;
+use scid::ShortChannelId;
+use secp256k1::PublicKey;
+use std::fmt;
+use tag::{ExtraHop, Tag};
+use types::Error;
+use u5::U5;
+
+/// Wrap an error of `lightning-invoice` or of its dependencies.
+fn ldk_error<E: fmt::Debug>(error: E) -> Error {
+    Error::InvalidValue(format!("lightning-invoice: {:?}", error))
+}
+
+fn to_ldk_key(key: &PublicKey) -> Result<LdkPublicKey, Error> {
+    LdkPublicKey::from_slice(&key.serialize_compressed()).map_err(ldk_error)
+}
+
+fn from_ldk_key(key: &LdkPublicKey) -> Result<PublicKey, Error> {
+    ExtraHop::parse_pub_key(&key.serialize())
+}
+
+fn to_ldk_hash(hash: &[u8]) -> Result<Sha256, Error> {
+    sha256::Hash::from_slice(hash).map(Sha256).map_err(ldk_error)
+}
+
+impl TryFrom<Currency> for LdkCurrency {
+    type Error = Error;
+
+    fn try_from(currency: Currency) -> Result<LdkCurrency, Error> {
+        match currency {
+            Currency::Bitcoin => Ok(LdkCurrency::Bitcoin),
+            Currency::BitcoinTestnet => Ok(LdkCurrency::BitcoinTestnet),
+            Currency::BitcoinSignet => Ok(LdkCurrency::Signet),
+            Currency::BitcoinRegtest => Ok(LdkCurrency::Regtest),
+            Currency::BitcoinSimnet => Ok(LdkCurrency::Simnet),
+            other => Err(Error::InvalidValue(format!(
+                "lightning-invoice doesn't support currency `{}`",
+                other
+            ))),
+        }
+    }
+}
+
+impl From<LdkCurrency> for Currency {
+    fn from(currency: LdkCurrency) -> Currency {
+        match currency {
+            LdkCurrency::Bitcoin => Currency::Bitcoin,
+            LdkCurrency::BitcoinTestnet => Currency::BitcoinTestnet,
+            LdkCurrency::Signet => Currency::BitcoinSignet,
+            LdkCurrency::Regtest => Currency::BitcoinRegtest,
+            LdkCurrency::Simnet => Currency::BitcoinSimnet,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a ExtraHop> for RouteHintHop {
+    type Error = Error;
+
+    fn try_from(hop: &ExtraHop) -> Result<RouteHintHop, Error> {
+        Ok(RouteHintHop {
+            src_node_id: to_ldk_key(&hop.pub_key)?,
+            short_channel_id: hop.short_channel_id.into(),
+            fees: RoutingFees {
+                base_msat: u32::try_from(hop.fee_base_msat.as_u64()).map_err(ldk_error)?,
+                proportional_millionths: hop.fee_proportional_millionths,
+            },
+            cltv_expiry_delta: hop.cltv_expiry_delta,
+            htlc_minimum_msat: None,
+            htlc_maximum_msat: None,
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a RouteHintHop> for ExtraHop {
+    type Error = Error;
+
+    /// The HTLC limits of the hop are dropped, BOLT11 has no room for them.
+    fn try_from(hop: &RouteHintHop) -> Result<ExtraHop, Error> {
+        Ok(ExtraHop {
+            pub_key: from_ldk_key(&hop.src_node_id)?,
+            short_channel_id: ShortChannelId::from(hop.short_channel_id),
+            fee_base_msat: MilliSatoshi::from(u64::from(hop.fees.base_msat)),
+            fee_proportional_millionths: hop.fees.proportional_millionths,
+            cltv_expiry_delta: hop.cltv_expiry_delta,
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a RoutingInfo> for RouteHint {
+    type Error = Error;
+
+    fn try_from(route: &RoutingInfo) -> Result<RouteHint, Error> {
+        route
+            .path
+            .iter()
+            .map(RouteHintHop::try_from)
+            .collect::<Result<Vec<RouteHintHop>, Error>>()
+            .map(RouteHint)
+    }
+}
+
+impl<'a> TryFrom<&'a RouteHint> for RoutingInfo {
+    type Error = Error;
+
+    fn try_from(route: &RouteHint) -> Result<RoutingInfo, Error> {
+        let path = route
+            .0
+            .iter()
+            .map(ExtraHop::try_from)
+            .collect::<Result<Vec<ExtraHop>, Error>>()?;
+        Ok(RoutingInfo { path })
+    }
+}
+
+impl PaymentRequest {
+    /// Return the private routes to the payee as LDK route hints, one per `'r'` field, e.g.
+    /// for `PaymentParameters::with_route_hints`.
+    pub fn ldk_route_hints(&self) -> Result<Vec<RouteHint>, Error> {
+        self.routes().iter().map(RouteHint::try_from).collect()
+    }
+}
+
+impl<'a> TryFrom<&'a Tag> for RawTaggedField {
+    type Error = Error;
+
+    fn try_from(tag: &Tag) -> Result<RawTaggedField, Error> {
+        let field = match *tag {
+            Tag::PaymentHash { ref hash } => TaggedField::PaymentHash(to_ldk_hash(hash)?),
+            Tag::PaymentSecret { secret } => TaggedField::PaymentSecret(PaymentSecret(secret)),
+            Tag::PayeeNodeId { ref pubkey } => {
+                TaggedField::PayeeNodeId(PayeeNodeId(to_ldk_key(pubkey)?))
+            }
+            Tag::Description { ref description } => TaggedField::Description(
+                Description::new(description.to_owned()).map_err(ldk_error)?,
+            ),
+            Tag::DescriptionHash { ref hash } => TaggedField::DescriptionHash(to_ldk_hash(hash)?),
+            Tag::FallbackAddress { version, ref hash } => {
+                TaggedField::Fallback(match version {
+                    17 => Fallback::PubKeyHash(PubkeyHash::from_slice(hash).map_err(ldk_error)?),
+                    18 => Fallback::ScriptHash(ScriptHash::from_slice(hash).map_err(ldk_error)?),
+                    version => Fallback::SegWitProgram {
+                        version: WitnessVersion::try_from(version).map_err(ldk_error)?,
+                        program: hash.to_owned(),
+                    },
+                })
+            }
+            Tag::Expiry { seconds } => TaggedField::ExpiryTime(ExpiryTime::from_seconds(seconds)),
+            Tag::MinFinalCltvExpiry { blocks } => {
+                TaggedField::MinFinalCltvExpiryDelta(MinFinalCltvExpiryDelta(blocks))
+            }
+            Tag::RoutingInfo { ref path } => {
+                let route = RouteHint::try_from(&RoutingInfo {
+                    path: path.to_owned(),
+                })?;
+                TaggedField::PrivateRoute(PrivateRoute::new(route).map_err(ldk_error)?)
+            }
+            Tag::Metadata { ref bytes } => TaggedField::PaymentMetadata(bytes.to_owned()),
+            Tag::Features { ref features } => {
+                if features.to_raw_u5_vec() != features.to_u5_vec() {
+                    return Err(Error::InvalidValue(
+                        "lightning-invoice can't keep the padding of features".to_owned(),
+                    ));
+                }
+                let mut flags = Vec::new();
+                for bit in features.iter() {
+                    if flags.len() <= bit / 8 {
+                        flags.resize(bit / 8 + 1, 0u8);
+                    }
+                    flags[bit / 8] |= 1 << (bit % 8);
+                }
+                TaggedField::Features(Bolt11InvoiceFeatures::from_le_bytes(flags))
+            }
+            Tag::UnknownTag { .. } => {
+                // fields of unknown semantics are kept whole, header included
+                let words = tag
+                    .to_vec_u5()?
+                    .into_iter()
+                    .map(|word| TryFrom::try_from(word.to_u8()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ldk_error)?;
+                return Ok(RawTaggedField::UnknownSemantics(words));
+            }
+        };
+        Ok(RawTaggedField::KnownSemantics(field))
+    }
+}
+
+impl TryFrom<RawTaggedField> for Tag {
+    type Error = Error;
+
+    fn try_from(field: RawTaggedField) -> Result<Tag, Error> {
+        let field = match field {
+            RawTaggedField::KnownSemantics(field) => field,
+            RawTaggedField::UnknownSemantics(words) => {
+                let words = words.iter().map(|word| word.to_u8()).collect::<Vec<u8>>();
+                return Tag::parse(&U5::from_slice(&words)?);
+            }
+        };
+        let tag = match field {
+            TaggedField::PaymentHash(Sha256(hash)) => Tag::PaymentHash {
+                hash: hash[..].to_vec(),
+            },
+            TaggedField::Description(description) => Tag::Description {
+                description: description.into_inner(),
+            },
+            TaggedField::PayeeNodeId(PayeeNodeId(pubkey)) => Tag::PayeeNodeId {
+                pubkey: from_ldk_key(&pubkey)?,
+            },
+            TaggedField::DescriptionHash(Sha256(hash)) => Tag::DescriptionHash {
+                hash: hash[..].to_vec(),
+            },
+            TaggedField::ExpiryTime(expiry) => Tag::Expiry {
+                seconds: expiry.as_seconds(),
+            },
+            TaggedField::MinFinalCltvExpiryDelta(MinFinalCltvExpiryDelta(blocks)) => {
+                Tag::MinFinalCltvExpiry { blocks }
+            }
+            TaggedField::Fallback(Fallback::PubKeyHash(hash)) => Tag::FallbackAddress {
+                version: 17,
+                hash: hash[..].to_vec(),
+            },
+            TaggedField::Fallback(Fallback::ScriptHash(hash)) => Tag::FallbackAddress {
+                version: 18,
+                hash: hash[..].to_vec(),
+            },
+            TaggedField::Fallback(Fallback::SegWitProgram { version, program }) => {
+                Tag::FallbackAddress {
+                    version: version.to_num(),
+                    hash: program,
+                }
+            }
+            TaggedField::PrivateRoute(route) => Tag::RoutingInfo {
+                path: RoutingInfo::try_from(&route.into_inner())?.path,
+            },
+            TaggedField::PaymentSecret(PaymentSecret(secret)) => Tag::PaymentSecret { secret },
+            TaggedField::PaymentMetadata(bytes) => Tag::Metadata { bytes },
+            TaggedField::Features(features) => {
+                let flags = features.le_flags();
+                let bits = (0..flags.len() * 8).filter(|bit| flags[bit / 8] & 1 << (bit % 8) != 0);
+                Tag::Features {
+                    features: Features::from_bits(bits),
+                }
+            }
+        };
+        Ok(tag)
+    }
+}
+
+impl<'a> TryFrom<&'a PaymentRequest> for Bolt11Invoice {
+    type Error = Error;
+
+    /// Fails if `lightning-invoice` doesn't accept the payment request, e.g. because it has
+    /// no payment secret.
+    fn try_from(pay_request: &PaymentRequest) -> Result<Bolt11Invoice, Error> {
+        let currency = Currency::from_prefix(&pay_request.prefix).ok_or_else(|| {
+            Error::InvalidValue(format!("unknown currency prefix `{}`", pay_request.prefix))
+        })?;
+        let (raw_amount, si_prefix) = match pay_request.amount {
+            Some(amount) => {
+                let encoded =
+                    Amount::encode_with(amount.as_u64(), pay_request.amount_encoding)?;
+                let digits = encoded.trim_end_matches(|c: char| !c.is_ascii_digit());
+                let si_prefix = match &encoded[digits.len()..] {
+                    "m" => Some(SiPrefix::Milli),
+                    "u" => Some(SiPrefix::Micro),
+                    "n" => Some(SiPrefix::Nano),
+                    "p" => Some(SiPrefix::Pico),
+                    _ => None,
+                };
+                (Some(digits.parse::<u64>()?), si_prefix)
+            }
+            None => (None, None),
+        };
+        let tagged_fields = pay_request
+            .tags
+            .iter()
+            .map(RawTaggedField::try_from)
+            .collect::<Result<Vec<RawTaggedField>, Error>>()?;
+        let raw_invoice = RawBolt11Invoice {
+            hrp: RawHrp {
+                currency: LdkCurrency::try_from(currency)?,
+                raw_amount,
+                si_prefix,
+            },
+            data: RawDataPart {
+                timestamp: PositiveTimestamp::from_unix_timestamp(pay_request.timestamp)
+                    .map_err(ldk_error)?,
+                tagged_fields,
+            },
+        };
+        let signature = &pay_request.signature;
+        if signature.len() != 65 {
+            return Err(Error::InvalidSignatureLength(signature.len()));
+        }
+        let recovery_id = RecoveryId::from_i32(i32::from(signature[64])).map_err(ldk_error)?;
+        let signature =
+            RecoverableSignature::from_compact(&signature[..64], recovery_id).map_err(ldk_error)?;
+        let signed = raw_invoice.sign(|_| Ok::<RecoverableSignature, Error>(signature))?;
+        Bolt11Invoice::from_signed(signed).map_err(ldk_error)
+    }
+}
+
+impl TryFrom<Bolt11Invoice> for PaymentRequest {
+    type Error = Error;
+
+    fn try_from(invoice: Bolt11Invoice) -> Result<PaymentRequest, Error> {
+        let node_id = from_ldk_key(&invoice.recover_payee_pub_key())?;
+        let (raw_invoice, _, signature) = invoice.into_signed_raw().into_parts();
+        let (recovery_id, compact) = signature.0.serialize_compact();
+        let mut signature = compact.to_vec();
+        signature.push(recovery_id.to_i32() as u8);
+
+        let currency = Currency::from(raw_invoice.hrp.currency);
+        let (amount, amount_encoding) = match raw_invoice.hrp.raw_amount {
+            Some(raw_amount) => {
+                let multiplier = match raw_invoice.hrp.si_prefix {
+                    Some(SiPrefix::Milli) => "m",
+                    Some(SiPrefix::Micro) => "u",
+                    Some(SiPrefix::Nano) => "n",
+                    Some(SiPrefix::Pico) => "p",
+                    None => "",
+                };
+                let encoded = format!("{}{}", raw_amount, multiplier);
+                (
+                    Some(MilliSatoshi::new(Amount::decode(&encoded)?)),
+                    Amount::encoding_of(&encoded),
+                )
+            }
+            None => (None, Default::default()),
+        };
+        let tags = raw_invoice
+            .data
+            .tagged_fields
+            .into_iter()
+            .map(Tag::try_from)
+            .collect::<Result<Vec<Tag>, Error>>()?;
+        Ok(PaymentRequest {
+            prefix: currency.prefix().to_owned(),
+            amount,
+            amount_encoding,
+            timestamp: raw_invoice.data.timestamp.as_unix_timestamp(),
+            node_id,
+            tags,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use builder::PaymentRequestBuilder;
+    use secp256k1::SecretKey;
+
+    #[test]
+    fn round_trip() {
+        let secret_key = SecretKey::parse(&[7u8; 32]).unwrap();
+        let hop_key = PublicKey::from_secret_key(&SecretKey::parse(&[8u8; 32]).unwrap());
+        let hop = ExtraHop {
+            pub_key: hop_key.clone(),
+            short_channel_id: ShortChannelId::from(72623859790382856),
+            fee_base_msat: MilliSatoshi::from(1),
+            fee_proportional_millionths: 20,
+            cltv_expiry_delta: 3,
+        };
+        let mut pay_request = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .amount(MilliSatoshi::new(250_000_000))
+            .payment_hash(vec![1u8; 32])
+            .payment_secret([2u8; 32])
+            .description("coffee".to_owned())
+            .fallback_address("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T".to_owned())
+            .extra_hops(vec![hop])
+            .timestamp(1496314658)
+            .build(&secret_key)
+            .unwrap();
+        pay_request.tags.push(Tag::Features {
+            features: Features::from_bits(vec![8, 15, 101]),
+        });
+        pay_request.tags.push(Tag::UnknownTag {
+            tag: U5::new(0).unwrap(),
+            bytes: vec![U5::MAX; 4],
+        });
+        let pay_request = pay_request.sign(&secret_key).unwrap();
+
+        let hints = pay_request.ldk_route_hints().unwrap();
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].0[0].src_node_id.serialize(), hop_key.serialize_compressed());
+        assert_eq!(hints[0].0[0].fees.proportional_millionths, 20);
+        assert_eq!(RoutingInfo::try_from(&hints[0]).unwrap(), pay_request.routes()[0]);
+
+        let invoice = Bolt11Invoice::try_from(&pay_request).unwrap();
+        assert_eq!(invoice.to_string(), pay_request.encode().unwrap());
+        let converted = PaymentRequest::try_from(invoice).unwrap();
+        // `Features` equality ignores the raw words, the encodings must match too
+        assert_eq!(converted.encode().unwrap(), pay_request.encode().unwrap());
+        assert_eq!(converted, pay_request);
+
+        let mut no_secret = pay_request.clone();
+        no_secret.tags.retain(|tag| !matches!(*tag, Tag::PaymentSecret { .. }));
+        let no_secret = no_secret.sign(&secret_key).unwrap();
+        assert!(Bolt11Invoice::try_from(&no_secret).is_err());
+    }
+
+    #[test]
+    fn padded_features() {
+        let secret_key = SecretKey::parse(&[7u8; 32]).unwrap();
+        let mut pay_request = PaymentRequestBuilder::new(Currency::Bitcoin)
+            .payment_hash(vec![1u8; 32])
+            .payment_secret([2u8; 32])
+            .description("coffee".to_owned())
+            .timestamp(1496314658)
+            .build(&secret_key)
+            .unwrap();
+        let features = Features::from_bits(vec![8, 14]);
+        pay_request.tags.push(Tag::Features {
+            features: features.clone(),
+        });
+        let minimal = pay_request.sign(&secret_key).unwrap();
+        let invoice = Bolt11Invoice::try_from(&minimal).unwrap();
+        assert_eq!(invoice.to_string(), minimal.encode().unwrap());
+
+        let mut words = vec![U5::new(0).unwrap()];
+        words.extend(features.to_u5_vec());
+        pay_request.tags.pop();
+        pay_request.tags.push(Tag::Features {
+            features: Features::from_u5(&words),
+        });
+        let padded = pay_request.sign(&secret_key).unwrap();
+        assert_ne!(padded.encode().unwrap(), minimal.encode().unwrap());
+        assert!(Bolt11Invoice::try_from(&padded).is_err());
+    }
+}
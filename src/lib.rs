@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 #![deny(non_upper_case_globals)]
 #![deny(non_camel_case_types)]
@@ -9,25 +10,140 @@
 //! This is an implementation of
 //! [Lightning BOLT11 Specification](https://github.com/lightningnetwork/lightning-rfc/blob/master/11-payment-encoding.md)
 //! for providing a minimal QR-code-ready format for requesting lightning payments.
+//!
+//! Without the default `std` feature the crate only needs `alloc`: fallback addresses can't be
+//! parsed or rendered, the current time is unknown and the `scan`, `uri` and `cache` modules
+//! are left out.
 
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "fuzz")]
+extern crate arbitrary;
+#[cfg(feature = "std")]
 extern crate base58check;
+#[cfg(feature = "bitcoin")]
+extern crate bitcoin;
+#[cfg(feature = "std")]
 extern crate bitcoin_bech32;
 extern crate byteorder;
-extern crate crypto;
-extern crate hex;
-extern crate itertools;
-#[macro_use]
-extern crate lazy_static;
-extern crate num;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "ldk")]
+extern crate lightning;
+#[cfg(feature = "ldk")]
+extern crate lightning_invoice;
 extern crate secp256k1;
+#[cfg(feature = "probe")]
+extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "schemars")]
+extern crate schemars;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "wasm")]
+extern crate serde_wasm_bindgen;
+extern crate sha2;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+extern crate zeroize;
 
 #[macro_use]
 mod macros;
-mod timestamp;
 mod utils;
-mod amount;
 mod bech32;
+#[cfg(feature = "serde")]
+mod hex_serde;
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "fuzz")]
+mod fuzz;
 
+pub mod consts;
+pub mod hex;
 pub mod types;
+pub mod u5;
+pub mod amount;
 pub mod tag;
+pub mod custom_tag;
+pub mod timestamp;
+pub mod tag_set;
+pub mod route;
+pub mod signature;
+pub mod signer;
 pub mod payment_request;
+pub mod invoice;
+#[cfg(feature = "std")]
+pub mod scan;
+#[cfg(feature = "std")]
+pub mod uri;
+#[cfg(feature = "std")]
+pub mod cache;
+pub mod tlv;
+pub mod features;
+pub mod spans;
+pub mod alias;
+pub mod currency;
+pub mod codec;
+pub mod scid;
+pub mod msat;
+pub mod builder;
+pub mod expiry;
+pub mod hrp;
+pub mod redact;
+#[cfg(feature = "json")]
+pub mod channels;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "ldk")]
+pub mod ldk;
+#[cfg(feature = "json")]
+pub mod lnd;
+#[cfg(feature = "probe")]
+pub mod probe;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "std")]
+pub use scan::{classify, PaymentStringKind};
+
+/// Compile-time check that the public types can be shared across threads, e.g. between the
+/// tasks of a payment server.
+#[allow(dead_code)]
+fn assert_send_sync() {
+    fn assert<T: Send + Sync>() {}
+    assert::<amount::AmountEncoding>();
+    assert::<builder::PaymentRequestBuilder>();
+    #[cfg(feature = "std")]
+    assert::<cache::InvoiceCache>();
+    assert::<codec::Decoder>();
+    assert::<codec::Encoder>();
+    assert::<currency::Currency>();
+    assert::<expiry::ExpiryGrace>();
+    assert::<features::Feature>();
+    assert::<features::Features>();
+    assert::<hrp::Hrp>();
+    assert::<msat::MilliSatoshi>();
+    assert::<payment_request::PaymentRequest>();
+    assert::<route::RouteHintBuilder>();
+    assert::<route::RoutingInfo>();
+    #[cfg(feature = "probe")]
+    assert::<probe::ProbeInvoice>();
+    assert::<scid::ShortChannelId>();
+    assert::<spans::ParseTree>();
+    assert::<tag::ExtraHop>();
+    assert::<tag::Tag>();
+    assert::<tag::TagReader<'static>>();
+    assert::<tag_set::TagSet>();
+    assert::<tlv::TlvRecord>();
+    assert::<types::Error>();
+    assert::<u5::U5>();
+    #[cfg(feature = "std")]
+    assert::<uri::PaymentUri>();
+}
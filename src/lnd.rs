@@ -0,0 +1,158 @@
+//! Payment requests in the JSON shape of lnd's `DecodePayReq` response.
+//!
+//! Dashboards built around lnd's REST API can switch to this crate as their data source without
+//! changes: field names are the same, 64-bit integers are strings, bytes are base64 and unset
+//! fields are present with their default value, as lnd's REST proxy writes them.
+
+use features::Feature;
+use msat::Rounding;
+use payment_request::PaymentRequest;
+use serde_json::{json, Map, Value};
+use utils::to_hex;
+
+/// Name lnd gives to `feature`.
+fn lnd_feature_name(feature: Feature) -> &'static str {
+    match feature {
+        Feature::VarOnionOptin => "tlv-onion",
+        Feature::PaymentSecret => "payment-addr",
+        Feature::BasicMpp => "multi-path-payments",
+        Feature::PaymentMetadata => "payment-metadata",
+    }
+}
+
+/// Standard base64 with padding, how protobuf's JSON mapping writes bytes.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, byte)| group | u32::from(*byte) << (16 - 8 * index));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+impl PaymentRequest {
+    /// Return this payment request as lnd's `DecodePayReq` REST endpoint would, i.e. the JSON
+    /// mapping of `lnrpc.PayReq`. Expiry and cltv expiry default as in lnd.
+    pub fn to_lnd_pay_req(&self) -> Value {
+        let amount_msat = self.amount.map_or(0, |amount| amount.as_u64());
+        let num_satoshis = self.amount_msat()
+            .and_then(|amount| amount.to_sat(Rounding::Floor).ok())
+            .unwrap_or(0);
+        let route_hints = self.routes()
+            .iter()
+            .map(|route| {
+                let hop_hints = route
+                    .path
+                    .iter()
+                    .map(|hop| {
+                        json!({
+                            "node_id": to_hex(&hop.pub_key_bytes()),
+                            "chan_id": hop.short_channel_id.as_u64().to_string(),
+                            "fee_base_msat": hop.fee_base_msat.as_u64(),
+                            "fee_proportional_millionths": hop.fee_proportional_millionths,
+                            "cltv_expiry_delta": hop.cltv_expiry_delta,
+                        })
+                    })
+                    .collect::<Vec<Value>>();
+                json!({ "hop_hints": hop_hints })
+            })
+            .collect::<Vec<Value>>();
+        let mut features = Map::new();
+        for bit in self.features().map(|f| f.iter().collect()).unwrap_or_else(Vec::new) {
+            let feature = Feature::from_bit(bit);
+            features.insert(
+                bit.to_string(),
+                json!({
+                    "name": feature.map_or("unknown", lnd_feature_name),
+                    "is_required": Feature::is_required_bit(bit),
+                    "is_known": feature.is_some(),
+                }),
+            );
+        }
+        json!({
+            "destination": to_hex(&self.node_id.serialize_compressed()),
+            "payment_hash": self.payment_hash().map(|h| to_hex(&h)).unwrap_or_default(),
+            "num_satoshis": num_satoshis.to_string(),
+            "timestamp": self.timestamp.to_string(),
+            "expiry": self.expiry_or_default().to_string(),
+            "description": self.description().unwrap_or_default(),
+            "description_hash": self.description_hash().map(|h| to_hex(&h)).unwrap_or_default(),
+            "fallback_addr": self.fallback_address().unwrap_or_default(),
+            "cltv_expiry": self.min_final_cltv_expiry_or_default().to_string(),
+            "route_hints": route_hints,
+            "payment_addr": self.payment_secret().map(|s| base64(&s)).unwrap_or_default(),
+            "num_msat": amount_msat.to_string(),
+            "features": features,
+            "blinded_paths": [],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff; 4]), "/////w==");
+    }
+
+    #[test]
+    fn pay_req() {
+        // BOLT11 test vector with a fallback address and route hints
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+            58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+            9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+            qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+            f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+            dhhwkj";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        pay_request.update_payment_secret([0x11; 32]);
+        let mut features = ::features::Features::new();
+        features.set_optional(Feature::PaymentSecret);
+        features.set(100);
+        pay_request.update_features(features);
+
+        let pay_req = pay_request.to_lnd_pay_req();
+        assert_eq!(
+            pay_req["destination"],
+            "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"
+        );
+        assert_eq!(pay_req["num_satoshis"], "2000000");
+        assert_eq!(pay_req["num_msat"], "2000000000");
+        assert_eq!(pay_req["timestamp"], "1496314658");
+        assert_eq!(pay_req["expiry"], "3600");
+        assert_eq!(pay_req["cltv_expiry"], "18");
+        assert_eq!(pay_req["description"], "");
+        assert_eq!(pay_req["fallback_addr"], "1RustyRX2oai4EYYDpQGWvEL62BBGqN9T");
+        assert_eq!(pay_req["payment_addr"], base64(&[0x11; 32]));
+        let hop = &pay_req["route_hints"][0]["hop_hints"][1];
+        assert_eq!(
+            hop["node_id"],
+            "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"
+        );
+        assert_eq!(hop["chan_id"], "217304205466536202");
+        assert_eq!(hop["fee_base_msat"], 2);
+        assert_eq!(hop["fee_proportional_millionths"], 30);
+        assert_eq!(hop["cltv_expiry_delta"], 4);
+        assert_eq!(pay_req["features"]["15"]["name"], "payment-addr");
+        assert_eq!(pay_req["features"]["15"]["is_required"], false);
+        assert_eq!(pay_req["features"]["100"]["is_known"], false);
+        assert_eq!(pay_req["features"]["100"]["is_required"], true);
+    }
+}
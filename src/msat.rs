@@ -0,0 +1,172 @@
+//! Millisatoshi amounts.
+
+use alloc::string::String;
+use amount::Amount;
+use core::fmt;
+use types::Error;
+
+/// Number of millisatoshis in a satoshi.
+const MSAT_PER_SAT: u64 = 1000;
+
+/// Where the sub-satoshi remainder goes when converting millisatoshis to satoshis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Round down, dropping the remainder.
+    Floor,
+    /// Round up to the next satoshi.
+    Ceil,
+    /// Fail unless the amount is a whole number of satoshis.
+    Exact,
+}
+
+/// An amount in millisatoshis, the unit of amounts and fees in the Lightning Network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MilliSatoshi(u64);
+
+impl MilliSatoshi {
+    /// Create an amount of `msat` millisatoshis.
+    pub const fn new(msat: u64) -> MilliSatoshi {
+        MilliSatoshi(msat)
+    }
+
+    /// Return the amount in millisatoshis.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Create an amount of `sat` satoshis, failing on overflow.
+    pub fn from_sat(sat: u64) -> Result<MilliSatoshi, Error> {
+        sat.checked_mul(MSAT_PER_SAT)
+            .map(MilliSatoshi)
+            .ok_or_else(|| Error::InvalidValue(format!("{} sat overflows", sat)))
+    }
+
+    /// Return the amount in satoshis, rounded as requested.
+    /// # Params
+    /// `rounding` What to do with the sub-satoshi remainder.
+    pub fn to_sat(&self, rounding: Rounding) -> Result<u64, Error> {
+        let (sat, remainder) = (self.0 / MSAT_PER_SAT, self.0 % MSAT_PER_SAT);
+        match rounding {
+            _ if remainder == 0 => Ok(sat),
+            Rounding::Floor => Ok(sat),
+            Rounding::Ceil => Ok(sat + 1),
+            Rounding::Exact => Err(Error::InvalidValue(format!(
+                "{} is not a whole number of satoshis",
+                self
+            ))),
+        }
+    }
+
+    /// Parse a decimal amount of bitcoins, e.g. `"0.00025"`, see `Amount::from_btc_str`.
+    pub fn from_btc_str(btc: &str) -> Result<MilliSatoshi, Error> {
+        Amount::from_btc_str(btc).map(MilliSatoshi)
+    }
+
+    /// Format as a decimal amount of bitcoins, e.g. `"0.00025"`.
+    pub fn to_btc_string(&self) -> String {
+        Amount::to_btc_string(self.0)
+    }
+
+    /// Add `other`, `None` on overflow.
+    pub fn checked_add(&self, other: MilliSatoshi) -> Option<MilliSatoshi> {
+        self.0.checked_add(other.0).map(MilliSatoshi)
+    }
+
+    /// Subtract `other`, `None` if it is larger than this amount.
+    pub fn checked_sub(&self, other: MilliSatoshi) -> Option<MilliSatoshi> {
+        self.0.checked_sub(other.0).map(MilliSatoshi)
+    }
+
+    /// Multiply by `factor`, `None` on overflow.
+    pub fn checked_mul(&self, factor: u64) -> Option<MilliSatoshi> {
+        self.0.checked_mul(factor).map(MilliSatoshi)
+    }
+
+    /// Add `other`, stopping at the largest amount.
+    pub fn saturating_add(&self, other: MilliSatoshi) -> MilliSatoshi {
+        MilliSatoshi(self.0.saturating_add(other.0))
+    }
+
+    /// Subtract `other`, stopping at zero.
+    pub fn saturating_sub(&self, other: MilliSatoshi) -> MilliSatoshi {
+        MilliSatoshi(self.0.saturating_sub(other.0))
+    }
+
+    /// Multiply by `factor`, stopping at the largest amount.
+    pub fn saturating_mul(&self, factor: u64) -> MilliSatoshi {
+        MilliSatoshi(self.0.saturating_mul(factor))
+    }
+}
+
+impl From<u64> for MilliSatoshi {
+    fn from(msat: u64) -> MilliSatoshi {
+        MilliSatoshi(msat)
+    }
+}
+
+impl From<MilliSatoshi> for u64 {
+    fn from(msat: MilliSatoshi) -> u64 {
+        msat.0
+    }
+}
+
+impl fmt::Display for MilliSatoshi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} msat", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sat_conversions() {
+        let amount = MilliSatoshi::new(2_500_001);
+        assert_eq!(amount.to_sat(Rounding::Floor).unwrap(), 2500);
+        assert_eq!(amount.to_sat(Rounding::Ceil).unwrap(), 2501);
+        assert!(amount.to_sat(Rounding::Exact).is_err());
+
+        let amount = MilliSatoshi::from_sat(2500).unwrap();
+        assert_eq!(amount, MilliSatoshi::new(2_500_000));
+        for rounding in vec![Rounding::Floor, Rounding::Ceil, Rounding::Exact] {
+            assert_eq!(amount.to_sat(rounding).unwrap(), 2500);
+        }
+        assert_eq!(
+            MilliSatoshi::new(u64::max_value()).to_sat(Rounding::Ceil).unwrap(),
+            u64::max_value() / 1000 + 1
+        );
+        assert!(MilliSatoshi::from_sat(u64::max_value()).is_err());
+    }
+
+    #[test]
+    fn btc_conversions() {
+        let amount = MilliSatoshi::from_btc_str("0.00025").unwrap();
+        assert_eq!(amount, MilliSatoshi::new(25_000_000));
+        assert_eq!(amount.to_btc_string(), "0.00025");
+        assert_eq!(MilliSatoshi::new(1).to_btc_string(), "0.00000000001");
+        assert!(MilliSatoshi::from_btc_str("0.000000000001").is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let (one, two, max) = (
+            MilliSatoshi::new(1),
+            MilliSatoshi::new(2),
+            MilliSatoshi::new(u64::max_value()),
+        );
+        assert_eq!(one.checked_add(two), Some(MilliSatoshi::new(3)));
+        assert_eq!(max.checked_add(one), None);
+        assert_eq!(two.checked_sub(one), Some(one));
+        assert_eq!(one.checked_sub(two), None);
+        assert_eq!(two.checked_mul(3), Some(MilliSatoshi::new(6)));
+        assert_eq!(max.checked_mul(2), None);
+
+        assert_eq!(max.saturating_add(one), max);
+        assert_eq!(one.saturating_sub(two), MilliSatoshi::new(0));
+        assert_eq!(max.saturating_mul(2), max);
+        assert_eq!(two.saturating_mul(3), MilliSatoshi::new(6));
+    }
+}
@@ -1,41 +1,84 @@
 //! Represents a decoded or to be encoded payment request
 
-use bech32::{Bech32, create_checksum as bech32_checksum, CHARSET};
-use tag::{ExtraHop, Tag};
+use bech32;
+use bech32::{create_checksum as bech32_checksum, u5_to_char};
+use codec::DecodeOptions;
+use consts::{SIGNATURE_BYTES, SIGNATURE_LENGTH, TIMESTAMP_LENGTH};
+use features::{Feature, Features};
+use msat::{MilliSatoshi, Rounding};
+use route::RoutingInfo;
+use signature;
+use signature::{is_low_s, normalize_s};
+use signer::Bolt11Signer;
+#[cfg(feature = "async")]
+use signer::{AsyncBolt11Signer, SignatureFuture};
+use tag::{ExtraHop, Tag, TagVisitor};
+use tag_set::TagSet;
+use tlv::TlvRecord;
 use timestamp::Timestamp;
 use types::Error;
-use utils::{U5, U5Conversions, U8Conversions};
+use u5::U5;
+use utils::{constant_time_eq, sha256, to_hex, U5Conversions, U8Conversions};
 use secp256k1;
 use secp256k1::{Message, PublicKey, RecoveryId, SecretKey, Signature};
-use crypto::sha2::Sha256;
-use crypto::digest::Digest;
-use amount::Amount;
-use std::fmt;
+use alias::AliasResolver;
+use amount::{Amount, AmountEncoding};
+use currency::Currency;
+use hrp::Hrp;
+use expiry;
+use expiry::{ExpiryGrace, DEFAULT_EXPIRY_SECONDS, DEFAULT_MIN_FINAL_CLTV_EXPIRY};
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::slice;
+use core::str::FromStr;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "async")]
+use core::future::Future;
+#[cfg(feature = "async")]
+use core::pin::Pin;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+#[cfg(feature = "chrono")]
+use core::convert::TryFrom;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, TimeZone, Utc};
+#[cfg(feature = "std")]
 use base58check::*;
-use itertools::Itertools;
+#[cfg(feature = "std")]
 use bitcoin_bech32::WitnessProgram;
-use bitcoin_bech32::constants::Network;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Lightning Payment Request
 /// *see* [Lightning RFC](https://github.com/lightningnetwork/lightning-rfc/blob/master/11-payment-encoding.md)
 ///
 /// Represents a decoded or to be encoded payment request.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PaymentRequest {
     /// Specifies what network this Lightning payment request is meant for
     /// lnbc for bitcoin, lntb for bitcoin testnet.
     pub prefix: String,
     /// Amount to pay in millisatoshis. Donation addresses often don't have an associated amount,
     /// so amount is optional in that case.
-    pub amount: Option<u64>,
+    pub amount: Option<MilliSatoshi>,
+    /// How the amount is written in the human readable part. Decoded payment requests keep the
+    /// representation chosen by their issuer, so they re-encode to the same string.
+    pub amount_encoding: AmountEncoding,
     /// Request timestamp (UNIX format).
     pub timestamp: u64,
     /// Id of the node emitting the payment request.
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    #[cfg_attr(feature = "serde", serde(with = "::hex_serde::pub_key"))]
     pub node_id: PublicKey,
     /// Payment tags; must include a single PaymentHash tag.
     pub tags: Vec<Tag>,
     /// Request signature that will be checked against node id.
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    #[cfg_attr(feature = "serde", serde(with = "::hex_serde::bytes"))]
     pub signature: Vec<u8>,
 }
 
@@ -57,7 +100,37 @@ impl PaymentRequest {
     /// `input` The encoded payment request.
     ///
     pub fn decode(input: &str) -> Result<PaymentRequest, Error> {
-        let Bech32 { hrp, mut data } = Bech32::from_string(input.to_owned())?;
+        PaymentRequest::decode_with_options(input, &DecodeOptions::default())
+    }
+
+    /// Decode a payment request, checking it as strictly as `options` asks.
+    ///
+    /// # Params
+    /// `input` The encoded payment request.
+    /// `options` Maximum accepted input length and strictness.
+    pub fn decode_with_options(
+        input: &str,
+        options: &DecodeOptions,
+    ) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode_buffers(input, options, &mut String::new(), &mut Vec::new())
+    }
+
+    /// Decode a payment request using the given buffers for the human readable part and the
+    /// 5-bit words of the data part, so they can be reused across calls.
+    pub(crate) fn decode_buffers(
+        input: &str,
+        options: &DecodeOptions,
+        hrp: &mut String,
+        data: &mut Vec<U5>,
+    ) -> Result<PaymentRequest, Error> {
+        options.check_length(input)?;
+        bech32::decode_into(input, hrp, data)?;
+        let Hrp {
+            currency,
+            amount,
+            amount_encoding,
+        } = Hrp::parse_with(hrp, &options.currencies)?;
+        let prefix = currency.prefix().to_owned();
 
         match data.len() {
             // 65 bytes signature length (65 + 7) * 8 / 5 = 104
@@ -65,24 +138,39 @@ impl PaymentRequest {
                 "data is too short to decode".to_owned(),
             )),
             len => {
-                let signature_bytes = data.split_off(len - 104).to_u8_vec(false)?;
+                let signature_start = len - SIGNATURE_LENGTH;
+                let signature_bytes = data[signature_start..].to_u8_vec(false);
 
-                let message = PaymentRequest::parse_message(&hrp, &data.to_u8_vec(true)?);
+                let message_bytes = data[..signature_start].to_u8_vec(true);
+                let message = PaymentRequest::parse_message(hrp, &message_bytes);
 
-                let timestamp = Timestamp::decode(&data.drain(..7).collect::<Vec<_>>());
-                let tags = Tag::parse_all(&data)?;
+                let timestamp = Timestamp::decode(&data[..signature_start])?;
+                let tags = options
+                    .parse_tags(&data[TIMESTAMP_LENGTH..signature_start], TIMESTAMP_LENGTH)?;
+                options.check(&tags)?;
+                options.check_signature(&signature_bytes)?;
 
                 let (recovery_id, signature) = PaymentRequest::parse_signature(&signature_bytes)?;
 
-                let node_id = secp256k1::recover(&message, &signature, &recovery_id)?;
+                // an explicit payee node id is checked against the signature, otherwise the
+                // node id is recovered from it
+                let payee_node_id = tags.iter()
+                    .filter_map(|t| match *t {
+                        Tag::PayeeNodeId { ref pubkey } => Some(pubkey.clone()),
+                        _ => None,
+                    })
+                    .next();
+                let node_id = match payee_node_id {
+                    Some(pubkey) => pubkey,
+                    None => secp256k1::recover(&message, &signature, &recovery_id)?,
+                };
 
-                let prefix = hrp[..4].to_owned();
-                let amount = hrp.get(4..).and_then(|u| Amount::decode(u).ok());
                 let valid_signature = secp256k1::verify(&message, &signature, &node_id);
                 if valid_signature {
                     Ok(PaymentRequest {
                         prefix,
                         amount,
+                        amount_encoding,
                         timestamp,
                         node_id,
                         tags,
@@ -97,53 +185,398 @@ impl PaymentRequest {
 
     /// Returns the encoded representation of a bech32 payment request.
     pub fn encode(&self) -> Result<String, Error> {
-        let hr_amount = self.amount.map_or(String::new(), |a| Amount::encode(a));
-        let mut hrp = self.prefix.to_owned() + &hr_amount;
-        let stream = [self.stream(), self.signature.to_u5_vec(true)?].concat();
+        let mut output = String::new();
+        self.encode_buffers(&mut Vec::new(), &mut output)?;
+        Ok(output)
+    }
 
-        let checksum = bech32_checksum(&hrp.as_bytes().to_vec(), &stream);
-        let stream_sum = [stream, checksum]
-            .concat()
-            .iter()
-            .map(|i| CHARSET[*i as usize])
-            .collect::<String>();
-        hrp.push_str("1");
-        hrp.push_str(&stream_sum);
-        Ok(hrp)
+    /// Returns the encoded payment request in uppercase, which QR codes store in their more
+    /// compact alphanumeric mode. Decoding accepts either case but not a mix of both.
+    pub fn to_uppercase_string(&self) -> Result<String, Error> {
+        let mut output = self.encode()?;
+        output.make_ascii_uppercase();
+        Ok(output)
     }
 
-    /// Return the hash of this payment request.
-    pub fn hash(&self) -> Result<Vec<u8>, Error> {
-        let amount = self.amount.map_or(String::new(), |a| Amount::encode(a));
-        let bytes = (self.prefix.to_owned() + &amount).as_bytes().to_vec();
+    /// Encode this payment request into `output`, using `stream` as a workspace for the 5-bit
+    /// words of the data part. Both buffers are cleared first.
+    pub(crate) fn encode_buffers(
+        &self,
+        stream: &mut Vec<U5>,
+        output: &mut String,
+    ) -> Result<(), Error> {
+        output.clear();
+        output.push_str(&self.hrp()?);
+        stream.clear();
+        self.write_stream(stream)?;
+        // an unsigned or corrupt payment request would otherwise encode into other bytes
+        if self.signature.len() != SIGNATURE_BYTES {
+            return Err(Error::InvalidSignatureLength(self.signature.len()));
+        }
+        stream.extend(self.signature.to_u5_vec(true));
 
-        Ok(
-            PaymentRequest::sha256_hasher(&[bytes, self.stream().to_u8_vec(false)?].concat())
-                .to_vec(),
-        )
+        let checksum = bech32_checksum(output.as_bytes(), stream);
+        output.push('1');
+        output.extend(
+            stream
+                .iter()
+                .chain(checksum.iter())
+                .map(|i| u5_to_char(*i)),
+        );
+        Ok(())
+    }
+
+    /// Return the hash of this payment request: the SHA256 of the human readable part and of
+    /// the data part without its signature, padded with zero bits to a byte boundary. This is
+    /// the digest signed by the payee node.
+    pub fn hash(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.signing_hash()?.to_vec())
     }
 
     /// Return a new PaymentRequest signed with the provided secret key.
     /// # Params
     /// `secret_key` The secret key used to sign the payment request.
     pub fn sign(&self, secret_key: &SecretKey) -> Result<PaymentRequest, Error> {
-        let hrp = self.prefix.to_owned() + &self.amount.map(Amount::encode).unwrap_or_default();
-        let message = PaymentRequest::parse_message(&hrp, &self.stream().to_u8_vec(true)?);
-        match secp256k1::sign(&message, secret_key) {
-            Ok((signature, recovery_id)) => {
-                let mut signed = self.clone();
-                let mut bytes = signature.serialize().to_vec();
-                bytes.push(recovery_id.serialize());
-                signed.signature = bytes;
-                Ok(signed)
+        self.sign_with(secret_key)
+    }
+
+    /// Return a new PaymentRequest signed by `signer`, which only ever sees the digest returned
+    /// by `hash`.
+    /// # Params
+    /// `signer` The signer holding the payee node key.
+    pub fn sign_with<S: Bolt11Signer + ?Sized>(
+        &self,
+        signer: &S,
+    ) -> Result<PaymentRequest, Error> {
+        let (signature, recovery_id) = signer.sign_recoverable(self.signing_hash()?)?;
+        Ok(self.with_signature(&signature, &recovery_id))
+    }
+
+    /// Return a future resolving to a new PaymentRequest signed by `signer`, which only ever
+    /// sees the digest returned by `hash`.
+    /// # Params
+    /// `signer` The signer holding the payee node key.
+    #[cfg(feature = "async")]
+    pub fn sign_with_async<'a, S: AsyncBolt11Signer + ?Sized>(
+        &self,
+        signer: &'a S,
+    ) -> SignWithAsync<'a> {
+        SignWithAsync {
+            unsigned: self.clone(),
+            signature: self.signing_hash()
+                .map(|hash| signer.sign_recoverable(hash))
+                .map_err(Some),
+        }
+    }
+
+    /// Re-issue this payment request: return it changed by `edit`, e.g. with a later expiry
+    /// or another amount, with its timestamp set to now and signed by `signer`, encoded.
+    /// Fails if `signer` doesn't hold the key of the node id.
+    /// # Params
+    /// `signer` The signer holding the payee node key.
+    /// `edit` Changes to apply; a timestamp set here is overwritten.
+    pub fn resign_with<S, F>(&self, signer: &S, edit: F) -> Result<String, Error>
+    where
+        S: Bolt11Signer + ?Sized,
+        F: FnOnce(&mut PaymentRequest),
+    {
+        self.resign_with_at(signer, edit, expiry::now()?)
+    }
+
+    /// Same as `resign_with`, issuing the payment request at the time `now` in seconds since
+    /// the epoch.
+    pub fn resign_with_at<S, F>(&self, signer: &S, edit: F, now: u64) -> Result<String, Error>
+    where
+        S: Bolt11Signer + ?Sized,
+        F: FnOnce(&mut PaymentRequest),
+    {
+        let mut edited = self.clone();
+        edit(&mut edited);
+        edited.timestamp = now;
+        let signed = edited.sign_with(signer)?;
+        if signed.verify_any(&[signed.node_id.clone()])?.is_none() {
+            return Err(Error::InvalidValue(
+                "signer doesn't hold the key of the node id".to_owned(),
+            ));
+        }
+        signed.encode()
+    }
+
+    // Copy of this payment request carrying the given signature, normalized to low S.
+    fn with_signature(&self, signature: &Signature, recovery_id: &RecoveryId) -> PaymentRequest {
+        let mut signed = self.clone();
+        let mut bytes = signature.serialize().to_vec();
+        bytes.push(recovery_id.serialize());
+        normalize_s(&mut bytes);
+        signed.signature = bytes;
+        signed
+    }
+
+    /// Return true if the signature has a low S value, as emitted by this crate and other
+    /// Lightning implementations.
+    pub fn has_low_s(&self) -> bool {
+        is_low_s(&self.signature)
+    }
+
+    /// Replace a high S signature by the equivalent low S one. The signature stays valid but
+    /// the encoding of the payment request changes. Returns true if the signature was changed.
+    pub fn normalize_signature(&mut self) -> bool {
+        normalize_s(&mut self.signature)
+    }
+
+    /// Check whether this payment request was signed by any of the given node ids, returning the
+    /// first one that matches.
+    /// # Params
+    /// `node_ids` Accepted node ids.
+    pub fn verify_any<'a>(
+        &self,
+        node_ids: &'a [PublicKey],
+    ) -> Result<Option<&'a PublicKey>, Error> {
+        let message = self.signing_message()?;
+        let (_, signature) = PaymentRequest::parse_signature(&self.signature)?;
+        Ok(node_ids
+            .iter()
+            .find(|node_id| secp256k1::verify(&message, &signature, node_id)))
+    }
+
+    /// Return true if this payment request was issued by `expected`, e.g. the counterparty a
+    /// withdrawal is paid to: the signature must be valid for `expected`, which must also be the
+    /// node id and the payee node id when the `n` tag is present.
+    pub fn verify_payee(&self, expected: &PublicKey) -> bool {
+        self.node_id == *expected
+            && self.payee_node_id().map_or(true, |payee| payee == *expected)
+            && self.verify_any(slice::from_ref(expected))
+                .map_or(false, |found| found.is_some())
+    }
+
+    /// Check the signatures of many payment requests against their `node_id`, e.g. withdrawal
+    /// requests submitted to an exchange, returning one result per payment request in order.
+    /// The buffers used to compute the signed digests are shared by all payment requests, and
+    /// secp256k1 uses static precomputed tables, so nothing is set up per payment request.
+    pub fn verify_batch(pay_requests: &[PaymentRequest]) -> Vec<Result<(), Error>> {
+        let mut stream = Vec::new();
+        let mut message_bytes = Vec::new();
+        pay_requests
+            .iter()
+            .map(|pay_request| {
+                let hash = pay_request.signing_hash_buffers(&mut stream, &mut message_bytes)?;
+                let (_, signature) = PaymentRequest::parse_signature(&pay_request.signature)?;
+                let message = Message::parse(&hash);
+                if secp256k1::verify(&message, &signature, &pay_request.node_id) {
+                    Ok(())
+                } else {
+                    Err(Error::SignatureError(secp256k1::Error::InvalidSignature))
+                }
+            })
+            .collect()
+    }
+
+    /// Return a one line summary of the payment request: amount, payee and description.
+    pub fn summary(&self) -> String {
+        self.summary_with(&|_: &PublicKey| None::<String>)
+    }
+
+    /// Return a one line summary of the payment request, showing the payee by its alias when
+    /// the resolver knows it.
+    /// # Params
+    /// `resolver` Node alias resolver.
+    pub fn summary_with<R: AliasResolver + ?Sized>(&self, resolver: &R) -> String {
+        let amount = self.amount
+            .map_or("any amount".to_owned(), |a| a.to_string());
+        let payee = resolver
+            .resolve(&self.node_id)
+            .unwrap_or_else(|| ::utils::to_hex(&self.node_id.serialize_compressed()));
+        match self.description() {
+            Some(description) => format!("{} to {}: {}", amount, payee, description),
+            None => format!("{} to {}", amount, payee),
+        }
+    }
+
+    /// Return every field of the payment request, one per line with a label and its unit:
+    /// hashes and keys in hex, fallback addresses rendered for the currency of the payment
+    /// request and route hints as tables. Meant to be read by people, e.g. support teams; the
+    /// layout may change.
+    pub fn explain(&self) -> String {
+        let mut lines = Vec::new();
+        {
+            // continuation lines have an empty label
+            let mut line = |label: &str, value: String| {
+                let label = if label.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}:", label)
+                };
+                lines.push(format!("{:<22} {}", label, value))
+            };
+            let currency = Currency::from_prefix(&self.prefix);
+            line(
+                "currency",
+                match currency {
+                    Some(currency) => format!("{} ({:?})", self.prefix, currency),
+                    None => format!("{} (unknown)", self.prefix),
+                },
+            );
+            line(
+                "amount",
+                match self.amount {
+                    Some(amount) => {
+                        format!("{} ({} BTC)", amount, explain_btc(amount.as_u64()))
+                    }
+                    None => "any".to_owned(),
+                },
+            );
+            line("timestamp", format!("{} (unix time)", self.timestamp));
+            line(
+                "expiry",
+                match self.expiry() {
+                    Some(seconds) => format!("{} seconds", seconds),
+                    None => format!("{} seconds (default)", self.expiry_or_default()),
+                },
+            );
+            line("expires at", format!("{} (unix time)", self.expires_at()));
+            line(
+                "node id",
+                format!(
+                    "{} ({})",
+                    to_hex(&self.node_id.serialize_compressed()),
+                    if self.payee_node_id().is_some() {
+                        "payee node id field"
+                    } else {
+                        "recovered from the signature"
+                    }
+                ),
+            );
+            for tag in &self.tags {
+                match *tag {
+                    Tag::PayeeNodeId { .. } | Tag::Expiry { .. } => {}
+                    Tag::FallbackAddress { version, .. } => {
+                        match currency.and_then(|c| tag.fallback_address_string(c)) {
+                            Some(address) => line(
+                                "fallback address",
+                                format!("{} (version {})", address, version),
+                            ),
+                            None => explain_tag(&mut line, tag),
+                        }
+                    }
+                    Tag::RoutingInfo { ref path } => {
+                        line("route hint", format!("{} hops", path.len()));
+                        for hop in path {
+                            line("", hop.to_string());
+                        }
+                    }
+                    Tag::Features { ref features } => {
+                        let bits = features
+                            .iter()
+                            .map(|bit| match Feature::from_bit(bit) {
+                                Some(feature) if Feature::is_required_bit(bit) => {
+                                    format!("{} ({}, required)", bit, feature)
+                                }
+                                Some(feature) => format!("{} ({}, optional)", bit, feature),
+                                None => format!("{} (unknown)", bit),
+                            })
+                            .collect::<Vec<_>>();
+                        if bits.is_empty() {
+                            explain_tag(&mut line, tag)
+                        } else {
+                            line("features", bits.join(", "))
+                        }
+                    }
+                    _ => explain_tag(&mut line, tag),
+                }
+            }
+            if self.min_final_cltv_expiry().is_none() {
+                line(
+                    "min final cltv expiry",
+                    format!("{} blocks (default)", self.min_final_cltv_expiry_or_default()),
+                );
+            }
+            line("signature", to_hex(&self.signature));
+        }
+        lines.join("\n")
+    }
+
+    /// Return a stable identifier of the payment: the SHA256 of the node id, the payment hash
+    /// and the amount. Payment requests re-issued for the same payment, e.g. with a new
+    /// expiry, share it.
+    pub fn payment_id(&self) -> [u8; 32] {
+        let mut bytes = self.node_id.serialize_compressed().to_vec();
+        // absent fields are marked, so they can't be confused with present ones
+        match self.payment_hash() {
+            Some(hash) => {
+                bytes.push(1);
+                bytes.extend(hash);
+            }
+            None => bytes.push(0),
+        }
+        match self.amount {
+            Some(amount) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&amount.as_u64().to_be_bytes());
+            }
+            None => bytes.push(0),
+        }
+        sha256(&bytes)
+    }
+
+    /// Return true if `other` requests the same payment with the same fields, only signed
+    /// differently, with its tags in another order or its amount written differently.
+    pub fn semantically_equal(&self, other: &PaymentRequest) -> bool {
+        if self.prefix != other.prefix
+            || self.amount != other.amount
+            || self.timestamp != other.timestamp
+            || self.node_id != other.node_id
+            || self.tags.len() != other.tags.len()
+        {
+            return false;
+        }
+        // tags may repeat, each one of `other` is matched at most once
+        let mut matched = vec![false; other.tags.len()];
+        self.tags.iter().all(|tag| {
+            let found = (0..other.tags.len())
+                .find(|index| !matched[*index] && other.tags[*index] == *tag);
+            match found {
+                Some(index) => {
+                    matched[index] = true;
+                    true
+                }
+                None => false,
             }
-            Err(e) => Err(Error::SignatureError(e)),
+        })
+    }
+
+    /// Return the chain this payment request is for, detected from its prefix. Unlike segwit
+    /// networks, currencies tell signet (`lntbs`) and regtest (`lnbcrt`) apart from testnet.
+    /// Only built in currencies are detected, see `CurrencyRegistry::from_prefix` for others.
+    pub fn network(&self) -> Option<Currency> {
+        Currency::from_prefix(&self.prefix)
+    }
+
+    /// Return true if this payment request is for `network`, e.g. so test infrastructure
+    /// rejects mainnet payment requests.
+    pub fn is_for_network(&self, network: Currency) -> bool {
+        self.prefix == network.prefix()
+    }
+
+    /// Return the amount to pay, if any.
+    pub fn amount_msat(&self) -> Option<MilliSatoshi> {
+        self.amount
+    }
+
+    /// Return the amount to pay in satoshis, if any.
+    /// # Params
+    /// `rounding` What to do with the sub-satoshi remainder.
+    pub fn amount_sat(&self, rounding: Rounding) -> Result<Option<u64>, Error> {
+        match self.amount {
+            Some(amount) => amount.to_sat(rounding).map(Some),
+            None => Ok(None),
         }
     }
 
-    /// Update the payment amount.
-    pub fn update_amount(&mut self, amount: Option<u64>) {
+    /// Update the payment amount, `None` allowing the payer to choose any amount. It will be
+    /// encoded in its shortest representation.
+    pub fn update_amount(&mut self, amount: Option<MilliSatoshi>) {
         self.amount = amount;
+        self.amount_encoding = AmountEncoding::Shortest;
     }
 
     /// Update the public key of the payee node.
@@ -151,28 +584,80 @@ impl PaymentRequest {
         self.node_id = node_id;
     }
 
+    /// Call the method of `visitor` for each tagged field, in order.
+    pub fn for_each_tag<V: TagVisitor + ?Sized>(&self, visitor: &mut V) {
+        for tag in &self.tags {
+            tag.accept(visitor);
+        }
+    }
+
+    /// Return the tags checked against the BOLT11 rules on duplicated and mandatory fields.
+    pub fn tag_set(&self) -> Result<TagSet, Error> {
+        TagSet::new(self.tags.to_owned())
+    }
+
     /// Return the payment hash.
     pub fn payment_hash(&self) -> Option<Vec<u8>> {
-        self.tags
+        self.find_tag(|tag| match *tag {
+            Tag::PaymentHash { ref hash } => Some(hash.to_owned()),
+            _ => None,
+        })
+    }
+
+    /// Return true if `preimage` hashes to the payment hash, i.e. proves this payment request
+    /// was paid. The hashes are compared in constant time.
+    pub fn matches_preimage(&self, preimage: &[u8; 32]) -> bool {
+        match self.payment_hash() {
+            Some(hash) => constant_time_eq(&sha256(preimage), &hash),
+            None => false,
+        }
+    }
+
+    /// Return the payment secret if any.
+    pub fn payment_secret(&self) -> Option<[u8; 32]> {
+        self.find_tag(|tag| match *tag {
+            Tag::PaymentSecret { secret } => Some(secret),
+            _ => None,
+        })
+    }
+
+    /// Update the payment secret.
+    pub fn update_payment_secret(&mut self, secret: [u8; 32]) {
+        let mut tags = self.tags
             .iter()
-            .filter_map(|v| match *v {
-                Tag::PaymentHash { ref hash } => Some(hash.to_owned()),
-                _ => None,
-            })
-            .next()
+            .filter(|t| !matches!(*t, &Tag::PaymentSecret{..}))
+            .map(|t| t.to_owned())
+            .collect::<Vec<Tag>>();
+        tags.push(Tag::PaymentSecret { secret });
+        self.tags = tags;
+    }
+
+    /// Return the payee node id field if any. The node id of a decoded payment request is
+    /// always available as `node_id`, this only tells whether it was given explicitly.
+    pub fn payee_node_id(&self) -> Option<PublicKey> {
+        self.find_tag(|tag| match *tag {
+            Tag::PayeeNodeId { ref pubkey } => Some(pubkey.clone()),
+            _ => None,
+        })
+    }
+
+    /// Include `node_id` as the payee node id field, so readers don't have to recover it from
+    /// the signature.
+    pub fn update_payee_node_id(&mut self) {
+        let mut tags = self.tags
+            .iter()
+            .filter(|t| !matches!(*t, &Tag::PayeeNodeId{..}))
+            .map(|t| t.to_owned())
+            .collect::<Vec<Tag>>();
+        tags.push(Tag::PayeeNodeId {
+            pubkey: self.node_id.clone(),
+        });
+        self.tags = tags;
     }
 
     /// Return the description of the payment or its hash if any.
     pub fn description(&self) -> Option<String> {
-        self.tags
-            .iter()
-            .filter_map(|v| match *v {
-                Tag::Description { .. } | Tag::DescriptionHash { .. } => {
-                    Description::new(v).map(|d| d.to_string())
-                }
-                _ => None,
-            })
-            .next()
+        self.find_tag(|tag| Description::new(tag).map(|d| d.to_string()))
     }
 
     /// Update the payment description. <br>
@@ -205,18 +690,26 @@ impl PaymentRequest {
                 _ => None,
             })
             .flatten()
-            .collect_vec()
+            .collect::<Vec<_>>()
+    }
+
+    /// Return the private routes to the payee, one per `'r'` field.
+    pub fn routes(&self) -> Vec<RoutingInfo> {
+        self.tags.iter().filter_map(RoutingInfo::from_tag).collect()
     }
 
     /// Return the min_final_cltv_expiry if any.
     pub fn min_final_cltv_expiry(&self) -> Option<u64> {
-        self.tags
-            .iter()
-            .filter_map(|v| match *v {
-                Tag::MinFinalCltvExpiry { blocks } => Some(blocks),
-                _ => None,
-            })
-            .next()
+        self.find_tag(|tag| match *tag {
+            Tag::MinFinalCltvExpiry { blocks } => Some(blocks),
+            _ => None,
+        })
+    }
+
+    /// Return the min_final_cltv_expiry, or `DEFAULT_MIN_FINAL_CLTV_EXPIRY` if the payment
+    /// request doesn't specify one.
+    pub fn min_final_cltv_expiry_or_default(&self) -> u64 {
+        self.min_final_cltv_expiry().unwrap_or(DEFAULT_MIN_FINAL_CLTV_EXPIRY)
     }
 
     /// Update the min_final_cltv_expiry.
@@ -234,13 +727,15 @@ impl PaymentRequest {
 
     /// Return the payment request expiry if any.
     pub fn expiry(&self) -> Option<u64> {
-        self.tags
-            .iter()
-            .filter_map(|v| match *v {
-                Tag::Expiry { seconds } => Some(seconds),
-                _ => None,
-            })
-            .next()
+        self.find_tag(|tag| match *tag {
+            Tag::Expiry { seconds } => Some(seconds),
+            _ => None,
+        })
+    }
+
+    /// Return the payment request expiry, or `DEFAULT_EXPIRY_SECONDS` if it doesn't specify one.
+    pub fn expiry_or_default(&self) -> u64 {
+        self.expiry().unwrap_or(DEFAULT_EXPIRY_SECONDS)
     }
 
     /// Update the expiry data for this payment request.
@@ -256,76 +751,104 @@ impl PaymentRequest {
         self.tags = tags;
     }
 
+    /// Return the time at which this payment request expires, in seconds since the epoch,
+    /// using the default expiry if the payment request doesn't specify one.
+    pub fn expires_at(&self) -> u64 {
+        self.timestamp.saturating_add(self.expiry_or_default())
+    }
+
+    /// Creation time of this payment request, `None` if the timestamp is out of range.
+    #[cfg(feature = "chrono")]
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        i64::try_from(self.timestamp)
+            .ok()
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single())
+    }
+
+    /// Same as `expires_at`, as a `SystemTime`. `None` if the expiry is too far in the future
+    /// for the platform to represent it.
+    #[cfg(feature = "std")]
+    pub fn expiry_time(&self) -> Option<SystemTime> {
+        UNIX_EPOCH.checked_add(Duration::from_secs(self.expires_at()))
+    }
+
+    /// Return how long this payment request remains payable, `None` if it is expired.
+    pub fn time_until_expiry(&self) -> Result<Option<Duration>, Error> {
+        Ok(self.expires_at()
+            .checked_sub(expiry::now()?)
+            .map(Duration::from_secs))
+    }
+
+    /// Return true if this payment request is expired.
+    pub fn is_expired(&self) -> Result<bool, Error> {
+        self.is_expired_with(&ExpiryGrace::none())
+    }
+
+    /// Return true if this payment request is expired, given some allowance.
+    /// # Params
+    /// `grace` Allowance applied to the expiry.
+    pub fn is_expired_with(&self, grace: &ExpiryGrace) -> Result<bool, Error> {
+        Ok(grace.is_expired(self.expires_at(), expiry::now()?))
+    }
+
+    /// Check that this payment request can be paid now: it must have a payment hash, must not
+    /// be expired and must not have been issued in the future.
+    /// # Params
+    /// `grace` Allowance applied to the expiry and the timestamp.
+    pub fn validate(&self, grace: &ExpiryGrace) -> Result<(), Error> {
+        self.validate_at(grace, expiry::now()?)
+    }
+
+    /// Same as `validate`, at the time `now` in seconds since the epoch.
+    pub fn validate_at(&self, grace: &ExpiryGrace, now: u64) -> Result<(), Error> {
+        if self.payment_hash().is_none() {
+            Err(Error::InvalidValue("missing payment hash".to_owned()))
+        } else if grace.is_in_future(self.timestamp, now) {
+            Err(Error::InvalidValue(
+                "payment request timestamp is in the future".to_owned(),
+            ))
+        } else if grace.is_expired(self.expires_at(), now) {
+            Err(Error::InvalidValue("payment request is expired".to_owned()))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Return the description hash if any.
     pub fn description_hash(&self) -> Option<Vec<u8>> {
-        self.tags
-            .iter()
-            .filter_map(|v| match *v {
-                Tag::DescriptionHash { ref hash } => Some(hash.to_owned()),
-                _ => None,
-            })
-            .next()
+        self.find_tag(|tag| match *tag {
+            Tag::DescriptionHash { ref hash } => Some(hash.to_owned()),
+            _ => None,
+        })
     }
 
-    /// Return the fallback address if any. It could be a script address, pubkey address, ..
-    pub fn fallback_address(&self) -> Option<String> {
-        // encode fallback address
-        fn bech_address(version: u8, program: Vec<u8>, network: Network) -> Option<String> {
-            let witness_program = WitnessProgram {
-                version,
-                program,
-                network,
-            };
-            witness_program.to_address().ok()
-        }
-        // prefix used in fallback closure
-        let prefix = &self.prefix;
-
-        let fallback = |tag: &Tag| -> Option<String> {
-            match *tag {
-                Tag::FallbackAddress {
-                    version: 17,
-                    ref hash,
-                } if prefix == "lnbc" =>
-                {
-                    // 0 PubkeyAddress
-                    Some(hash.to_base58check(0))
-                }
-                Tag::FallbackAddress {
-                    version: 18,
-                    ref hash,
-                } if prefix == "lnbc" =>
-                {
-                    // 5 ScriptAddress
-                    Some(hash.as_slice().to_base58check(5))
-                }
-                Tag::FallbackAddress {
-                    version: 17,
-                    ref hash,
-                } if prefix == "lntb" =>
-                {
-                    // 111 PubkeyAddressTestnet
-                    Some(hash.as_slice().to_base58check(111))
-                }
-                Tag::FallbackAddress {
-                    version: 18,
-                    ref hash,
-                } if prefix == "lntb" =>
-                {
-                    // 196 ScriptAddressTestnet
-                    Some(hash.as_slice().to_base58check(196))
-                }
-                Tag::FallbackAddress { version, ref hash } if prefix == "lnbc" => {
-                    bech_address(version, hash.to_owned(), Network::Bitcoin)
-                }
-                Tag::FallbackAddress { version, ref hash } if prefix == "lntb" => {
-                    bech_address(version, hash.to_owned(), Network::Testnet)
-                }
-                _ => None,
+    /// Check that `long_description`, e.g. received from a LNURL service, is the description
+    /// whose SHA256 hash is in the description hash tag. `false` without such a tag.
+    pub fn verify_description(&self, long_description: &str) -> bool {
+        match self.description_hash() {
+            Some(hash) => {
+                hash[..] == sha256(long_description.as_bytes())[..]
             }
-        };
+            None => false,
+        }
+    }
+
+    /// Return the first fallback address if any. It could be a script address, pubkey
+    /// address, ..
+    pub fn fallback_address(&self) -> Option<String> {
+        self.fallback_addresses().into_iter().next()
+    }
 
-        self.tags.iter().filter_map(fallback).next()
+    /// Return the fallback addresses that can be rendered for the currency of this payment
+    /// request, in order.
+    pub fn fallback_addresses(&self) -> Vec<String> {
+        match Currency::from_prefix(&self.prefix) {
+            Some(currency) => self.tags
+                .iter()
+                .filter_map(|tag| tag.fallback_address_string(currency))
+                .collect(),
+            None => vec![],
+        }
     }
 
     /// Update the fallback address.
@@ -346,6 +869,66 @@ impl PaymentRequest {
         self.tags = tags
     }
 
+    /// Add a fallback address after the existing ones, see `update_fallback_address`.
+    pub fn add_fallback_address(&mut self, version: u8, hash: Vec<u8>) {
+        self.tags.push(Tag::FallbackAddress { version, hash });
+    }
+
+    /// Return the feature bits if any.
+    pub fn features(&self) -> Option<Features> {
+        self.find_tag(|tag| match *tag {
+            Tag::Features { ref features } => Some(features.to_owned()),
+            _ => None,
+        })
+    }
+
+    /// Update the feature bits. Bits unknown to this crate are kept as they are.
+    pub fn update_features(&mut self, features: Features) {
+        let mut tags = self.tags
+            .iter()
+            .filter(|t| !matches!(*t, &Tag::Features{..}))
+            .map(|t| t.to_owned())
+            .collect::<Vec<Tag>>();
+        tags.push(Tag::Features { features });
+        self.tags = tags;
+    }
+
+    /// Return the payment metadata if any.
+    pub fn metadata(&self) -> Option<Vec<u8>> {
+        self.find_tag(|tag| match *tag {
+            Tag::Metadata { ref bytes } => Some(bytes.to_owned()),
+            _ => None,
+        })
+    }
+
+    /// Return the payment metadata decoded as TLV records. Payment requests without metadata
+    /// have no records.
+    pub fn metadata_records(&self) -> Result<Vec<TlvRecord>, Error> {
+        self.metadata()
+            .map_or(Ok(Vec::new()), |bytes| TlvRecord::decode_all(&bytes))
+    }
+
+    /// Update the payment metadata.
+    /// # Params
+    /// `bytes` Opaque metadata bytes.
+    pub fn update_metadata(&mut self, bytes: Vec<u8>) {
+        let mut tags = self.tags
+            .iter()
+            .filter(|t| !matches!(*t, &Tag::Metadata{..}))
+            .map(|t| t.to_owned())
+            .collect::<Vec<Tag>>();
+        tags.push(Tag::Metadata { bytes });
+        self.tags = tags;
+    }
+
+    /// Update the payment metadata with the TLV encoding of the given records.
+    /// # Params
+    /// `records` Metadata records, sorted by strictly increasing type.
+    pub fn update_metadata_records(&mut self, records: &[TlvRecord]) -> Result<(), Error> {
+        self.update_metadata(TlvRecord::encode_all(records)?);
+        Ok(())
+    }
+
     /// Create a new PaymentRequest.
     ///
     /// # Arguments
@@ -361,7 +944,7 @@ impl PaymentRequest {
     /// `min_final_cltv_expiry` min_final_cltv_expiry.
     pub fn new(
         prefix: String,
-        amount: Option<u64>,
+        amount: Option<MilliSatoshi>,
         payment_hash: Vec<u8>,
         secret_key: &SecretKey,
         description: String,
@@ -386,13 +969,10 @@ impl PaymentRequest {
             tags.push(tag)
         }
 
-        let time = timestamp.unwrap_or({
-            let start = SystemTime::now();
-            let time = start
-                .duration_since(UNIX_EPOCH)
-                .map_err(|_| Error::InvalidValue("invalid system time".to_owned()))?;
-            time.as_secs() * 1000 + time.subsec_nanos() as u64 / 1_000_000
-        });
+        let time = match timestamp {
+            Some(time) => time,
+            None => expiry::now()?,
+        };
 
         if let Some(blocks) = min_final_cltv_expiry {
             tags.push(Tag::MinFinalCltvExpiry { blocks })
@@ -401,6 +981,7 @@ impl PaymentRequest {
         let pay = PaymentRequest {
             prefix: prefix,
             amount,
+            amount_encoding: AmountEncoding::Shortest,
             timestamp: time,
             node_id: secp256k1::PublicKey::from_secret_key(&secret_key),
             tags,
@@ -409,17 +990,55 @@ impl PaymentRequest {
         pay.sign(&secret_key)
     }
 
-    /// A representation of this payment request, without its signature, as a bit stream.
-    /// This is what will be signed
-    fn stream(&self) -> Vec<U5> {
-        //Result<Vec<u8>, Error> {
-        let bytes = self.tags
-            .iter()
-            .flat_map(|tag| tag.to_vec_u5())
-            .collect_vec()
-            .concat();
-        [Timestamp::encode(self.timestamp), bytes].concat()
+    /// The human readable part: prefix and amount.
+    pub(crate) fn hrp(&self) -> Result<String, Error> {
+        match self.amount {
+            Some(amount) => Ok(self.prefix.to_owned()
+                + &Amount::encode_with(amount.as_u64(), self.amount_encoding)?),
+            None => Ok(self.prefix.to_owned()),
+        }
+    }
+
+    /// Append the bit stream representation of this payment request to `stream`. Fails if a
+    /// tag can't be encoded rather than leaving it out.
+    pub(crate) fn write_stream(&self, stream: &mut Vec<U5>) -> Result<(), Error> {
+        stream.extend(Timestamp::encode(self.timestamp)?);
+        for tag in &self.tags {
+            stream.extend(tag.to_vec_u5()?);
+        }
+        Ok(())
     }
+    /// The message signed by the payee node.
+    fn signing_message(&self) -> Result<Message, Error> {
+        Ok(secp256k1::Message::parse(&self.signing_hash()?))
+    }
+
+    /// The digest of the message signed by the payee node.
+    pub(crate) fn signing_hash(&self) -> Result<[u8; 32], Error> {
+        self.signing_hash_buffers(&mut Vec::new(), &mut Vec::new())
+    }
+
+    /// The digest of the message signed by the payee node, using `stream` and `message_bytes`
+    /// as workspaces. Both buffers are cleared first.
+    fn signing_hash_buffers(
+        &self,
+        stream: &mut Vec<U5>,
+        message_bytes: &mut Vec<u8>,
+    ) -> Result<[u8; 32], Error> {
+        stream.clear();
+        self.write_stream(stream)?;
+        message_bytes.clear();
+        message_bytes.extend_from_slice(self.hrp()?.as_bytes());
+        message_bytes.extend(stream.to_u8_vec(true));
+        Ok(sha256(message_bytes))
+    }
+
+    /// Return the first value `f` extracts from a tag, so getters follow the first field of
+    /// each type when a payment request has several.
+    fn find_tag<T, F: FnMut(&Tag) -> Option<T>>(&self, f: F) -> Option<T> {
+        self.tags.iter().filter_map(f).next()
+    }
+
     /// Remove the payment description
     fn filter_description(&self) -> Vec<Tag> {
         self.tags
@@ -432,51 +1051,32 @@ impl PaymentRequest {
     }
 
     /// Parse the message
-    fn parse_message(hrp: &String, bytes: &[u8]) -> Message {
+    fn parse_message(hrp: &str, bytes: &[u8]) -> Message {
         let message_bytes = [hrp.as_bytes(), bytes].concat();
-        let raw_message = PaymentRequest::sha256_hasher(&message_bytes);
+        let raw_message = sha256(&message_bytes);
 
         secp256k1::Message::parse(&raw_message)
     }
 
-    /// Sha256 hasher
-    fn sha256_hasher(bytes: &[u8]) -> [u8; 32] {
-        let mut hash = [0u8; 32];
-        let mut hasher = Sha256::new();
-        hasher.input(bytes);
-        hasher.result(&mut hash);
-        hash
-    }
-
-    /// Parse the signature, the signature must be 65 bytes
+    /// Parse the signature, failing with `Error::InvalidSignatureLength` unless it is 65 bytes
     fn parse_signature(bytes: &[u8]) -> Result<(RecoveryId, Signature), Error> {
-        if bytes.len() == 65 {
-            //  the recovery id is the last byte of the signature
-            let recovery_id = secp256k1::RecoveryId::parse(bytes[64])?;
-            let sig_raw = bytes[..64].iter().enumerate().fold(
-                [0u8; 64],
-                |mut acc, (index, item)| {
-                    acc[index] = *item;
-                    acc
-                },
-            );
-            let signature = secp256k1::Signature::parse(&sig_raw);
-            Ok((recovery_id, signature))
-        } else {
-            Err(Error::InvalidLength(
-                "the length must be 65 bytes".to_owned(),
-            ))
-        }
+        let (signature, recovery_id) = signature::Signature::decode(bytes)?.to_secp256k1()?;
+        Ok((recovery_id, signature))
     }
 
     // get tag from fallback adress
-    fn tag_from_fallback_address(address: String) -> Option<Tag> {
+    #[cfg(feature = "std")]
+    pub(crate) fn tag_from_fallback_address(address: String) -> Option<Tag> {
         match address.from_base58check() {
-            Ok((version, hash)) => match version {
-                0 | 111 => Some(Tag::FallbackAddress { version: 17, hash }),
-                5 | 196 => Some(Tag::FallbackAddress { version: 18, hash }),
-                _ => None,
-            },
+            Ok((version, hash)) => Currency::all()
+                .iter()
+                .filter_map(|currency| match currency.base58_versions() {
+                    (pubkey_version, _) if pubkey_version == version => Some(17),
+                    (_, script_version) if script_version == version => Some(18),
+                    _ => None,
+                })
+                .next()
+                .map(|version| Tag::FallbackAddress { version, hash }),
             _ => match WitnessProgram::from_address(address.to_owned()) {
                 Ok(witness) => Some(Tag::FallbackAddress {
                     version: witness.version,
@@ -486,6 +1086,79 @@ impl PaymentRequest {
             },
         }
     }
+
+    // fallback addresses can't be parsed without std
+    #[cfg(not(feature = "std"))]
+    pub(crate) fn tag_from_fallback_address(_address: String) -> Option<Tag> {
+        None
+    }
+}
+
+/// Format an amount of millisatoshis in bitcoins, without trailing zeros.
+fn explain_btc(msat: u64) -> String {
+    let fraction = format!("{:011}", msat % 100_000_000_000);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}", msat / 100_000_000_000)
+    } else {
+        format!("{}.{}", msat / 100_000_000_000, fraction)
+    }
+}
+
+/// Pass the `Display` of `tag` to `line` as the label and value of an `explain` line.
+fn explain_tag<F: FnMut(&str, String)>(line: &mut F, tag: &Tag) {
+    let text = tag.to_string();
+    match text.find(": ") {
+        Some(at) => line(&text[..at], text[at + 2..].to_owned()),
+        None => line(&text, String::new()),
+    }
+}
+
+/// Formats the bech32 encoding of the payment request. Fails with `fmt::Error` if the payment
+/// request can't be encoded, use `encode` to know why.
+impl fmt::Display for PaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoded = self.encode().map_err(|_| fmt::Error)?;
+        f.write_str(&encoded)
+    }
+}
+
+impl FromStr for PaymentRequest {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<PaymentRequest, Error> {
+        PaymentRequest::decode(input)
+    }
+}
+
+/// Future returned by `PaymentRequest::sign_with_async`, resolving to the signed payment
+/// request.
+#[cfg(feature = "async")]
+pub struct SignWithAsync<'a> {
+    unsigned: PaymentRequest,
+    // the error is taken out when the future completes
+    signature: Result<SignatureFuture<'a>, Option<Error>>,
+}
+
+#[cfg(feature = "async")]
+impl<'a> Future for SignWithAsync<'a> {
+    type Output = Result<PaymentRequest, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let (unsigned, signature) = (&this.unsigned, &mut this.signature);
+        match *signature {
+            Ok(ref mut signing) => match signing.as_mut().poll(cx) {
+                Poll::Ready(signed) => Poll::Ready(signed.map(|(signature, recovery_id)| {
+                    unsigned.with_signature(&signature, &recovery_id)
+                })),
+                Poll::Pending => Poll::Pending,
+            },
+            Err(ref mut error) => Poll::Ready(Err(error
+                .take()
+                .expect("SignWithAsync polled after completion"))),
+        }
+    }
 }
 
 /// PaymentRequest description
@@ -517,23 +1190,14 @@ impl fmt::Display for Description {
 #[cfg(test)]
 mod test {
     use super::*;
-    use lazy_static;
+    use bech32::char_to_u5;
+    use codec::DEFAULT_MAX_INPUT_LENGTH;
+    use fixtures::{coffee, node_key, payment_hash, sec_key};
+    use scid::ShortChannelId;
     use utils::{from_hex, to_hex};
 
-    lazy_static!{
-         static ref SEC_KEY: secp256k1::SecretKey = {
-            let key = from_hex(
-                "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734",
-            ).unwrap()
-                .iter()
-                .enumerate()
-                .fold([0u8; 32], |mut acc, (index, item)| {
-                    acc[index] = *item;
-                    acc
-                });
-            secp256k1::SecretKey::parse(&key).unwrap()
-         };
-         static ref PUB_KEY: secp256k1::PublicKey = secp256k1::PublicKey::from_secret_key(&SEC_KEY);
+    fn pub_key(hex: &str) -> PublicKey {
+        ExtraHop::parse_pub_key(&from_hex(hex).unwrap()).unwrap()
     }
 
     #[test]
@@ -544,7 +1208,7 @@ mod test {
 
         let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
-        let amount = Some(850_000_000u64);
+        let amount = Some(MilliSatoshi::new(850_000_000));
         pay_request.update_amount(amount);
         assert_eq!(pay_request.amount, amount);
 
@@ -560,6 +1224,80 @@ mod test {
         assert_eq!(pay_request.fallback_address(), fallback_address)
     }
 
+    #[test]
+    fn test_amount_sat() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        assert_eq!(pay_request.amount_msat(), Some(MilliSatoshi::new(250_000_000)));
+        assert_eq!(pay_request.amount_sat(Rounding::Exact).unwrap(), Some(250_000));
+
+        pay_request.update_amount(Some(MilliSatoshi::new(850_000_500)));
+        assert_eq!(pay_request.amount_sat(Rounding::Floor).unwrap(), Some(850_000));
+        assert_eq!(pay_request.amount_sat(Rounding::Ceil).unwrap(), Some(850_001));
+        assert!(pay_request.amount_sat(Rounding::Exact).is_err());
+
+        pay_request.update_amount(None);
+        assert_eq!(pay_request.amount_sat(Rounding::Exact).unwrap(), None);
+    }
+
+    #[test]
+    fn test_any_amount() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        pay_request.update_amount(None);
+        let any_amount = pay_request.sign(&sec_key()).unwrap().encode().unwrap();
+        assert!(any_amount.starts_with("lnbc1"));
+        assert_eq!(PaymentRequest::decode(&any_amount).unwrap().amount_msat(), None);
+
+        // zero is an amount, encoding it would make it indistinguishable from any amount
+        pay_request.update_amount(Some(MilliSatoshi::new(0)));
+        assert!(pay_request.sign(&sec_key()).is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut pay_request = coffee(None, None);
+        assert_eq!(pay_request.metadata(), None);
+
+        for bytes in vec![vec![0x01], from_hex("01fafaf0").unwrap(), vec![0xff; 64]] {
+            pay_request.update_metadata(bytes.clone());
+            let signed = pay_request.sign(&sec_key()).unwrap();
+            let decoded = PaymentRequest::decode(&signed.encode().unwrap()).unwrap();
+            assert_eq!(decoded.metadata(), Some(bytes));
+            assert_eq!(decoded, signed);
+        }
+        assert_eq!(pay_request.tags.iter().filter(|t| t.tag_char() == 'm').count(), 1);
+    }
+
+    #[test]
+    fn test_metadata_records() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        assert!(pay_request.metadata_records().unwrap().is_empty());
+
+        let records = vec![
+            TlvRecord::new(1, b"order-42".to_vec()),
+            TlvRecord::new(3, b"merchant".to_vec()),
+        ];
+        pay_request.update_metadata_records(&records).unwrap();
+        assert_eq!(pay_request.metadata_records().unwrap(), records);
+        let reencoded = pay_request.sign(&sec_key()).unwrap().encode().unwrap();
+        assert_eq!(
+            PaymentRequest::decode(&reencoded)
+                .unwrap()
+                .metadata_records()
+                .unwrap(),
+            records
+        );
+    }
+
     #[test]
     fn test_send_using_payment_hash() {
         // Please make a donation of any amount using payment_hash 0001020304050607080900010203040506070809000102030405060708090102 to me @03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad
@@ -575,7 +1313,7 @@ mod test {
         assert!(pay_request.amount.is_none());
         assert_eq!(pay_request.payment_hash().unwrap(), payment_hash);
         assert_eq!(pay_request.timestamp, 1496_314_658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "Please consider supporting this project".to_owned()
@@ -584,7 +1322,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 2);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
         assert_eq!(
@@ -605,10 +1343,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(250_000_000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(250_000_000)));
         assert_eq!(pay_request.payment_hash().unwrap(), payment_hash);
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "1 cup coffee".to_owned()
@@ -617,7 +1355,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 3);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -635,10 +1373,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000_000_000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(2000_000_000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1".to_owned()
@@ -647,7 +1385,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 2);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -664,10 +1402,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lntb");
-        assert_eq!(pay_request.amount, Some(2000_000_000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(2000_000_000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1".to_owned()
@@ -679,7 +1417,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 3);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -702,20 +1440,20 @@ mod test {
         let routing_info = Tag::RoutingInfo {
             path: vec![
                 ExtraHop {
-                    pub_key: from_hex(
+                    pub_key: pub_key(
                         "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                    ).unwrap(),
-                    short_channel_id: 72623859790382856u64,
-                    fee_base_msat: 1,
+                    ),
+                    short_channel_id: ShortChannelId::from(72623859790382856),
+                    fee_base_msat: MilliSatoshi::from(1),
                     fee_proportional_millionths: 20,
                     cltv_expiry_delta: 3,
                 },
                 ExtraHop {
-                    pub_key: from_hex(
+                    pub_key: pub_key(
                         "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                    ).unwrap(),
-                    short_channel_id: 217304205466536202u64,
-                    fee_base_msat: 2,
+                    ),
+                    short_channel_id: ShortChannelId::from(217304205466536202),
+                    fee_base_msat: MilliSatoshi::from(2),
                     fee_proportional_millionths: 30,
                     cltv_expiry_delta: 4,
                 },
@@ -723,10 +1461,10 @@ mod test {
         };
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000_000_000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(2000_000_000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1".to_owned()
@@ -741,10 +1479,18 @@ mod test {
             },
             routing_info
         );
+        assert_eq!(pay_request.routes().len(), 1);
+        assert_eq!(pay_request.routes()[0].total_cltv_delta(), 7);
+        assert_eq!(
+            pay_request.routes()[0]
+                .total_fee_msat(MilliSatoshi::from(2000_000_000))
+                .unwrap(),
+            MilliSatoshi::from(100_004)
+        );
         assert_eq!(pay_request.tags.len(), 4);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -762,10 +1508,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000000000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(2000000000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1".to_owned()
@@ -777,7 +1523,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 3);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -794,10 +1540,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000000000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(2000000000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1".to_owned()
@@ -809,7 +1555,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 3);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -827,10 +1573,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000000000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(2000000000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1".to_owned()
@@ -842,7 +1588,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 3);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -859,10 +1605,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(2000000000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(2000000000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap().to_string(),
             "3925b6f67e2c340036ed12093dd44e0368df1b6ea26c53dbe4811f58fd5db8c1".to_owned()
@@ -875,7 +1621,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 4);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -892,10 +1638,10 @@ mod test {
         let pay_request = PaymentRequest::decode(tx_ref).unwrap();
 
         assert_eq!(pay_request.prefix, "lnbc");
-        assert_eq!(pay_request.amount, Some(250_000_000u64));
+        assert_eq!(pay_request.amount, Some(MilliSatoshi::new(250_000_000)));
         assert_eq!(pay_request.payment_hash(), Some(payment_hash));
         assert_eq!(pay_request.timestamp, 1496314658u64);
-        assert!(pay_request.node_id.eq(&PUB_KEY));
+        assert!(pay_request.node_id.eq(&node_key()));
         assert_eq!(
             pay_request.description().unwrap(),
             "ナンセンス 1杯".to_owned()
@@ -904,7 +1650,7 @@ mod test {
         assert_eq!(pay_request.tags.len(), 3);
         assert_eq!(pay_request.encode().unwrap(), tx_ref);
         assert_eq!(
-            pay_request.sign(&SEC_KEY).unwrap().encode().unwrap(),
+            pay_request.sign(&sec_key()).unwrap().encode().unwrap(),
             tx_ref
         );
     }
@@ -920,7 +1666,7 @@ mod test {
             pay_request.prefix.clone(),
             pay_request.amount.clone(),
             pay_request.payment_hash().unwrap(),
-            &SEC_KEY,
+            &sec_key(),
             pay_request.description().unwrap().to_string(),
             pay_request.fallback_address(),
             pay_request.expiry(),
@@ -943,7 +1689,7 @@ mod test {
             pay_request.prefix.clone(),
             pay_request.amount.clone(),
             pay_request.payment_hash().unwrap(),
-            &SEC_KEY,
+            &sec_key(),
             pay_request.description().unwrap(),
             pay_request.fallback_address(),
             pay_request.expiry(),
@@ -954,4 +1700,699 @@ mod test {
 
         assert_eq!(pay_request, new_pay_request);
     }
+
+    #[test]
+    fn test_test_network_round_trip() {
+        let payment_hash = payment_hash();
+        for prefix in vec!["lntbs", "lnbcrt", "lnsb"] {
+            for amount in vec![None, Some(MilliSatoshi::new(250_000_000))] {
+                let pay_request = PaymentRequest::new(
+                    prefix.to_owned(),
+                    amount,
+                    payment_hash.clone(),
+                    &sec_key(),
+                    "1 cup coffee".to_owned(),
+                    None,
+                    Some(60),
+                    vec![],
+                    Some(1496314658),
+                    None,
+                ).unwrap();
+                let encoded = pay_request.encode().unwrap();
+                let hrp = prefix.to_owned() + &amount.map(|a| Amount::encode(a.as_u64())).unwrap_or_default();
+                assert!(encoded.starts_with(&(hrp + "1")));
+
+                let decoded = PaymentRequest::decode(&encoded).unwrap();
+                assert_eq!(decoded.prefix, prefix);
+                assert_eq!(decoded.network(), Currency::from_prefix(prefix));
+                assert!(!decoded.is_for_network(Currency::Bitcoin));
+                assert!(!decoded.is_for_network(Currency::BitcoinTestnet));
+                assert_eq!(decoded.amount, amount);
+                assert_eq!(decoded, pay_request);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_features_preserved() {
+        let mut pay_request = coffee(Some(MilliSatoshi::new(250_000_000)), Some(60));
+        // var_onion_optin and payment_secret plus unknown bits, with a leading zero word
+        let mut words = vec![U5::ZERO];
+        words.extend(Features::from_bits(vec![8, 14, 101, 255]).to_u5_vec());
+        pay_request.update_features(Features::from_u5(&words));
+        let encoded = pay_request.sign(&sec_key()).unwrap().encode().unwrap();
+
+        // edit and re-sign
+        let mut decoded = PaymentRequest::decode(&encoded).unwrap();
+        decoded.update_description("2 cups of coffee".to_owned());
+        decoded.update_expiry(120);
+        let encoded = decoded.sign(&sec_key()).unwrap().encode().unwrap();
+
+        let features = PaymentRequest::decode(&encoded).unwrap().features().unwrap();
+        assert_eq!(features.iter().collect::<Vec<_>>(), vec![8, 14, 101, 255]);
+        assert_eq!(features.raw_u5(), Some(words.as_slice()));
+
+        // used as a template for a new payment request
+        let mut template = PaymentRequest::decode(&encoded).unwrap();
+        template.update_amount(Some(MilliSatoshi::new(1000)));
+        template.update_features(template.features().unwrap());
+        let encoded = template.sign(&sec_key()).unwrap().encode().unwrap();
+        let features = PaymentRequest::decode(&encoded).unwrap().features().unwrap();
+        assert_eq!(features.raw_u5(), Some(words.as_slice()));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        assert!(pay_request.tag_set().is_ok());
+        // issued at 1496314658 with a 60 seconds expiry
+        assert_eq!(pay_request.expires_at(), 1496314718);
+        assert_eq!(
+            pay_request.expiry_time(),
+            Some(UNIX_EPOCH + Duration::from_secs(1496314718))
+        );
+        assert!(pay_request.is_expired().unwrap());
+        assert_eq!(pay_request.time_until_expiry().unwrap(), None);
+
+        let none = ExpiryGrace::none();
+        assert!(pay_request.validate_at(&none, 1496314718).is_ok());
+        assert!(pay_request.validate_at(&none, 1496314719).is_err());
+        assert!(pay_request.validate_at(&none, 1496314657).is_err());
+
+        let grace = ExpiryGrace::new(10, 5);
+        assert!(pay_request.validate_at(&grace, 1496314653).is_ok());
+        assert!(pay_request.validate_at(&grace, 1496314713).is_ok());
+        assert!(pay_request.validate_at(&grace, 1496314714).is_err());
+
+        assert_eq!(pay_request.expiry_or_default(), 60);
+        assert_eq!(
+            pay_request.min_final_cltv_expiry_or_default(),
+            DEFAULT_MIN_FINAL_CLTV_EXPIRY
+        );
+        // the first field wins
+        pay_request.tags.push(Tag::Expiry { seconds: 120 });
+        assert_eq!(pay_request.expiry(), Some(60));
+
+        pay_request.tags.retain(|t| !matches!(*t, Tag::Expiry { .. }));
+        assert_eq!(pay_request.expiry_or_default(), DEFAULT_EXPIRY_SECONDS);
+        assert_eq!(pay_request.expires_at(), 1496314658 + DEFAULT_EXPIRY_SECONDS);
+        pay_request.tags.retain(|t| !matches!(*t, Tag::PaymentHash { .. }));
+        assert!(pay_request.validate_at(&none, 1496314700).is_err());
+        assert!(pay_request.tag_set().is_err());
+
+        let now = expiry::now().unwrap();
+        let pay_request = PaymentRequest::new(
+            "lnbc".to_owned(),
+            None,
+            vec![0u8; 32],
+            &sec_key(),
+            "1 cup coffee".to_owned(),
+            None,
+            None,
+            vec![],
+            None,
+            None,
+        ).unwrap();
+        assert!(pay_request.timestamp >= now && pay_request.timestamp < now + 60);
+        assert!(!pay_request.is_expired().unwrap());
+        let remaining = pay_request.time_until_expiry().unwrap().unwrap();
+        assert!(remaining > Duration::from_secs(DEFAULT_EXPIRY_SECONDS - 60));
+        assert!(remaining <= Duration::from_secs(DEFAULT_EXPIRY_SECONDS));
+        assert!(pay_request.validate(&none).is_ok());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_created_at() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        assert_eq!(
+            pay_request.created_at().unwrap().to_rfc3339(),
+            "2017-06-01T10:57:38+00:00"
+        );
+        pay_request.timestamp = u64::max_value();
+        assert_eq!(pay_request.created_at(), None);
+    }
+
+    #[test]
+    fn test_unknown_tags_round_trip() {
+        // xorshift, so failures are reproducible
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        let unknown_chars = "qzy8g2tvw03j54ke6ua7l";
+        for _ in 0..64 {
+            let mut pay_request = coffee(Some(MilliSatoshi::new(250_000_000)), Some(60));
+            for _ in 0..next(5) + 1 {
+                let tag = unknown_chars.as_bytes()[next(unknown_chars.len())] as char;
+                let tag = char_to_u5(tag).unwrap();
+                let bytes = (0..next(100))
+                    .map(|_| U5::new(next(32) as u8).unwrap())
+                    .collect::<Vec<U5>>();
+                let index = next(pay_request.tags.len() + 1);
+                pay_request.tags.insert(index, Tag::UnknownTag { tag, bytes });
+            }
+            let pay_request = pay_request.sign(&sec_key()).unwrap();
+            let encoded = pay_request.encode().unwrap();
+            let decoded = PaymentRequest::decode(&encoded).unwrap();
+            assert_eq!(decoded, pay_request);
+            assert_eq!(decoded.encode().unwrap(), encoded);
+        }
+    }
+
+    #[test]
+    fn test_sign_with() {
+        use std::cell::RefCell;
+
+        // stands in for an HSM: holds the key and remembers what it was asked to sign
+        struct RemoteSigner {
+            key: SecretKey,
+            digests: RefCell<Vec<[u8; 32]>>,
+        }
+        impl Bolt11Signer for RemoteSigner {
+            fn sign_recoverable(
+                &self,
+                msg_hash: [u8; 32],
+            ) -> Result<(Signature, RecoveryId), Error> {
+                self.digests.borrow_mut().push(msg_hash);
+                Bolt11Signer::sign_recoverable(&self.key, msg_hash)
+            }
+        }
+        struct OfflineSigner;
+        impl Bolt11Signer for OfflineSigner {
+            fn sign_recoverable(&self, _: [u8; 32]) -> Result<(Signature, RecoveryId), Error> {
+                Err(Error::InvalidValue("signer unavailable".to_owned()))
+            }
+        }
+
+        let pay_request = coffee(Some(MilliSatoshi::new(250_000_000)), Some(60));
+        let signer = RemoteSigner {
+            key: sec_key(),
+            digests: RefCell::new(vec![]),
+        };
+        let signed = pay_request.sign_with(&signer).unwrap();
+        assert_eq!(signed, pay_request.sign(&sec_key()).unwrap());
+        assert_eq!(
+            signer.digests.borrow().iter().map(|d| d.to_vec()).collect::<Vec<_>>(),
+            vec![pay_request.hash().unwrap()]
+        );
+        let decoded = PaymentRequest::decode(&signed.encode().unwrap()).unwrap();
+        assert_eq!(decoded.node_id, node_key());
+
+        assert!(pay_request.sign_with(&OfflineSigner).is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_sign_with_async() {
+        use signer::SignatureFuture;
+        use std::future::Future;
+        use std::pin::Pin;
+        use std::ptr;
+        use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+        // answers on the second poll, like a signer behind a network round trip
+        struct RemoteSigner(SecretKey);
+        struct Pending<'a>(&'a SecretKey, [u8; 32], bool);
+        impl<'a> Future for Pending<'a> {
+            type Output = Result<(Signature, RecoveryId), Error>;
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+                if self.2 {
+                    Poll::Ready(Bolt11Signer::sign_recoverable(self.0, self.1))
+                } else {
+                    self.2 = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }
+        }
+        impl AsyncBolt11Signer for RemoteSigner {
+            fn sign_recoverable<'a>(&'a self, msg_hash: [u8; 32]) -> SignatureFuture<'a> {
+                Box::pin(Pending(&self.0, msg_hash, false))
+            }
+        }
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            fn clone(_: *const ()) -> RawWaker {
+                RawWaker::new(ptr::null(), &VTABLE)
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) };
+            let mut cx = Context::from_waker(&waker);
+            let mut future = Box::pin(future);
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            }
+        }
+
+        let pay_request = coffee(Some(MilliSatoshi::new(250_000_000)), Some(60));
+        let signed = pay_request.sign(&sec_key()).unwrap();
+        let signer = RemoteSigner(sec_key());
+        assert_eq!(block_on(pay_request.sign_with_async(&signer)).unwrap(), signed);
+        assert_eq!(block_on(pay_request.sign_with_async(&sec_key())).unwrap(), signed);
+    }
+
+    #[test]
+    fn test_high_s() {
+        use signature::negate_s;
+
+        let encoded = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(encoded).unwrap();
+        assert!(pay_request.has_low_s());
+
+        // negating S gives another valid signature, as issued by some implementations
+        let mut high_s = pay_request.clone();
+        negate_s(&mut high_s.signature);
+        assert!(!high_s.has_low_s());
+        let high_s_encoded = high_s.encode().unwrap();
+        assert_eq!(PaymentRequest::decode(&high_s_encoded).unwrap(), high_s);
+        match PaymentRequest::decode_with_options(&high_s_encoded, &DecodeOptions::strict()) {
+            Err(Error::HighS) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        assert!(high_s.normalize_signature());
+        assert_eq!(high_s, pay_request);
+        assert!(!high_s.normalize_signature());
+    }
+
+    #[test]
+    fn test_encode_signature_length() {
+        let pay_request = coffee(None, None);
+        for length in vec![0, 64, 66] {
+            let mut corrupt = pay_request.clone();
+            corrupt.signature.resize(length, 0);
+            match corrupt.encode() {
+                Err(Error::InvalidSignatureLength(l)) => assert_eq!(l, length),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_unencodable_tag() {
+        let mut pay_request = coffee(None, None);
+        pay_request.tags.push(Tag::UnknownTag {
+            tag: U5::ZERO,
+            bytes: vec![U5::ZERO; 1024],
+        });
+        assert!(pay_request.sign(&sec_key()).is_err());
+        match pay_request.encode() {
+            Err(Error::TagTooLong { tag: 'q', len: 1024 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_amount_encoding_preserved() {
+        let mut pay_request = coffee(Some(MilliSatoshi::new(250_000_000)), Some(60));
+        assert!(pay_request.encode().unwrap().starts_with("lnbc2500u1"));
+
+        // as written by an implementation not using the shortest representation
+        pay_request.amount_encoding = AmountEncoding::Multiplier(Some('n'));
+        let pay_request = pay_request.sign(&sec_key()).unwrap();
+        let encoded = pay_request.encode().unwrap();
+        assert!(encoded.starts_with("lnbc2500000n1"));
+
+        let mut decoded = PaymentRequest::decode(&encoded).unwrap();
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.encode().unwrap(), encoded);
+
+        decoded.update_amount(Some(MilliSatoshi::new(100_000_000_000)));
+        let decoded = decoded.sign(&sec_key()).unwrap();
+        assert!(decoded.encode().unwrap().starts_with("lnbc11"));
+        assert_eq!(
+            PaymentRequest::decode(&decoded.encode().unwrap()).unwrap().amount,
+            Some(MilliSatoshi::new(100_000_000_000))
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        let payment_hash = payment_hash();
+        // descriptions of different lengths give every possible padding of the data part
+        for length in 0..9 {
+            let pay_request = PaymentRequest::new(
+                "lnbc".to_owned(),
+                Some(MilliSatoshi::new(250_000_000)),
+                payment_hash.clone(),
+                &sec_key(),
+                "c".repeat(length),
+                None,
+                None,
+                vec![],
+                Some(1496314658),
+                None,
+            ).unwrap();
+
+            let hash = pay_request.hash().unwrap();
+            let (recovery_id, signature) =
+                PaymentRequest::parse_signature(&pay_request.signature).unwrap();
+            let mut digest = [0u8; 32];
+            digest.copy_from_slice(&hash);
+            let message = Message::parse(&digest);
+            assert!(secp256k1::verify(&message, &signature, &node_key()));
+            assert_eq!(
+                secp256k1::recover(&message, &signature, &recovery_id).unwrap(),
+                node_key()
+            );
+
+            let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+            assert_eq!(decoded.node_id, node_key());
+            assert_eq!(decoded.hash().unwrap(), hash);
+        }
+    }
+
+    #[test]
+    fn test_verify_any() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let other_key = secp256k1::PublicKey::from_secret_key(
+            &secp256k1::SecretKey::parse(&[1u8; 32]).unwrap(),
+        );
+
+        let fleet = vec![other_key.clone(), node_key()];
+        assert_eq!(pay_request.verify_any(&fleet).unwrap(), Some(&fleet[1]));
+        assert_eq!(pay_request.verify_any(&fleet[..1]).unwrap(), None);
+        assert_eq!(pay_request.verify_any(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let valid = PaymentRequest::decode(tx_ref).unwrap();
+        let mut tampered = valid.clone();
+        tampered.update_expiry(120);
+        let mut unsigned = valid.clone();
+        unsigned.signature = Vec::new();
+
+        let results = PaymentRequest::verify_batch(&[valid.clone(), tampered, unsigned, valid]);
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_err());
+        assert!(results[3].is_ok());
+        assert!(PaymentRequest::verify_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_display_and_from_str() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let pay_request = tx_ref.parse::<PaymentRequest>().unwrap();
+        assert_eq!(pay_request, PaymentRequest::decode(tx_ref).unwrap());
+        assert_eq!(pay_request.to_string(), tx_ref);
+        assert_eq!(format!("{}", pay_request), tx_ref);
+        assert!("lnbc1invalid".parse::<PaymentRequest>().is_err());
+    }
+
+    #[test]
+    fn test_explain() {
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+            58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+            9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+            qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+            f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+            dhhwkj";
+        let explained = PaymentRequest::decode(tx_ref).unwrap().explain();
+        let lines = explained.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], "currency:              lnbc (Bitcoin)");
+        assert_eq!(lines[1], "amount:                2000000000 msat (0.02 BTC)");
+        assert!(lines.contains(&"expiry:                3600 seconds (default)"));
+        assert!(lines.contains(
+            &"fallback address:      1RustyRX2oai4EYYDpQGWvEL62BBGqN9T (version 17)"
+        ));
+        assert!(lines.contains(&"route hint:            2 hops"));
+        assert!(lines.contains(
+            &"                       hop via 029e…7255, scid 66051x263430x1800, fee 1msat + 20ppm, \
+              cltv Δ3"
+        ));
+        assert!(lines.contains(&"payment hash:          \
+            0001020304050607080900010203040506070809000102030405060708090102"));
+        assert!(lines.contains(&"min final cltv expiry: 18 blocks (default)"));
+        assert!(lines.last().unwrap().starts_with("signature:"));
+
+        assert_eq!(explain_btc(2_000_000_000), "0.02");
+        assert_eq!(explain_btc(100_000_000_000), "1");
+        assert_eq!(explain_btc(1), "0.00000000001");
+    }
+
+    #[test]
+    fn test_payment_id() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        let mut reordered = pay_request.clone();
+        reordered.tags.reverse();
+        reordered.amount_encoding = AmountEncoding::default();
+        let reordered = reordered.sign(&sec_key()).unwrap();
+        assert!(pay_request.semantically_equal(&reordered));
+        assert_eq!(pay_request.payment_id(), reordered.payment_id());
+
+        let mut reissued = pay_request.clone();
+        reissued.update_expiry(7200);
+        reissued.timestamp += 60;
+        let reissued = reissued.sign(&sec_key()).unwrap();
+        assert!(!pay_request.semantically_equal(&reissued));
+        assert_eq!(pay_request.payment_id(), reissued.payment_id());
+
+        let mut other_amount = pay_request.clone();
+        other_amount.update_amount(Some(MilliSatoshi::new(1)));
+        assert!(!pay_request.semantically_equal(&other_amount));
+        assert_ne!(pay_request.payment_id(), other_amount.payment_id());
+        other_amount.update_amount(None);
+        assert_ne!(pay_request.payment_id(), other_amount.payment_id());
+
+        let mut duplicated = pay_request.clone();
+        duplicated.tags[0] = duplicated.tags[1].clone();
+        assert!(!pay_request.semantically_equal(&duplicated));
+        assert!(!duplicated.semantically_equal(&pay_request));
+    }
+
+    #[test]
+    fn test_uppercase() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let uppercase = pay_request.to_uppercase_string().unwrap();
+        assert_eq!(uppercase, tx_ref.to_uppercase());
+        assert_eq!(PaymentRequest::decode(&uppercase).unwrap(), pay_request);
+
+        let mixed = format!("LNBC{}", &tx_ref[4..]);
+        match PaymentRequest::decode(&mixed) {
+            Err(Error::Bech32Err(bech32::Error::MixedCase)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(all(feature = "serde", feature = "json"))]
+    #[test]
+    fn test_serde() {
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58y\
+        jmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9yq\
+        20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqafqxu\
+        92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9f58\
+        zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2q\
+        qdhhwkj";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let json = serde_json::to_string(&pay_request).unwrap();
+        assert!(json.contains(
+            "\"node_id\":\"03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad\""
+        ));
+        assert!(json.contains(
+            "\"hash\":\"0001020304050607080900010203040506070809000102030405060708090102\""
+        ));
+        assert!(json.contains("\"short_channel_id\":72623859790382856"));
+        let decoded: PaymentRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.encode().unwrap(), tx_ref);
+    }
+
+    #[test]
+    fn test_payee_node_id() {
+        let mut pay_request = coffee(None, None);
+        assert_eq!(pay_request.payee_node_id(), None);
+
+        pay_request.update_payee_node_id();
+        let pay_request = pay_request.sign(&sec_key()).unwrap();
+        assert_eq!(pay_request.payee_node_id(), Some(node_key()));
+        let decoded = PaymentRequest::decode(&pay_request.encode().unwrap()).unwrap();
+        assert_eq!(decoded, pay_request);
+        assert_eq!(decoded.node_id, node_key());
+
+        // the signature must match the explicit payee node id
+        let other_key = secp256k1::SecretKey::parse(&[1u8; 32]).unwrap();
+        let forged = pay_request.sign(&other_key).unwrap();
+        assert!(PaymentRequest::decode(&forged.encode().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_for_each_tag() {
+        #[derive(Default)]
+        struct Collect {
+            chars: String,
+            description: Option<String>,
+            hops: usize,
+        }
+
+        impl TagVisitor for Collect {
+            fn visit_description(&mut self, description: &str) {
+                self.chars.push('d');
+                self.description = Some(description.to_owned());
+            }
+
+            fn visit_description_hash(&mut self, _hash: &[u8]) {
+                self.chars.push('h');
+            }
+
+            fn visit_routing_info(&mut self, path: &[ExtraHop]) {
+                self.chars.push('r');
+                self.hops += path.len();
+            }
+        }
+
+        // BOLT11 test vector with a fallback address and route hints
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+            58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+            9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+            qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+            f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+            dhhwkj";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let mut collect = Collect::default();
+        pay_request.for_each_tag(&mut collect);
+        assert_eq!(collect.chars, "hr");
+        assert_eq!(collect.description, None);
+        assert_eq!(collect.hops, 2);
+    }
+
+    #[test]
+    fn test_matches_preimage() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let preimage = [7u8; 32];
+        for tag in &mut pay_request.tags {
+            if let Tag::PaymentHash { ref mut hash } = *tag {
+                *hash = sha256(&preimage).to_vec();
+            }
+        }
+        assert!(pay_request.matches_preimage(&preimage));
+        assert!(!pay_request.matches_preimage(&[8u8; 32]));
+
+        pay_request.tags.clear();
+        assert!(!pay_request.matches_preimage(&preimage));
+    }
+
+    #[test]
+    fn test_verify_payee() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let mut pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let other_key = PublicKey::from_secret_key(&SecretKey::parse(&[0x22; 32]).unwrap());
+        assert!(pay_request.verify_payee(&node_key()));
+        assert!(!pay_request.verify_payee(&other_key));
+
+        // a node id swapped after decoding doesn't match the signature
+        pay_request.update_node_id(other_key.clone());
+        assert!(!pay_request.verify_payee(&other_key));
+        assert!(!pay_request.verify_payee(&node_key()));
+    }
+
+    #[test]
+    fn test_resign() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        let encoded = pay_request
+            .resign_with_at(
+                &sec_key(),
+                |pay_request| {
+                    pay_request.update_expiry(3600);
+                    pay_request.update_amount(Some(MilliSatoshi::new(300_000_000)));
+                },
+                1_600_000_000,
+            )
+            .unwrap();
+        let resigned = PaymentRequest::decode(&encoded).unwrap();
+        assert_eq!(resigned.timestamp, 1_600_000_000);
+        assert_eq!(resigned.expiry(), Some(3600));
+        assert_eq!(resigned.amount, Some(MilliSatoshi::new(300_000_000)));
+        assert_eq!(resigned.node_id, pay_request.node_id);
+        assert_eq!(resigned.description(), pay_request.description());
+
+        let other_key = SecretKey::parse(&[0x22; 32]).unwrap();
+        assert!(pay_request.resign_with(&other_key, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+
+        assert_eq!(
+            pay_request.summary(),
+            "250000000 msat to \
+             03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad: 1 cup coffee"
+        );
+        let resolver = |node_id: &PublicKey| {
+            if node_id == &node_key() {
+                Some("ACINQ".to_owned())
+            } else {
+                None
+            }
+        };
+        assert_eq!(
+            pay_request.summary_with(&resolver),
+            "250000000 msat to ACINQ: 1 cup coffee"
+        );
+    }
+
+    #[test]
+    fn test_decode_max_length() {
+        let tx_ref = "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2p\
+        kx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvus8g6rfwvs8qun0dfjkxaq8rkx3yf5tcsyz3d73gafnh3cax9r\
+        n449d9p5uxz9ezhhypd0elx87sjle52x86fux2ypatgddc6k63n7erqz25le42c4u4ecky03ylcqca784w";
+
+        let oversized = "lnbc1".to_owned() + &"q".repeat(DEFAULT_MAX_INPUT_LENGTH);
+        assert!(matches!(
+            PaymentRequest::decode(&oversized),
+            Err(Error::InvalidLength(_))
+        ));
+        let options = |max_length| DecodeOptions {
+            max_length,
+            ..DecodeOptions::default()
+        };
+        assert!(matches!(
+            PaymentRequest::decode_with_options(tx_ref, &options(100)),
+            Err(Error::InvalidLength(_))
+        ));
+        assert!(PaymentRequest::decode_with_options(tx_ref, &options(tx_ref.len())).is_ok());
+    }
 }
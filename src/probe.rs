@@ -0,0 +1,118 @@
+//! Invoices for probing routes.
+//!
+//! Route probing tools send payments that are meant to fail at the destination, to learn
+//! whether a route can carry a given amount. The invoices they pay have a random payment hash
+//! whose preimage nobody knows, so they can never be settled. They also have a random payment
+//! secret, which nodes require before forwarding the probe to its destination.
+
+use builder::PaymentRequestBuilder;
+use currency::Currency;
+use features::{Feature, Features};
+use msat::MilliSatoshi;
+use payment_request::PaymentRequest;
+use rand;
+use secp256k1::SecretKey;
+use tag::ExtraHop;
+use types::Error;
+
+/// A signed payment request that is unpayable by design: the preimage of its payment hash was
+/// never known to anyone. Only hand it to probing tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeInvoice {
+    payment_request: PaymentRequest,
+}
+
+impl ProbeInvoice {
+    /// Amount of probe invoices unless told otherwise.
+    pub const DEFAULT_AMOUNT: MilliSatoshi = MilliSatoshi::new(1000);
+    /// Expiry of probe invoices in seconds.
+    pub const EXPIRY_SECONDS: u64 = 60;
+    /// Description of probe invoices.
+    pub const DESCRIPTION: &'static str = "probe";
+
+    /// Generate a probe invoice of `DEFAULT_AMOUNT` with a random payment hash and secret.
+    /// # Params
+    /// `currency` Currency of the probed network.
+    /// `secret_key` Secret key the invoice is signed with.
+    /// `extra_hops` Extra routing information leading to the probed node.
+    pub fn new(
+        currency: Currency,
+        secret_key: &SecretKey,
+        extra_hops: Vec<ExtraHop>,
+    ) -> Result<ProbeInvoice, Error> {
+        ProbeInvoice::with_amount(
+            currency,
+            secret_key,
+            extra_hops,
+            ProbeInvoice::DEFAULT_AMOUNT,
+        )
+    }
+
+    /// Generate a probe invoice of `amount` with a random payment hash and secret.
+    /// # Params
+    /// `currency` Currency of the probed network.
+    /// `secret_key` Secret key the invoice is signed with.
+    /// `extra_hops` Extra routing information leading to the probed node.
+    /// `amount` Probed amount.
+    pub fn with_amount(
+        currency: Currency,
+        secret_key: &SecretKey,
+        extra_hops: Vec<ExtraHop>,
+        amount: MilliSatoshi,
+    ) -> Result<ProbeInvoice, Error> {
+        let payment_hash: [u8; 32] = rand::random();
+        let mut features = Features::new();
+        features.set_required(Feature::VarOnionOptin);
+        features.set_required(Feature::PaymentSecret);
+        let payment_request = PaymentRequestBuilder::new(currency)
+            .amount(amount)
+            .payment_hash(payment_hash.to_vec())
+            .payment_secret(rand::random())
+            .features(features)
+            .description(ProbeInvoice::DESCRIPTION.to_owned())
+            .expiry_seconds(ProbeInvoice::EXPIRY_SECONDS)
+            .extra_hops(extra_hops)
+            .build(secret_key)?;
+        Ok(ProbeInvoice { payment_request })
+    }
+
+    /// The unpayable payment request.
+    pub fn payment_request(&self) -> &PaymentRequest {
+        &self.payment_request
+    }
+
+    /// Returns the encoded representation of the unpayable payment request.
+    pub fn encode(&self) -> Result<String, Error> {
+        self.payment_request.encode()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn probe_invoice() {
+        let secret_key = SecretKey::parse(&[1u8; 32]).unwrap();
+        let probe = ProbeInvoice::new(Currency::BitcoinTestnet, &secret_key, vec![]).unwrap();
+        let other = ProbeInvoice::new(Currency::BitcoinTestnet, &secret_key, vec![]).unwrap();
+
+        let decoded = PaymentRequest::decode(&probe.encode().unwrap()).unwrap();
+        assert_eq!(&decoded, probe.payment_request());
+        assert_eq!(decoded.prefix, "lntb");
+        assert_eq!(decoded.amount, Some(ProbeInvoice::DEFAULT_AMOUNT));
+        assert_eq!(decoded.payment_hash().map(|h| h.len()), Some(32));
+        assert_ne!(decoded.payment_hash(), other.payment_request().payment_hash());
+        assert!(decoded.payment_secret().is_some());
+        assert_ne!(decoded.payment_secret(), other.payment_request().payment_secret());
+        let features = decoded.features().unwrap();
+        assert!(features.requires(Feature::VarOnionOptin));
+        assert!(features.requires(Feature::PaymentSecret));
+        assert!(!decoded.is_expired().unwrap());
+
+        let amount = MilliSatoshi::new(50_000);
+        let probe =
+            ProbeInvoice::with_amount(Currency::Bitcoin, &secret_key, vec![], amount).unwrap();
+        assert_eq!(probe.payment_request().amount, Some(amount));
+    }
+}
@@ -0,0 +1,126 @@
+//! Payment requests without the fields that identify the payee or what is paid for, so they can
+//! be logged, e.g. by support tooling and telemetry.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use msat::MilliSatoshi;
+use payment_request::PaymentRequest;
+use tag::Tag;
+use utils::to_hex;
+
+/// What is left of a payment request after `PaymentRequest::redacted`: its chain, amount,
+/// timestamp, payment hash, expiries and features. The payee node id, description, route
+/// hints, fallback addresses, payment secret, metadata and signature are removed, so it can't be
+/// paid or encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RedactedPaymentRequest {
+    /// Prefix of the payment request, e.g. `lnbc`.
+    pub prefix: String,
+    /// Amount to pay, if any.
+    pub amount: Option<MilliSatoshi>,
+    /// Request timestamp (UNIX format).
+    pub timestamp: u64,
+    /// Kept tags, in their original order: payment hash, expiry, min final cltv expiry and
+    /// features.
+    pub tags: Vec<Tag>,
+    /// Number of route hints that were removed.
+    pub removed_route_hints: usize,
+}
+
+impl RedactedPaymentRequest {
+    /// Return the payment hash if any.
+    pub fn payment_hash(&self) -> Option<&[u8]> {
+        self.tags.iter().find_map(|tag| match *tag {
+            Tag::PaymentHash { ref hash } => Some(&hash[..]),
+            _ => None,
+        })
+    }
+
+    /// Return the expiry in seconds if any.
+    pub fn expiry(&self) -> Option<u64> {
+        self.tags.iter().find_map(|tag| match *tag {
+            Tag::Expiry { seconds } => Some(seconds),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for RedactedPaymentRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.prefix)?;
+        match self.amount {
+            Some(amount) => write!(f, " {}", amount)?,
+            None => write!(f, " any amount")?,
+        }
+        write!(f, ", timestamp {}", self.timestamp)?;
+        if let Some(hash) = self.payment_hash() {
+            write!(f, ", payment hash {}", to_hex(hash))?;
+        }
+        if let Some(expiry) = self.expiry() {
+            write!(f, ", expiry {} seconds", expiry)?;
+        }
+        if self.removed_route_hints > 0 {
+            write!(f, ", route hints removed: {}", self.removed_route_hints)?;
+        }
+        Ok(())
+    }
+}
+
+impl PaymentRequest {
+    /// Return this payment request without the payee node id, description, route hints and
+    /// other fields that identify the payee or the purchase, but with its amount, payment hash
+    /// and expiry. Its `Display` output is meant for logs.
+    pub fn redacted(&self) -> RedactedPaymentRequest {
+        let tags = self.tags
+            .iter()
+            .filter(|tag| {
+                matches!(**tag, Tag::PaymentHash { .. })
+                    || matches!(**tag, Tag::Expiry { .. })
+                    || matches!(**tag, Tag::MinFinalCltvExpiry { .. })
+                    || matches!(**tag, Tag::Features { .. })
+            })
+            .cloned()
+            .collect();
+        RedactedPaymentRequest {
+            prefix: self.prefix.clone(),
+            amount: self.amount,
+            timestamp: self.timestamp,
+            tags,
+            removed_route_hints: self.routes().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redacted() {
+        // BOLT11 test vector with a fallback address and route hints
+        let tx_ref = "lnbc20m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp\
+            58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr\
+            9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqaf\
+            qxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzqj9n4evl6mr5aj9\
+            f58zp6fyjzup6ywn3x6sk8akg5v4tgn2q8g4fhx05wf6juaxu9760yp46454gpg5mtzgerlzezqcqvjnhjh8z3g2qq\
+            dhhwkj";
+        let pay_request = PaymentRequest::decode(tx_ref).unwrap();
+        let redacted = pay_request.redacted();
+        assert_eq!(redacted.amount, pay_request.amount);
+        assert_eq!(redacted.payment_hash(), pay_request.payment_hash().as_ref().map(|h| &h[..]));
+        assert_eq!(redacted.tags.len(), 1);
+        assert_eq!(redacted.removed_route_hints, 1);
+
+        let logged = redacted.to_string();
+        assert_eq!(
+            logged,
+            "lnbc 2000000000 msat, timestamp 1496314658, payment hash \
+             0001020304050607080900010203040506070809000102030405060708090102, \
+             route hints removed: 1"
+        );
+        assert!(!logged.contains(&to_hex(&pay_request.node_id.serialize_compressed())));
+    }
+}
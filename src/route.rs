@@ -0,0 +1,270 @@
+//! Private routes to the payee, as given by the `'r'` fields of payment requests.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use consts::MAX_ROUTE_HOPS;
+use msat::MilliSatoshi;
+use tag::{ExtraHop, Tag};
+use types::Error;
+
+/// Largest proportional fee accepted by default by `RouteHintBuilder`: 100%.
+pub const DEFAULT_MAX_FEE_PROPORTIONAL_MILLIONTHS: u32 = 1_000_000;
+/// Largest cltv expiry delta accepted by default by `RouteHintBuilder`: two weeks of blocks.
+pub const DEFAULT_MAX_CLTV_EXPIRY_DELTA: u16 = 2016;
+
+/// A private route to the payee: the hops of a single `'r'` field, the last one being the
+/// channel that reaches the payee.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingInfo {
+    /// Hops of the route, in the order they are used.
+    pub path: Vec<ExtraHop>,
+}
+
+impl RoutingInfo {
+    /// Return the route of a `RoutingInfo` tag, `None` for other tags.
+    pub fn from_tag(tag: &Tag) -> Option<RoutingInfo> {
+        match *tag {
+            Tag::RoutingInfo { ref path } => Some(RoutingInfo {
+                path: path.to_owned(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Fees charged by the hops of this route to deliver `amount` to the payee. Each hop is paid
+    /// on the amount it forwards, fees of the hops after it included. Fails on overflow.
+    /// # Params
+    /// `amount` Amount the payee receives.
+    pub fn total_fee_msat(&self, amount: MilliSatoshi) -> Result<MilliSatoshi, Error> {
+        let overflow = || Error::InvalidValue(format!("fees to route {} overflow", amount));
+        let forwarded = self.path.iter().rev().fold(Ok(amount.as_u64()), |forward, hop| {
+            forward.and_then(|forward| {
+                let forward = u128::from(forward);
+                let fee = u128::from(hop.fee_base_msat.as_u64())
+                    + forward * u128::from(hop.fee_proportional_millionths) / 1_000_000;
+                let next = forward + fee;
+                if next > u128::from(u64::max_value()) {
+                    Err(overflow())
+                } else {
+                    Ok(next as u64)
+                }
+            })
+        })?;
+        Ok(MilliSatoshi::new(forwarded - amount.as_u64()))
+    }
+
+    /// Sum of the cltv expiry deltas of the hops of this route.
+    pub fn total_cltv_delta(&self) -> u64 {
+        self.path
+            .iter()
+            .map(|hop| u64::from(hop.cltv_expiry_delta))
+            .sum()
+    }
+}
+
+impl From<Vec<ExtraHop>> for RoutingInfo {
+    fn from(path: Vec<ExtraHop>) -> RoutingInfo {
+        RoutingInfo { path }
+    }
+}
+
+/// Builder of the hops of a route hint, checking that they can be encoded and that their fees
+/// and cltv expiry deltas are within bounds, so a misconfigured channel doesn't end up in the
+/// payment requests of a node.
+///
+/// # Examples
+/// ```
+/// use bolt11::route::RouteHintBuilder;
+///
+/// let pub_key = [2u8; 33];
+/// let path = RouteHintBuilder::new()
+///     .raw_hop(&pub_key, 72623859790382856, 1000, 100, 40)
+///     .and_then(|builder| builder.build());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RouteHintBuilder {
+    hops: Vec<ExtraHop>,
+    max_fee_proportional_millionths: u32,
+    max_cltv_expiry_delta: u16,
+}
+
+impl RouteHintBuilder {
+    /// Start building a route hint with the default bounds.
+    pub fn new() -> RouteHintBuilder {
+        RouteHintBuilder {
+            hops: Vec::new(),
+            max_fee_proportional_millionths: DEFAULT_MAX_FEE_PROPORTIONAL_MILLIONTHS,
+            max_cltv_expiry_delta: DEFAULT_MAX_CLTV_EXPIRY_DELTA,
+        }
+    }
+
+    /// Append a hop.
+    pub fn hop(mut self, hop: ExtraHop) -> RouteHintBuilder {
+        self.hops.push(hop);
+        self
+    }
+
+    /// Append a hop from raw values, failing unless `pub_key` is a 33 bytes compressed public
+    /// key. See `ExtraHop::from_raw`.
+    pub fn raw_hop(
+        self,
+        pub_key: &[u8],
+        short_channel_id: u64,
+        fee_base_msat: u32,
+        fee_proportional_millionths: u32,
+        cltv_expiry_delta: u16,
+    ) -> Result<RouteHintBuilder, Error> {
+        let hop = ExtraHop::from_raw(
+            pub_key,
+            short_channel_id,
+            fee_base_msat,
+            fee_proportional_millionths,
+            cltv_expiry_delta,
+        )?;
+        Ok(self.hop(hop))
+    }
+
+    /// Largest proportional fee accepted, in millionths.
+    pub fn max_fee_proportional_millionths(mut self, millionths: u32) -> RouteHintBuilder {
+        self.max_fee_proportional_millionths = millionths;
+        self
+    }
+
+    /// Largest cltv expiry delta accepted, in blocks.
+    pub fn max_cltv_expiry_delta(mut self, blocks: u16) -> RouteHintBuilder {
+        self.max_cltv_expiry_delta = blocks;
+        self
+    }
+
+    /// Return the hops. Fails with `Error::InvalidLength` if there are none or more than
+    /// `MAX_ROUTE_HOPS`, or with `Error::InvalidValue` if a hop's base fee doesn't fit in 32
+    /// bits or its proportional fee or cltv expiry delta exceeds the bounds.
+    pub fn build(self) -> Result<Vec<ExtraHop>, Error> {
+        if self.hops.is_empty() || self.hops.len() > MAX_ROUTE_HOPS {
+            return Err(Error::InvalidLength(format!(
+                "route hints must have between 1 and {} hops, not {}",
+                MAX_ROUTE_HOPS,
+                self.hops.len()
+            )));
+        }
+        for (index, hop) in self.hops.iter().enumerate() {
+            let invalid =
+                |reason: String| Error::InvalidValue(format!("hop {}: {}", index, reason));
+            if hop.fee_base_msat.as_u64() > u64::from(u32::max_value()) {
+                return Err(invalid(format!(
+                    "base fee {} exceeds 32 bits",
+                    hop.fee_base_msat
+                )));
+            }
+            if hop.fee_proportional_millionths > self.max_fee_proportional_millionths {
+                return Err(invalid(format!(
+                    "proportional fee {} exceeds {} millionths",
+                    hop.fee_proportional_millionths, self.max_fee_proportional_millionths
+                )));
+            }
+            if hop.cltv_expiry_delta > self.max_cltv_expiry_delta {
+                return Err(invalid(format!(
+                    "cltv expiry delta {} exceeds {} blocks",
+                    hop.cltv_expiry_delta, self.max_cltv_expiry_delta
+                )));
+            }
+        }
+        Ok(self.hops)
+    }
+}
+
+impl Default for RouteHintBuilder {
+    fn default() -> RouteHintBuilder {
+        RouteHintBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use scid::ShortChannelId;
+    use secp256k1::{PublicKey, SecretKey};
+
+    fn hop(fee_base_msat: u64, fee_proportional_millionths: u32, cltv_delta: u16) -> ExtraHop {
+        ExtraHop {
+            pub_key: PublicKey::from_secret_key(&SecretKey::parse(&[1u8; 32]).unwrap()),
+            short_channel_id: ShortChannelId::new(1),
+            fee_base_msat: MilliSatoshi::new(fee_base_msat),
+            fee_proportional_millionths,
+            cltv_expiry_delta: cltv_delta,
+        }
+    }
+
+    #[test]
+    fn totals() {
+        let route = RoutingInfo::from(vec![hop(1000, 100, 40), hop(1, 1000, 144)]);
+        // the last hop forwards 1_000_000 msat for 1 + 1000 msat, the first one forwards
+        // 1_001_001 msat for 1000 + 100 msat
+        assert_eq!(
+            route.total_fee_msat(MilliSatoshi::new(1_000_000)).unwrap(),
+            MilliSatoshi::new(2101)
+        );
+        assert_eq!(route.total_cltv_delta(), 184);
+
+        let empty = RoutingInfo::from(vec![]);
+        assert_eq!(
+            empty.total_fee_msat(MilliSatoshi::new(1000)).unwrap(),
+            MilliSatoshi::new(0)
+        );
+        assert_eq!(empty.total_cltv_delta(), 0);
+
+        let expensive = RoutingInfo::from(vec![hop(u64::from(u32::max_value()), 0, 0)]);
+        assert!(expensive
+            .total_fee_msat(MilliSatoshi::new(u64::max_value()))
+            .is_err());
+
+        let tag = Tag::RoutingInfo {
+            path: route.path.clone(),
+        };
+        assert_eq!(RoutingInfo::from_tag(&tag), Some(route));
+        assert_eq!(RoutingInfo::from_tag(&Tag::Expiry { seconds: 1 }), None);
+    }
+    #[test]
+    fn route_hint_builder() {
+        let path = RouteHintBuilder::new()
+            .hop(hop(1000, 100, 40))
+            .hop(hop(1, 1000, 144))
+            .build()
+            .unwrap();
+        assert_eq!(path, vec![hop(1000, 100, 40), hop(1, 1000, 144)]);
+        // encodes in a single field
+        assert!(Tag::RoutingInfo { path }.to_vec_u5().is_ok());
+
+        let pub_key = hop(0, 0, 0).pub_key_bytes();
+        let built = RouteHintBuilder::new()
+            .raw_hop(&pub_key, 1, 1000, 100, 40)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(built[0].pub_key_bytes(), pub_key);
+        assert!(RouteHintBuilder::new().raw_hop(&pub_key[1..], 1, 0, 0, 0).is_err());
+        let mut uncompressed = pub_key.clone();
+        uncompressed[0] = 4;
+        assert!(RouteHintBuilder::new().raw_hop(&uncompressed, 1, 0, 0, 0).is_err());
+
+        assert!(RouteHintBuilder::new().build().is_err());
+        let full = (0..MAX_ROUTE_HOPS).fold(RouteHintBuilder::new(), |b, _| b.hop(hop(0, 0, 0)));
+        assert!(Tag::RoutingInfo {
+            path: full.clone().build().unwrap(),
+        }.to_vec_u5()
+            .is_ok());
+        assert!(full.hop(hop(0, 0, 0)).build().is_err());
+
+        assert!(RouteHintBuilder::new()
+            .hop(hop(1 << 32, 0, 0))
+            .build()
+            .is_err());
+        let generous = RouteHintBuilder::new().hop(hop(0, 1_000_001, 0));
+        assert!(generous.clone().build().is_err());
+        assert!(generous.max_fee_proportional_millionths(2_000_000).build().is_ok());
+        let slow = RouteHintBuilder::new().hop(hop(0, 0, 2017));
+        assert!(slow.clone().build().is_err());
+        assert!(slow.max_cltv_expiry_delta(4032).build().is_ok());
+    }
+}
@@ -0,0 +1,350 @@
+//! Normalization and classification of scanned payment strings.
+
+use base58check::FromBase58Check;
+use bitcoin_bech32::WitnessProgram;
+use currency::Currency;
+use payment_request::PaymentRequest;
+use secp256k1::PublicKey;
+use types::Error;
+use uri::{percent_decode, PaymentUri};
+use utils::from_hex;
+
+/// URI scheme used for plain lightning payment requests.
+pub(crate) const LIGHTNING_SCHEME: &str = "lightning:";
+/// URI scheme used by BIP-21 on-chain payment URIs.
+pub(crate) const BITCOIN_SCHEME: &str = "bitcoin:";
+/// BIP-21 query parameter carrying a lightning payment request.
+pub(crate) const LIGHTNING_PARAM: &str = "lightning";
+
+/// Turn a string read from a QR code or the clipboard into a clean payment request.
+///
+/// Surrounding whitespace is trimmed, `lightning:` URIs are unwrapped and the `lightning`
+/// parameter is extracted from BIP-21 `bitcoin:` URIs and percent-decoded. All-uppercase
+/// payment requests (as found in QR codes) are lowercased; mixed case is rejected as bech32
+/// forbids it.
+///
+/// # Examples
+/// ```
+/// use bolt11::scan::normalize_scanned;
+///
+/// assert_eq!(normalize_scanned(" LIGHTNING:LNBC1PVJLUEZ ").unwrap(), "lnbc1pvjluez");
+/// ```
+/// # Params
+/// `input` The scanned string.
+pub fn normalize_scanned(input: &str) -> Result<String, Error> {
+    let trimmed = input.trim();
+    let decoded;
+    let payment_request = if let Some(rest) = strip_scheme(trimmed, LIGHTNING_SCHEME) {
+        rest.trim_start_matches('/')
+    } else if let Some(rest) = strip_scheme(trimmed, BITCOIN_SCHEME) {
+        decoded = percent_decode(bip21_lightning_param(rest)?)?;
+        decoded.trim()
+    } else {
+        trimmed
+    };
+
+    if payment_request.is_empty() {
+        return Err(Error::InvalidLength("empty payment request".to_owned()));
+    }
+    let has_lower = payment_request.chars().any(|c| c.is_lowercase());
+    let has_upper = payment_request.chars().any(|c| c.is_uppercase());
+    if has_lower && has_upper {
+        return Err(Error::InvalidValue(
+            "mixed-case payment requests are not allowed".to_owned(),
+        ));
+    }
+    Ok(payment_request.to_lowercase())
+}
+
+/// Prefix of BOLT12 offers.
+const OFFER_PREFIX: &str = "lno1";
+/// Prefix of bech32 encoded LNURLs.
+const LNURL_PREFIX: &str = "lnurl1";
+/// Schemes of LUD-17 LNURLs.
+const LNURL_SCHEMES: &[&str] = &["lnurlc://", "lnurlw://", "lnurlp://", "keyauth://"];
+
+/// What a scanned payment string turned out to be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaymentStringKind {
+    /// A BOLT11 payment request, decoded and verified.
+    Bolt11(PaymentRequest),
+    /// A BOLT12 offer, lowercased but not decoded.
+    Bolt12Offer(String),
+    /// A bech32 or LUD-17 LNURL, or a lightning address (`user@domain`).
+    Lnurl(String),
+    /// A node URI, `node_id@host:port`.
+    NodeUri {
+        /// Id of the node.
+        node_id: PublicKey,
+        /// Network address of the node.
+        address: String,
+    },
+    /// An on-chain address, bare or from a BIP-21 URI without lightning payment request.
+    OnchainAddress(String),
+}
+
+/// Tell what a string read from a QR code or the clipboard is, so wallets can route it with a
+/// single call. BOLT11 payment requests are decoded, failing if they are invalid, except in
+/// BIP-21 URIs, which fall back to their on-chain address.
+///
+/// # Params
+/// `input` The scanned string.
+pub fn classify(input: &str) -> Result<PaymentStringKind, Error> {
+    let trimmed = input.trim();
+    if strip_scheme(trimmed, BITCOIN_SCHEME).is_some() {
+        return classify_bip21(trimmed);
+    }
+    let payment_string = strip_scheme(trimmed, LIGHTNING_SCHEME)
+        .map_or(trimmed, |rest| rest.trim_start_matches('/'));
+    let lowercase = payment_string.to_lowercase();
+
+    if lowercase.starts_with(LNURL_PREFIX)
+        || LNURL_SCHEMES.iter().any(|scheme| lowercase.starts_with(scheme))
+    {
+        Ok(PaymentStringKind::Lnurl(payment_string.to_owned()))
+    } else if lowercase.starts_with(OFFER_PREFIX) {
+        Ok(PaymentStringKind::Bolt12Offer(lowercase))
+    } else if lowercase
+        .rfind('1')
+        .and_then(|separator| Currency::split_hrp(&lowercase[..separator]))
+        .is_some()
+    {
+        decode_bolt11(payment_string)
+    } else if let Some(at) = payment_string.find('@') {
+        let (id, address) = (&payment_string[..at], &payment_string[at + 1..]);
+        match parse_node_id(id) {
+            Some(node_id) => Ok(PaymentStringKind::NodeUri {
+                node_id,
+                address: address.to_owned(),
+            }),
+            None if !id.is_empty() && address.contains('.') => {
+                Ok(PaymentStringKind::Lnurl(payment_string.to_owned()))
+            }
+            None => Err(unrecognized()),
+        }
+    } else if payment_string.from_base58check().is_ok()
+        || WitnessProgram::from_address(payment_string.to_owned()).is_ok()
+    {
+        Ok(PaymentStringKind::OnchainAddress(payment_string.to_owned()))
+    } else {
+        Err(unrecognized())
+    }
+}
+
+/// Classify a BIP-21 URI by its payment request, or by its on-chain address if it has none or
+/// it is invalid. Other errors, e.g. unknown required parameters, are kept.
+fn classify_bip21(uri: &str) -> Result<PaymentStringKind, Error> {
+    let payment_uri = PaymentUri::parse(uri).or_else(|err| {
+        PaymentUri::parse(&without_lightning_param(uri))
+            .ok()
+            .filter(|onchain| onchain.address.is_some())
+            .ok_or(err)
+    })?;
+    match (payment_uri.payment_request, payment_uri.address) {
+        (Some(payment_request), _) => Ok(PaymentStringKind::Bolt11(payment_request)),
+        (None, Some(address)) => Ok(PaymentStringKind::OnchainAddress(address)),
+        (None, None) => Err(unrecognized()),
+    }
+}
+
+/// Remove the `lightning` query parameter of a BIP-21 URI.
+fn without_lightning_param(uri: &str) -> String {
+    let mut parts = uri.splitn(2, '?');
+    let address = parts.next().unwrap_or_default();
+    let params = parts
+        .next()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| {
+            !param
+                .split('=')
+                .next()
+                .unwrap_or_default()
+                .eq_ignore_ascii_case(LIGHTNING_PARAM)
+        })
+        .collect::<Vec<&str>>();
+    if params.is_empty() {
+        address.to_owned()
+    } else {
+        format!("{}?{}", address, params.join("&"))
+    }
+}
+
+/// Decode a BOLT11 payment request, possibly wrapped in a URI.
+fn decode_bolt11(input: &str) -> Result<PaymentStringKind, Error> {
+    normalize_scanned(input)
+        .and_then(|payment_request| PaymentRequest::decode(&payment_request))
+        .map(PaymentStringKind::Bolt11)
+}
+
+/// Parse a hex encoded compressed public key.
+fn parse_node_id(hex: &str) -> Option<PublicKey> {
+    if hex.len() != 66 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bytes = from_hex(hex).ok()?;
+    let mut compressed = [0u8; 33];
+    compressed.copy_from_slice(&bytes);
+    PublicKey::parse_compressed(&compressed).ok()
+}
+
+fn unrecognized() -> Error {
+    Error::InvalidValue("unrecognized payment string".to_owned())
+}
+
+/// Strip a URI scheme, ignoring its case.
+pub(crate) fn strip_scheme<'a>(input: &'a str, scheme: &str) -> Option<&'a str> {
+    input
+        .get(..scheme.len())
+        .filter(|s| s.eq_ignore_ascii_case(scheme))
+        .map(|_| &input[scheme.len()..])
+}
+
+/// Extract the `lightning` query parameter of a BIP-21 URI (without its scheme).
+fn bip21_lightning_param(uri: &str) -> Result<&str, Error> {
+    uri.splitn(2, '?')
+        .nth(1)
+        .and_then(|query| {
+            query
+                .split('&')
+                .filter_map(|param| {
+                    let mut kv = param.splitn(2, '=');
+                    match (kv.next(), kv.next()) {
+                        (Some(key), Some(value)) if key.eq_ignore_ascii_case(LIGHTNING_PARAM) => {
+                            Some(value)
+                        }
+                        _ => None,
+                    }
+                })
+                .next()
+        })
+        .ok_or(Error::InvalidValue(
+            "bitcoin URI has no lightning parameter".to_owned(),
+        ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        let invoice = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqf";
+        let upper = invoice.to_uppercase();
+
+        assert_eq!(normalize_scanned(invoice).unwrap(), invoice);
+        assert_eq!(normalize_scanned(&upper).unwrap(), invoice);
+        assert_eq!(
+            normalize_scanned(&format!("  lightning:{}\n", invoice)).unwrap(),
+            invoice
+        );
+        assert_eq!(
+            normalize_scanned(&format!("LIGHTNING:{}", upper)).unwrap(),
+            invoice
+        );
+        assert_eq!(
+            normalize_scanned(&format!("lightning://{}", invoice)).unwrap(),
+            invoice
+        );
+        assert_eq!(
+            normalize_scanned(&format!(
+                "bitcoin:BC1QYLH3U67J673H6Y6ALV70M0PL2YZ53TZHVXGG7U?amount=0.00001&lightning={}",
+                upper
+            )).unwrap(),
+            invoice
+        );
+        assert_eq!(
+            normalize_scanned(&format!(
+                "bitcoin:1RustyRX2oai4EYYDpQGWvEL62BBGqN9T?lightning=%6C{}",
+                &invoice[1..]
+            )).unwrap(),
+            invoice
+        );
+    }
+
+    #[test]
+    fn normalize_errors() {
+        assert!(normalize_scanned("   ").is_err());
+        assert!(normalize_scanned("lightning:").is_err());
+        assert!(normalize_scanned("lnbc2500u1PVJLUEZ").is_err());
+        assert!(normalize_scanned("bitcoin:bc1qylh3u67j673h6y6alv70m0pl2yz53tzhvxgg7u").is_err());
+    }
+
+    #[test]
+    fn classify_payment_strings() {
+        let invoice = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+            q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfh\
+            q77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+        let decoded = PaymentRequest::decode(invoice).unwrap();
+        for input in vec![
+            invoice.to_owned(),
+            invoice.to_uppercase(),
+            format!("lightning:{}", invoice),
+            format!("bitcoin:1RustyRX2oai4EYYDpQGWvEL62BBGqN9T?lightning={}", invoice),
+            format!("bitcoin:1RustyRX2oai4EYYDpQGWvEL62BBGqN9T?lightning=%6C{}", &invoice[1..]),
+        ] {
+            assert_eq!(
+                classify(&input).unwrap(),
+                PaymentStringKind::Bolt11(decoded.clone())
+            );
+        }
+        assert!(classify(&invoice.replace("fj9srp", "fj9srq")).is_err());
+
+        let offer = "lno1pqps7sjqpgtyzm3qv4uxzmtsd3jjqer9wd3hy6tsw35k7msjzfpy7nz5yqcnygrfdej82um5\
+            wf5k2uckyypwa3eyt44h6txtxquqh7lz5djge4afgfjn7k4rgrkuag0jsd5xvxg";
+        assert_eq!(
+            classify(offer).unwrap(),
+            PaymentStringKind::Bolt12Offer(offer.to_owned())
+        );
+        let lnurl = "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV\
+            34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS";
+        assert_eq!(
+            classify(lnurl).unwrap(),
+            PaymentStringKind::Lnurl(lnurl.to_owned())
+        );
+        assert_eq!(
+            classify("lnurlp://example.com/pay").unwrap(),
+            PaymentStringKind::Lnurl("lnurlp://example.com/pay".to_owned())
+        );
+        assert_eq!(
+            classify("satoshi@example.com").unwrap(),
+            PaymentStringKind::Lnurl("satoshi@example.com".to_owned())
+        );
+
+        let node_id = "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255";
+        match classify(&format!("{}@127.0.0.1:9735", node_id)).unwrap() {
+            PaymentStringKind::NodeUri { address, .. } => assert_eq!(address, "127.0.0.1:9735"),
+            kind => panic!("unexpected {:?}", kind),
+        }
+
+        assert_eq!(
+            classify("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T").unwrap(),
+            PaymentStringKind::OnchainAddress("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T".to_owned())
+        );
+        assert_eq!(
+            classify("bitcoin:bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4?amount=0.1").unwrap(),
+            PaymentStringKind::OnchainAddress(
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned()
+            )
+        );
+        assert_eq!(
+            classify("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap(),
+            PaymentStringKind::OnchainAddress(
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned()
+            )
+        );
+        for input in vec![
+            format!("bitcoin:1RustyRX2oai4EYYDpQGWvEL62BBGqN9T?lightning={}", &invoice[1..]),
+            "bitcoin:1RustyRX2oai4EYYDpQGWvEL62BBGqN9T?lightning=%zz&label=coffee".to_owned(),
+        ] {
+            assert_eq!(
+                classify(&input).unwrap(),
+                PaymentStringKind::OnchainAddress("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T".to_owned())
+            );
+        }
+        assert!(classify(&format!("bitcoin:?lightning={}", &invoice[1..])).is_err());
+        assert!(classify("bitcoin:1RustyRX2oai4EYYDpQGWvEL62BBGqN9T?req-pay=1").is_err());
+        assert!(classify("hello").is_err());
+        assert!(classify("@example.com").is_err());
+    }
+}
@@ -0,0 +1,147 @@
+//! Short channel ids.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use types::Error;
+
+/// Largest block height or transaction index, which are encoded on 24 bits.
+const MAX_24_BITS: u32 = 0xff_ffff;
+
+/// Short channel id, locating the funding output of a channel in the blockchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShortChannelId(u64);
+
+impl ShortChannelId {
+    /// Create a short channel id from its 64-bit representation.
+    pub fn new(id: u64) -> ShortChannelId {
+        ShortChannelId(id)
+    }
+
+    /// Create a short channel id from its parts, failing if the block height or the
+    /// transaction index doesn't fit in 24 bits.
+    /// # Params
+    /// `block_height` Height of the block containing the funding transaction.
+    /// `tx_index` Index of the funding transaction in the block.
+    /// `output_index` Index of the funding output in the transaction.
+    pub fn from_parts(
+        block_height: u32,
+        tx_index: u32,
+        output_index: u16,
+    ) -> Result<ShortChannelId, Error> {
+        if block_height > MAX_24_BITS || tx_index > MAX_24_BITS {
+            return Err(Error::InvalidValue(format!(
+                "short channel id {}x{}x{} out of range",
+                block_height, tx_index, output_index
+            )));
+        }
+        Ok(ShortChannelId(
+            u64::from(block_height) << 40 | u64::from(tx_index) << 16 | u64::from(output_index),
+        ))
+    }
+
+    /// Return the 64-bit representation.
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Height of the block containing the funding transaction.
+    pub fn block_height(&self) -> u32 {
+        (self.0 >> 40) as u32
+    }
+
+    /// Index of the funding transaction in its block.
+    pub fn tx_index(&self) -> u32 {
+        (self.0 >> 16) as u32 & MAX_24_BITS
+    }
+
+    /// Index of the funding output in its transaction.
+    pub fn output_index(&self) -> u16 {
+        self.0 as u16
+    }
+}
+
+impl From<u64> for ShortChannelId {
+    fn from(id: u64) -> ShortChannelId {
+        ShortChannelId(id)
+    }
+}
+
+impl From<ShortChannelId> for u64 {
+    fn from(id: ShortChannelId) -> u64 {
+        id.0
+    }
+}
+
+impl fmt::Display for ShortChannelId {
+    /// Format as `BLOCKxTXxOUTPUT`, as lnd and c-lightning do.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}x{}",
+            self.block_height(),
+            self.tx_index(),
+            self.output_index()
+        )
+    }
+}
+
+impl FromStr for ShortChannelId {
+    type Err = Error;
+
+    /// Parse the `BLOCKxTXxOUTPUT` notation.
+    fn from_str(s: &str) -> Result<ShortChannelId, Error> {
+        let invalid = || Error::InvalidValue(format!("invalid short channel id `{}`", s));
+        let parts = s.split('x').collect::<Vec<&str>>();
+        match parts.as_slice() {
+            [block_height, tx_index, output_index] => ShortChannelId::from_parts(
+                block_height.parse().map_err(|_| invalid())?,
+                tx_index.parse().map_err(|_| invalid())?,
+                output_index.parse().map_err(|_| invalid())?,
+            ),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(ShortChannelId::from(113249697726464).to_string(), "103x1x0");
+        assert_eq!(ShortChannelId::from(u64::max_value()).to_string(), "16777215x16777215x65535");
+    }
+
+    #[test]
+    fn parts() {
+        let id = ShortChannelId::from_parts(103, 1, 0).unwrap();
+        assert_eq!(id, ShortChannelId::from(113249697726464));
+        assert_eq!(
+            (id.block_height(), id.tx_index(), id.output_index()),
+            (103, 1, 0)
+        );
+        let max = ShortChannelId::from(u64::max_value());
+        assert_eq!(
+            ShortChannelId::from_parts(max.block_height(), max.tx_index(), max.output_index())
+                .unwrap(),
+            max
+        );
+        assert!(ShortChannelId::from_parts(1 << 24, 0, 0).is_err());
+        assert!(ShortChannelId::from_parts(0, 1 << 24, 0).is_err());
+    }
+
+    #[test]
+    fn from_str() {
+        for id in vec![0, 113249697726464, 72623859790382856, u64::max_value()] {
+            let id = ShortChannelId::from(id);
+            assert_eq!(id.to_string().parse::<ShortChannelId>().unwrap(), id);
+        }
+        for invalid in vec!["", "103x1", "103x1x0x0", "103x1x65536", "16777216x0x0", "ax1x0"] {
+            assert!(invalid.parse::<ShortChannelId>().is_err());
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Compact recoverable signatures of payment requests: 64 bytes of `r` and `s` followed by
+//! the recovery id.
+
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use consts::SIGNATURE_BYTES;
+use secp256k1;
+use secp256k1::RecoveryId;
+use types::Error;
+#[cfg(feature = "bitcoin")]
+use alloc::string::ToString;
+#[cfg(feature = "bitcoin")]
+use bitcoin::secp256k1::ecdsa;
+
+/// ASN.1 tag of a sequence.
+const DER_SEQUENCE: u8 = 0x30;
+/// ASN.1 tag of an integer.
+const DER_INTEGER: u8 = 0x02;
+
+/// Order of the secp256k1 group, big-endian.
+const CURVE_ORDER: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c, 0xd0, 0x36, 0x41, 0x41,
+];
+
+/// Half the order of the secp256k1 group, rounded down: the largest low `s` value.
+const HALF_CURVE_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Largest recovery id.
+const MAX_RECOVERY_ID: u8 = 3;
+
+/// A recoverable ECDSA signature, as found at the end of payment requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    r: [u8; 32],
+    s: [u8; 32],
+    recovery_id: u8,
+}
+
+impl Signature {
+    /// Create a signature from its parts, failing with `Error::InvalidRecoveryId` unless
+    /// `recovery_id` is between 0 and 3.
+    /// # Params
+    /// `r` `r` value, big-endian.
+    /// `s` `s` value, big-endian.
+    /// `recovery_id` Recovery id.
+    pub fn new(r: [u8; 32], s: [u8; 32], recovery_id: u8) -> Result<Signature, Error> {
+        if recovery_id > MAX_RECOVERY_ID {
+            return Err(Error::InvalidRecoveryId(recovery_id));
+        }
+        Ok(Signature { r, s, recovery_id })
+    }
+
+    /// Return the `r` value, big-endian.
+    pub fn r(&self) -> &[u8; 32] {
+        &self.r
+    }
+
+    /// Return the `s` value, big-endian.
+    pub fn s(&self) -> &[u8; 32] {
+        &self.s
+    }
+
+    /// Return the recovery id, from 0 to 3.
+    pub fn recovery_id(&self) -> u8 {
+        self.recovery_id
+    }
+
+    /// Decode a 65-byte compact signature: `r`, `s` and the recovery id. Fails with
+    /// `Error::InvalidSignatureLength` on any other length, and like `new` on an invalid
+    /// recovery id.
+    pub fn decode(bytes: &[u8]) -> Result<Signature, Error> {
+        if bytes.len() != SIGNATURE_BYTES {
+            return Err(Error::InvalidSignatureLength(bytes.len()));
+        }
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        r.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..64]);
+        Signature::new(r, s, bytes[64])
+    }
+
+    /// Return the 65-byte compact encoding of this signature.
+    pub fn encode(&self) -> Vec<u8> {
+        [&self.r[..], &self.s[..], &[self.recovery_id][..]].concat()
+    }
+
+    /// Return the DER encoding of `r` and `s`, without the recovery id.
+    pub fn to_der(&self) -> Vec<u8> {
+        let (r, s) = (der_integer(&self.r), der_integer(&self.s));
+        [&[DER_SEQUENCE, (r.len() + s.len()) as u8][..], &r[..], &s[..]].concat()
+    }
+
+    /// Decode a strict DER signature, which doesn't carry the recovery id. Fails like `new` on
+    /// an invalid recovery id.
+    /// # Params
+    /// `der` DER encoded `r` and `s`.
+    /// `recovery_id` Recovery id of the signature.
+    pub fn from_der(der: &[u8], recovery_id: u8) -> Result<Signature, Error> {
+        let invalid = || Error::InvalidValue("invalid DER signature".to_owned());
+        match der {
+            [DER_SEQUENCE, length, rest @ ..] if *length as usize == rest.len() => {
+                let (r, rest) = parse_der_integer(rest).ok_or_else(invalid)?;
+                let (s, rest) = parse_der_integer(rest).ok_or_else(invalid)?;
+                if rest.is_empty() {
+                    Signature::new(r, s, recovery_id)
+                } else {
+                    Err(invalid())
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Convert to the signature and recovery id types of `secp256k1`.
+    pub fn to_secp256k1(&self) -> Result<(secp256k1::Signature, RecoveryId), Error> {
+        let mut compact = [0u8; 64];
+        compact[..32].copy_from_slice(&self.r);
+        compact[32..].copy_from_slice(&self.s);
+        Ok((
+            secp256k1::Signature::parse(&compact),
+            RecoveryId::parse(self.recovery_id)?,
+        ))
+    }
+
+    /// Convert to the non-recoverable signature type of `bitcoin`.
+    #[cfg(feature = "bitcoin")]
+    pub fn to_ecdsa(&self) -> Result<ecdsa::Signature, Error> {
+        ecdsa::Signature::from_compact(&self.encode()[..64])
+            .map_err(|e| Error::InvalidValue(e.to_string()))
+    }
+
+    /// Convert to the recoverable signature type of `bitcoin`.
+    #[cfg(feature = "bitcoin")]
+    pub fn to_recoverable(&self) -> Result<ecdsa::RecoverableSignature, Error> {
+        ecdsa::RecoveryId::from_i32(i32::from(self.recovery_id))
+            .and_then(|id| ecdsa::RecoverableSignature::from_compact(&self.encode()[..64], id))
+            .map_err(|e| Error::InvalidValue(e.to_string()))
+    }
+}
+
+#[cfg(feature = "bitcoin")]
+impl From<ecdsa::RecoverableSignature> for Signature {
+    fn from(signature: ecdsa::RecoverableSignature) -> Signature {
+        let (recovery_id, compact) = signature.serialize_compact();
+        let mut bytes = compact.to_vec();
+        bytes.push(recovery_id.to_i32() as u8);
+        Signature::decode(&bytes).expect("compact signatures are 64 bytes")
+    }
+}
+
+/// DER integer holding the big-endian unsigned `value`: leading zeros are dropped and a zero is
+/// prepended if the first bit is set, so the value reads as positive.
+fn der_integer(value: &[u8; 32]) -> Vec<u8> {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(31);
+    let mut integer = vec![DER_INTEGER, 0];
+    if value[start] & 0x80 != 0 {
+        integer.push(0);
+    }
+    integer.extend_from_slice(&value[start..]);
+    integer[1] = (integer.len() - 2) as u8;
+    integer
+}
+
+/// Parse a minimally encoded, positive DER integer of at most 32 bytes, returning it padded to
+/// 32 bytes and the rest of the input.
+fn parse_der_integer(der: &[u8]) -> Option<([u8; 32], &[u8])> {
+    match der {
+        [DER_INTEGER, length, rest @ ..] if *length as usize <= rest.len() && *length > 0 => {
+            let (integer, rest) = rest.split_at(*length as usize);
+            let negative = integer[0] & 0x80 != 0;
+            let padded = integer.len() > 1 && integer[0] == 0 && integer[1] & 0x80 == 0;
+            let value = if integer[0] == 0 && integer.len() > 1 {
+                &integer[1..]
+            } else {
+                integer
+            };
+            if negative || padded || value.len() > 32 {
+                return None;
+            }
+            let mut bytes = [0u8; 32];
+            bytes[32 - value.len()..].copy_from_slice(value);
+            Some((bytes, rest))
+        }
+        _ => None,
+    }
+}
+
+/// Return true if the `s` value of a compact signature is at most half the curve order, the
+/// form emitted by other Lightning implementations. Signatures shorter than 64 bytes aren't.
+pub fn is_low_s(signature: &[u8]) -> bool {
+    signature
+        .get(32..64)
+        .map_or(false, |s| s <= &HALF_CURVE_ORDER[..])
+}
+
+/// Replace a high `s` value of a 65-byte compact signature by its negation, flipping the
+/// recovery id so that it still recovers the same public key. Returns true if the signature
+/// was changed.
+pub fn normalize_s(signature: &mut [u8]) -> bool {
+    if signature.len() != 65 || is_low_s(signature) {
+        return false;
+    }
+    negate_s(signature);
+    true
+}
+
+/// Replace the `s` value of a 65-byte compact signature by its negation modulo the curve order
+/// and flip the recovery id.
+pub(crate) fn negate_s(signature: &mut [u8]) {
+    let mut borrow = 0i16;
+    for (byte, order) in signature[32..64].iter_mut().zip(CURVE_ORDER.iter()).rev() {
+        let difference = i16::from(*order) - i16::from(*byte) - borrow;
+        borrow = if difference < 0 { 1 } else { 0 };
+        *byte = (difference + 256 * borrow) as u8;
+    }
+    signature[64] ^= 1;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn low_s() {
+        let mut signature = [0u8; 65];
+        signature[32..64].copy_from_slice(&HALF_CURVE_ORDER);
+        assert!(is_low_s(&signature));
+        assert!(!normalize_s(&mut signature));
+
+        // n - 1 becomes 1
+        signature[32..64].copy_from_slice(&CURVE_ORDER);
+        signature[63] -= 1;
+        assert!(!is_low_s(&signature));
+        assert!(normalize_s(&mut signature));
+        assert_eq!(signature[32..63], [0u8; 31]);
+        assert_eq!(signature[63], 1);
+        assert_eq!(signature[64], 1);
+
+        // half the order plus one becomes half the order
+        signature[32..64].copy_from_slice(&HALF_CURVE_ORDER);
+        signature[63] += 1;
+        assert!(normalize_s(&mut signature));
+        assert_eq!(signature[32..64], HALF_CURVE_ORDER);
+        assert_eq!(signature[64], 0);
+
+        assert!(!is_low_s(&[0u8; 10]));
+    }
+
+    #[test]
+    fn der() {
+        let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+        r[0] = 0x80;
+        s[30] = 0x01;
+        s[31] = 0x02;
+        let signature = Signature::new(r, s, 1).unwrap();
+        let der = signature.to_der();
+        assert_eq!(der[..5], [DER_SEQUENCE, 2 + 33 + 2 + 2, DER_INTEGER, 33, 0]);
+        assert_eq!(der[37..], [DER_INTEGER, 2, 0x01, 0x02]);
+        assert_eq!(Signature::from_der(&der, 1).unwrap(), signature);
+
+        let compact = signature.encode();
+        assert_eq!(compact.len(), 65);
+        assert_eq!(Signature::decode(&compact).unwrap(), signature);
+        for length in vec![0, 64, 66] {
+            let mut bytes = compact.clone();
+            bytes.resize(length, 0);
+            match Signature::decode(&bytes) {
+                Err(Error::InvalidSignatureLength(l)) => assert_eq!(l, length),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+
+        for invalid in vec![
+            // trailing byte
+            [&der[..], &[0u8][..]].concat(),
+            // wrong sequence length
+            [&[DER_SEQUENCE, der[1] + 1][..], &der[2..]].concat(),
+            // negative r
+            [&[DER_SEQUENCE, 38, DER_INTEGER, 32][..], &signature.r()[..], &der[37..]].concat(),
+            // needlessly padded s
+            [&[DER_SEQUENCE, 40][..], &der[2..37], &[DER_INTEGER, 3, 0, 0x01, 0x02][..]].concat(),
+        ] {
+            assert!(Signature::from_der(&invalid, 1).is_err());
+        }
+    }
+
+    #[test]
+    fn recovery_id() {
+        assert_eq!(Signature::new([1; 32], [1; 32], 3).unwrap().recovery_id(), 3);
+        let mut compact = vec![1u8; 65];
+        compact[64] = 4;
+        for result in vec![
+            Signature::new([1; 32], [1; 32], 4),
+            Signature::decode(&compact),
+            Signature::from_der(&Signature::decode(&[1u8; 65]).unwrap().to_der(), 4),
+        ] {
+            match result {
+                Err(Error::InvalidRecoveryId(4)) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "bitcoin")]
+    #[test]
+    fn bitcoin_conversions() {
+        let compact = (0..65u8).map(|i| if i < 64 { i + 1 } else { 2 }).collect::<Vec<u8>>();
+        let signature = Signature::decode(&compact).unwrap();
+        let ecdsa = signature.to_ecdsa().unwrap();
+        assert_eq!(ecdsa.serialize_der().to_vec(), signature.to_der());
+        let recoverable = signature.to_recoverable().unwrap();
+        assert_eq!(Signature::from(recoverable), signature);
+    }
+}
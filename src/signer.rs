@@ -0,0 +1,113 @@
+//! Signing of payment requests by keys held outside of this crate.
+
+use secp256k1;
+use secp256k1::{Message, PublicKey, RecoveryId, SecretKey, Signature};
+use types::Error;
+use zeroize::Zeroizing;
+#[cfg(feature = "async")]
+use alloc::boxed::Box;
+#[cfg(feature = "async")]
+use core::future;
+#[cfg(feature = "async")]
+use core::future::Future;
+#[cfg(feature = "async")]
+use core::pin::Pin;
+
+/// Produces recoverable signatures for payment requests, e.g. by forwarding the digest to an
+/// HSM or a remote signer daemon so the node key never has to be handed to this crate.
+///
+/// Secret keys implement this trait by signing locally.
+pub trait Bolt11Signer {
+    /// Sign the SHA256 digest of a payment request, returning the signature and the id needed
+    /// to recover the node id from it.
+    fn sign_recoverable(&self, msg_hash: [u8; 32]) -> Result<(Signature, RecoveryId), Error>;
+}
+
+impl Bolt11Signer for SecretKey {
+    fn sign_recoverable(&self, msg_hash: [u8; 32]) -> Result<(Signature, RecoveryId), Error> {
+        secp256k1::sign(&Message::parse(&msg_hash), self).map_err(Error::SignatureError)
+    }
+}
+
+/// Signs with a node key kept in memory that is wiped when the signer is dropped, for services
+/// issuing invoices that don't want node keys lingering in freed memory.
+///
+/// The key is only turned into a `SecretKey` for the duration of each signature. libsecp256k1
+/// keeps its own copy of the key in every `SecretKey` and doesn't wipe it on drop, so each
+/// signature briefly leaves one such copy on the stack that this signer can't clear.
+pub struct LocalSigner {
+    key: Zeroizing<[u8; 32]>,
+    node_id: PublicKey,
+}
+
+impl LocalSigner {
+    /// Create a signer for the secret key `key`, failing if it isn't a valid key.
+    pub fn new(key: Zeroizing<[u8; 32]>) -> Result<LocalSigner, Error> {
+        let node_id = PublicKey::from_secret_key(&SecretKey::parse(&key)?);
+        Ok(LocalSigner { key, node_id })
+    }
+
+    /// Id of the node whose key this signer holds.
+    pub fn node_id(&self) -> &PublicKey {
+        &self.node_id
+    }
+}
+
+impl Bolt11Signer for LocalSigner {
+    fn sign_recoverable(&self, msg_hash: [u8; 32]) -> Result<(Signature, RecoveryId), Error> {
+        let secret_key = SecretKey::parse(&self.key)?;
+        Bolt11Signer::sign_recoverable(&secret_key, msg_hash)
+    }
+}
+
+/// Future returned by `AsyncBolt11Signer::sign_recoverable`.
+#[cfg(feature = "async")]
+pub type SignatureFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<(Signature, RecoveryId), Error>> + Send + 'a>>;
+
+/// Asynchronous counterpart of `Bolt11Signer`, for signers reached over the network, e.g. a
+/// validating signer or an LSP signing endpoint, so creating an invoice doesn't block on the
+/// round trip.
+///
+/// Secret keys implement this trait by signing locally, the future being ready immediately.
+#[cfg(feature = "async")]
+pub trait AsyncBolt11Signer {
+    /// Sign the SHA256 digest of a payment request, resolving to the signature and the id
+    /// needed to recover the node id from it.
+    fn sign_recoverable<'a>(&'a self, msg_hash: [u8; 32]) -> SignatureFuture<'a>;
+}
+
+#[cfg(feature = "async")]
+impl AsyncBolt11Signer for SecretKey {
+    fn sign_recoverable<'a>(&'a self, msg_hash: [u8; 32]) -> SignatureFuture<'a> {
+        Box::pin(future::ready(Bolt11Signer::sign_recoverable(self, msg_hash)))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncBolt11Signer for LocalSigner {
+    fn sign_recoverable<'a>(&'a self, msg_hash: [u8; 32]) -> SignatureFuture<'a> {
+        Box::pin(future::ready(Bolt11Signer::sign_recoverable(self, msg_hash)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn local_signer() {
+        let key = Zeroizing::new([7u8; 32]);
+        let signer = LocalSigner::new(key.clone()).unwrap();
+        let secret_key = SecretKey::parse(&key).unwrap();
+        let (signature, recovery_id) =
+            Bolt11Signer::sign_recoverable(&signer, [1u8; 32]).unwrap();
+        let (expected, expected_id) =
+            Bolt11Signer::sign_recoverable(&secret_key, [1u8; 32]).unwrap();
+        assert_eq!(signature.serialize()[..], expected.serialize()[..]);
+        assert_eq!(recovery_id.serialize(), expected_id.serialize());
+        assert_eq!(signer.node_id(), &PublicKey::from_secret_key(&secret_key));
+
+        assert!(LocalSigner::new(Zeroizing::new([0u8; 32])).is_err());
+    }
+}
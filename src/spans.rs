@@ -0,0 +1,155 @@
+//! Span-annotated parsing of payment requests.
+//!
+//! Unlike `PaymentRequest::decode`, parsing here keeps track of where every field is located
+//! in the original string, for tools such as highlighters or invoice inspectors. Signatures
+//! are parsed but not verified.
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use amount::Amount;
+use bech32::Bech32;
+use codec::DecodeOptions;
+use consts::{CHECKSUM_LENGTH, SIGNATURE_LENGTH, TIMESTAMP_LENGTH};
+use currency::Currency;
+use tag::{Tag, TagReader};
+use timestamp::Timestamp;
+use types::Error;
+use utils::U5Conversions;
+use core::ops::Range;
+
+/// A value along with the range of characters it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned<T> {
+    /// Parsed value.
+    pub value: T,
+    /// Character range in the original string.
+    pub span: Range<usize>,
+}
+
+/// Payment request fields annotated with their location in the original string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTree {
+    /// Network prefix.
+    pub prefix: Spanned<String>,
+    /// Amount in millisatoshis, if any.
+    pub amount: Option<Spanned<u64>>,
+    /// Position of the `'1'` separating the human readable part from the data part.
+    pub separator: usize,
+    /// Request timestamp.
+    pub timestamp: Spanned<u64>,
+    /// Tagged fields, including their type and length characters.
+    pub tags: Vec<Spanned<Tag>>,
+    /// Request signature.
+    pub signature: Spanned<Vec<u8>>,
+    /// Bech32 checksum.
+    pub checksum: Range<usize>,
+}
+
+/// Parse a payment request keeping track of the location of each field.
+///
+/// # Params
+/// `input` The encoded payment request.
+pub fn parse(input: &str) -> Result<ParseTree, Error> {
+    DecodeOptions::default().check_length(input)?;
+    let Bech32 { hrp, data } = Bech32::from_string(input.to_owned())?;
+    let (currency, hr_amount) = Currency::split_hrp(&hrp)
+        .ok_or(Error::InvalidValue("unknown currency prefix".to_owned()))?;
+    let prefix_len = currency.prefix().len();
+    if data.len() < TIMESTAMP_LENGTH + SIGNATURE_LENGTH {
+        return Err(Error::InvalidLength(
+            "data is too short to decode".to_owned(),
+        ));
+    }
+    let separator = hrp.len();
+    // character position of a 5-bit word of the data part
+    let position = |index: usize| separator + 1 + index;
+
+    let amount = match hr_amount {
+        "" => None,
+        a => Some(Spanned {
+            value: Amount::decode(a)?,
+            span: prefix_len..separator,
+        }),
+    };
+
+    let timestamp = Spanned {
+        value: Timestamp::decode(&data)?,
+        span: position(0)..position(TIMESTAMP_LENGTH),
+    };
+
+    let signature_start = data.len() - SIGNATURE_LENGTH;
+    let mut tags = Vec::new();
+    let mut reader = TagReader::at(&data[TIMESTAMP_LENGTH..signature_start], TIMESTAMP_LENGTH);
+    loop {
+        let start = TIMESTAMP_LENGTH + reader.words_consumed();
+        match reader.next() {
+            Some(tag) => tags.push(Spanned {
+                value: tag?,
+                span: position(start)..position(TIMESTAMP_LENGTH + reader.words_consumed()),
+            }),
+            None => break,
+        }
+    }
+
+    let signature = Spanned {
+        value: data[signature_start..].to_u8_vec(false),
+        span: position(signature_start)..position(data.len()),
+    };
+
+    Ok(ParseTree {
+        prefix: Spanned {
+            value: currency.prefix().to_owned(),
+            span: 0..prefix_len,
+        },
+        amount,
+        separator,
+        timestamp,
+        tags,
+        signature,
+        checksum: position(data.len())..position(data.len() + CHECKSUM_LENGTH),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use codec::DEFAULT_MAX_INPUT_LENGTH;
+
+    #[test]
+    fn spans() {
+        let tx_ref = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w\
+        3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+
+        let tree = parse(tx_ref).unwrap();
+
+        assert_eq!(&tx_ref[tree.prefix.span.clone()], "lnbc");
+        let amount = tree.amount.unwrap();
+        assert_eq!(amount.value, 250_000_000);
+        assert_eq!(&tx_ref[amount.span], "2500u");
+        assert_eq!(tree.separator, 9);
+        assert_eq!(tree.timestamp.value, 1496314658);
+        assert_eq!(&tx_ref[tree.timestamp.span], "pvjluez");
+
+        assert_eq!(tree.tags.len(), 3);
+        let spans = tree.tags.iter().map(|t| &tx_ref[t.span.clone()]).collect::<Vec<_>>();
+        assert!(spans[0].starts_with("pp5"));
+        assert_eq!(spans[1], "dq5xysxxatsyp3k7enxv4js");
+        assert_eq!(spans[2], "xqzpu");
+        assert_eq!(tree.tags[2].value, Tag::Expiry { seconds: 60 });
+        assert_eq!(tree.tags[2].span.end, tree.signature.span.start);
+
+        assert_eq!(tree.signature.value.len(), 65);
+        assert_eq!(tree.signature.span.len(), SIGNATURE_LENGTH);
+        assert_eq!(&tx_ref[tree.checksum], "fj9srp");
+    }
+
+    #[test]
+    fn spans_errors() {
+        assert!(parse("lnbc1qqqqqqqqqqqqqqqq").is_err());
+        assert!(parse("lnbc2500x1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5").is_err());
+        let oversized = "lnbc1".to_owned() + &"q".repeat(DEFAULT_MAX_INPUT_LENGTH);
+        assert!(matches!(parse(&oversized), Err(Error::InvalidLength(_))));
+    }
+}
@@ -1,29 +1,75 @@
 //! PaymentRequest tagged fields.
 
+use consts::{EXTRA_HOP_LENGTH, HASH_FIELD_LENGTH, MAX_FIELD_LENGTH, PUBKEY_FIELD_LENGTH,
+             TAG_DESCRIPTION, TAG_DESCRIPTION_HASH, TAG_EXPIRY, TAG_FALLBACK_ADDRESS, TAG_FEATURES,
+             TAG_HEADER_LENGTH, TAG_METADATA, TAG_MIN_FINAL_CLTV_EXPIRY, TAG_PAYEE_NODE_ID,
+             TAG_PAYMENT_HASH, TAG_PAYMENT_SECRET, TAG_ROUTING_INFO};
+use currency::Currency;
+use features::Features;
+use msat::MilliSatoshi;
+use scid::ShortChannelId;
+use secp256k1::PublicKey;
 use types::Error;
-use utils::{U5, U5Conversions, U64VecU5Conversions, U8Conversions};
-use std::collections::HashMap;
-use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
-use itertools::Itertools;
-
-/// Bech32 alphabet.
-lazy_static! {
-    static ref BECH32_ALPHABET: HashMap<char, u8> =
-        hashmap!['q' => 0,'p' => 1,'z' => 2,'r' => 3,'y' => 4,'9' => 5,'x' => 6,'8' => 7,'g' => 8,
-        'f' => 9,'2' => 10,'t' => 11,'v' => 12,'d' => 13,'w' => 14,'0' => 15,'s' => 16,'3' => 17,
-        'j' => 18,'n' => 19,'5' => 20,'4' => 21,'k' => 22,'h' => 23, 'c' => 24, 'e' => 25,'6' => 26,
-        'm' => 27,'u' => 28,'a' => 29,'7' => 30,'l' => 31];
+use u5::U5;
+use utils::{to_hex, U5Conversions, U64VecU5Conversions, U8Conversions};
+use bech32::{char_to_u5, u5_to_char};
+#[cfg(feature = "std")]
+use base58check::ToBase58Check;
+#[cfg(feature = "std")]
+use bitcoin_bech32::WitnessProgram;
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::FusedIterator;
+use byteorder::{BigEndian, ByteOrder};
+#[cfg(feature = "bitcoin")]
+use bitcoin::hashes::Hash;
+#[cfg(feature = "bitcoin")]
+use bitcoin::util::address::{Address, Payload, WitnessVersion};
+#[cfg(feature = "bitcoin")]
+use bitcoin::{Network, PubkeyHash, ScriptHash};
+#[cfg(feature = "bitcoin")]
+use core::convert::TryFrom;
+
+/// 5-bit value of a tag character, failing unless it is a bech32 character.
+fn tag_value(tag: char) -> Result<U5, Error> {
+    char_to_u5(tag).ok_or_else(|| Error::InvalidValue(format!("invalid tag character '{}'", tag)))
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
-/// PaymentRequest tagged fields.
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// PaymentRequest tagged fields. New fields keep being added to BOLT11, so matches outside this
+/// crate need a wildcard arm; `TagVisitor` gives typed access without one.
+#[non_exhaustive]
 pub enum Tag {
     /// `'p'`  256-bit SHA256 payment_hash. Preimage of this provides proof of payment.
     PaymentHash {
         /// `hash` payment hash.
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        #[cfg_attr(feature = "serde", serde(with = "::hex_serde::bytes"))]
         hash: Vec<u8>,
     },
 
+    /// `'s'`  256-bit secret preventing forwarding nodes from probing the payment recipient.
+    PaymentSecret {
+        /// `secret` payment secret.
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        #[cfg_attr(feature = "serde", serde(with = "::hex_serde::bytes32"))]
+        secret: [u8; 32],
+    },
+
+    /// `'n'`  33-byte public key of the payee node. When present, the signature is checked
+    /// against it instead of recovering the public key from the signature.
+    PayeeNodeId {
+        /// `pubkey` payee node id.
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        #[cfg_attr(feature = "serde", serde(with = "::hex_serde::pub_key"))]
+        pubkey: PublicKey,
+    },
+
     /// `'d'`  Short description of purpose of payment (UTF-8), e.g. '1 cup of coffee' or
     /// 'ナンセンス 1杯'. <br>
     /// *Note:* must be included if DescriptionHash is not provided.
@@ -39,6 +85,8 @@ pub enum Tag {
     DescriptionHash {
         /// `hash` Hash that will be included in the payment request, and can be checked against
         ///  the hash of a long description, an invoice.
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        #[cfg_attr(feature = "serde", serde(with = "::hex_serde::bytes"))]
         hash: Vec<u8>,
     },
 
@@ -51,6 +99,8 @@ pub enum Tag {
         ///               - 0 (segwit hash: p2wpkh (20 bytes) or p2wsh (32 bytes))
         version: u8,
         /// `hash`    Address hash
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        #[cfg_attr(feature = "serde", serde(with = "::hex_serde::bytes"))]
         hash: Vec<u8>,
     },
 
@@ -74,6 +124,23 @@ pub enum Tag {
         path: Vec<ExtraHop>,
     },
 
+    /// `'m'` Additional metadata to attach to the payment. Its content is opaque to the payer;
+    /// see `tlv::TlvRecord` for a structured encoding.
+    Metadata {
+        /// `bytes` Metadata bytes.
+        #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+        #[cfg_attr(feature = "serde", serde(with = "::hex_serde::bytes"))]
+        bytes: Vec<u8>,
+    },
+
+    /// `'9'` One or more 5-bit values containing features supported or required for receiving
+    /// this payment.
+    Features {
+        /// `features` Feature bits.
+        #[cfg_attr(feature = "schemars", schemars(with = "Vec<usize>"))]
+        features: Features,
+    },
+
     /// Unknown tag.
     UnknownTag {
         /// `tag` Unknown tag.
@@ -83,178 +150,580 @@ pub enum Tag {
     },
 }
 
+/// Typed access to tagged fields, see `Tag::accept` and `PaymentRequest::for_each_tag`. Every
+/// method does nothing by default, so visitors only implement those of the fields they need
+/// and keep compiling when fields are added.
+pub trait TagVisitor {
+    /// Visit a `'p'` field.
+    fn visit_payment_hash(&mut self, _hash: &[u8]) {}
+    /// Visit a `'s'` field.
+    fn visit_payment_secret(&mut self, _secret: &[u8; 32]) {}
+    /// Visit a `'n'` field.
+    fn visit_payee_node_id(&mut self, _pubkey: &PublicKey) {}
+    /// Visit a `'d'` field.
+    fn visit_description(&mut self, _description: &str) {}
+    /// Visit a `'h'` field.
+    fn visit_description_hash(&mut self, _hash: &[u8]) {}
+    /// Visit a `'f'` field.
+    fn visit_fallback_address(&mut self, _version: u8, _hash: &[u8]) {}
+    /// Visit a `'x'` field.
+    fn visit_expiry(&mut self, _seconds: u64) {}
+    /// Visit a `'c'` field.
+    fn visit_min_final_cltv_expiry(&mut self, _blocks: u64) {}
+    /// Visit a `'r'` field.
+    fn visit_routing_info(&mut self, _path: &[ExtraHop]) {}
+    /// Visit a `'m'` field.
+    fn visit_metadata(&mut self, _bytes: &[u8]) {}
+    /// Visit a `'9'` field.
+    fn visit_features(&mut self, _features: &Features) {}
+    /// Visit a field this crate doesn't know, see `custom_tag::CustomTag` to decode it.
+    fn visit_unknown(&mut self, _tag: U5, _bytes: &[U5]) {}
+}
+
 impl Tag {
-    /// Convert to a u5 vector.
+    /// Call the method of `visitor` for this field.
+    pub fn accept<V: TagVisitor + ?Sized>(&self, visitor: &mut V) {
+        match *self {
+            Tag::PaymentHash { ref hash } => visitor.visit_payment_hash(hash),
+            Tag::PaymentSecret { ref secret } => visitor.visit_payment_secret(secret),
+            Tag::PayeeNodeId { ref pubkey } => visitor.visit_payee_node_id(pubkey),
+            Tag::Description { ref description } => visitor.visit_description(description),
+            Tag::DescriptionHash { ref hash } => visitor.visit_description_hash(hash),
+            Tag::FallbackAddress { version, ref hash } => {
+                visitor.visit_fallback_address(version, hash)
+            }
+            Tag::Expiry { seconds } => visitor.visit_expiry(seconds),
+            Tag::MinFinalCltvExpiry { blocks } => visitor.visit_min_final_cltv_expiry(blocks),
+            Tag::RoutingInfo { ref path } => visitor.visit_routing_info(path),
+            Tag::Metadata { ref bytes } => visitor.visit_metadata(bytes),
+            Tag::Features { ref features } => visitor.visit_features(features),
+            Tag::UnknownTag { tag, ref bytes } => visitor.visit_unknown(tag, bytes),
+        }
+    }
+
+    /// Return the character identifying this tag.
+    pub fn tag_char(&self) -> char {
+        match *self {
+            Tag::PaymentHash { .. } => TAG_PAYMENT_HASH,
+            Tag::PaymentSecret { .. } => TAG_PAYMENT_SECRET,
+            Tag::PayeeNodeId { .. } => TAG_PAYEE_NODE_ID,
+            Tag::Description { .. } => TAG_DESCRIPTION,
+            Tag::DescriptionHash { .. } => TAG_DESCRIPTION_HASH,
+            Tag::FallbackAddress { .. } => TAG_FALLBACK_ADDRESS,
+            Tag::Expiry { .. } => TAG_EXPIRY,
+            Tag::MinFinalCltvExpiry { .. } => TAG_MIN_FINAL_CLTV_EXPIRY,
+            Tag::RoutingInfo { .. } => TAG_ROUTING_INFO,
+            Tag::Metadata { .. } => TAG_METADATA,
+            Tag::Features { .. } => TAG_FEATURES,
+            Tag::UnknownTag { tag, .. } => u5_to_char(tag),
+        }
+    }
+
+    /// Render a `FallbackAddress` tag as an address of `currency`: base58check for versions 17
+    /// (pubkey hash) and 18 (script hash), bech32 for segwit versions. `None` for other tags or
+    /// if `currency` has no such addresses. Also `None` for base58check versions whose hash isn't
+    /// 20 bytes long.
+    #[cfg(feature = "std")]
+    pub fn fallback_address_string(&self, currency: Currency) -> Option<String> {
+        let (pubkey_version, script_version) = currency.base58_versions();
+        match *self {
+            Tag::FallbackAddress { version: 17, ref hash } if hash.len() == 20 => {
+                Some(hash.to_base58check(pubkey_version))
+            }
+            Tag::FallbackAddress { version: 18, ref hash } if hash.len() == 20 => {
+                Some(hash.to_base58check(script_version))
+            }
+            Tag::FallbackAddress { version: 17, .. } | Tag::FallbackAddress { version: 18, .. } => {
+                None
+            }
+            Tag::FallbackAddress { version, ref hash } => {
+                currency.segwit_network().and_then(|network| {
+                    let program = WitnessProgram {
+                        version,
+                        program: hash.to_owned(),
+                        network,
+                    };
+                    program.to_address().ok()
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Render a `FallbackAddress` tag as an address of `currency`. Always `None` without the
+    /// `std` feature, addresses can't be encoded then.
+    #[cfg(not(feature = "std"))]
+    pub fn fallback_address_string(&self, _currency: Currency) -> Option<String> {
+        None
+    }
+
+    /// Convert to a u5 vector. Fails if a `FallbackAddress` version doesn't fit in 5 bits.
     pub fn to_vec_u5(&self) -> Result<Vec<U5>, Error> {
         match &self {
             &&Tag::PaymentHash { ref hash } => {
-                let bytes = hash.to_u5_vec(true);
-                let p = BECH32_ALPHABET[&'p'];
-                Tag::vec_u5_aux(p, bytes)
+                let p = tag_value(TAG_PAYMENT_HASH)?;
+                Tag::vec_u5_aux(p, hash.to_u5_vec(true))
+            }
+            &&Tag::PaymentSecret { ref secret } => {
+                let s = tag_value(TAG_PAYMENT_SECRET)?;
+                Tag::vec_u5_aux(s, secret.to_u5_vec(true))
+            }
+            &&Tag::PayeeNodeId { ref pubkey } => {
+                let bytes = pubkey.serialize_compressed().to_u5_vec(true);
+                let n = tag_value(TAG_PAYEE_NODE_ID)?;
+                Tag::vec_u5_aux(n, bytes)
             }
             &&Tag::Description { ref description } => {
-                let bytes = description.as_bytes().to_vec().to_u5_vec(true);
-                let d = BECH32_ALPHABET[&'d'];
+                let bytes = description.as_bytes().to_u5_vec(true);
+                let d = tag_value(TAG_DESCRIPTION)?;
                 Tag::vec_u5_aux(d, bytes)
             }
             &&Tag::DescriptionHash { ref hash } => {
-                let bytes = hash.to_u5_vec(true);
-                let h = BECH32_ALPHABET[&'h'];
-                Tag::vec_u5_aux(h, bytes)
+                let h = tag_value(TAG_DESCRIPTION_HASH)?;
+                Tag::vec_u5_aux(h, hash.to_u5_vec(true))
             }
             &&Tag::FallbackAddress { version, ref hash } => {
-                let bytes = hash.to_u5_vec(true).map(|b| {
-                    let mut data = vec![version];
-                    data.extend(b);
-                    data
-                });
-                let f = BECH32_ALPHABET[&'f'];
+                let mut bytes = vec![U5::new(version)?];
+                bytes.extend(hash.to_u5_vec(true));
+                let f = tag_value(TAG_FALLBACK_ADDRESS)?;
                 Tag::vec_u5_aux(f, bytes)
             }
             &&Tag::Expiry { seconds } => {
-                let bytes = seconds.to_u5_vec();
-                let x = BECH32_ALPHABET[&'x'];
-                Tag::write_size(bytes.len()).map(|size| [vec![x], size, bytes].concat())
+                let x = tag_value(TAG_EXPIRY)?;
+                Tag::vec_u5_aux(x, seconds.to_u5_vec())
             }
             &&Tag::MinFinalCltvExpiry { blocks } => {
-                let bytes = blocks.to_u5_vec();
-                let c = BECH32_ALPHABET[&'c'];
-                Tag::write_size(bytes.len()).map(|size| [vec![c], size, bytes].concat())
+                let c = tag_value(TAG_MIN_FINAL_CLTV_EXPIRY)?;
+                Tag::vec_u5_aux(c, blocks.to_u5_vec())
             }
             &&Tag::RoutingInfo { ref path } => {
                 let bytes = path.iter()
                     .map(|hop| hop.pack())
-                    .fold_results(Vec::<u8>::new(), |mut acc, hop| {
-                        acc.extend(hop);
-                        acc
-                    })
-                    .and_then(|v| v.to_u5_vec(true));
+                    .collect::<Result<Vec<Vec<u8>>, Error>>()?
+                    .concat();
 
-                let r = BECH32_ALPHABET[&'r'];
-                Tag::vec_u5_aux(r, bytes)
+                let r = tag_value(TAG_ROUTING_INFO)?;
+                Tag::vec_u5_aux(r, bytes.to_u5_vec(true))
+            }
+            &&Tag::Metadata { ref bytes } => {
+                let m = tag_value(TAG_METADATA)?;
+                Tag::vec_u5_aux(m, bytes.to_u5_vec(true))
             }
-            &&Tag::UnknownTag { tag, ref bytes } => Tag::write_size(bytes.len())
-                .map(|size| [vec![tag], size, bytes.to_owned()].concat()),
+            &&Tag::Features { ref features } => {
+                let n = tag_value(TAG_FEATURES)?;
+                Tag::vec_u5_aux(n, features.to_raw_u5_vec())
+            }
+            &&Tag::UnknownTag { tag, ref bytes } => Tag::vec_u5_aux(tag, bytes.to_owned()),
         }
     }
     // Helper for to_vec_u5.
-    fn vec_u5_aux(value: u8, data: Result<Vec<u8>, Error>) -> Result<Vec<U5>, Error> {
-        match data {
-            Ok(bytes) => {
-                let len = bytes.len();
-                let mut vec = vec![value, (len / 32) as u8, (len % 32) as u8];
-                vec.extend(bytes);
-                Ok(vec)
-            }
-            Err(err) => Err(err),
-        }
+    fn vec_u5_aux(value: U5, data: Vec<U5>) -> Result<Vec<U5>, Error> {
+        Tag::write_size(value, data.len()).map(|size| [vec![value], size, data].concat())
     }
 
-    // Write the size into u5 vector
-    fn write_size(size: usize) -> Result<Vec<U5>, Error> {
-        let output = (size as u64).to_u5_vec();
-        match output.len() {
-            0 => Ok(vec![0u8, 0]),
-            1 => Ok([vec![0u8], output].concat()),
-            2 => Ok(output),
-            _ => Err(Error::InvalidLength(String::from(
-                "tag data length field must be encoded on 2 5-bits u8",
-            ))),
+    // Write the size into u5 vector, failing if it doesn't fit in the two words of the header.
+    fn write_size(tag: U5, size: usize) -> Result<Vec<U5>, Error> {
+        if size > MAX_FIELD_LENGTH {
+            return Err(Error::TagTooLong {
+                tag: u5_to_char(tag),
+                len: size,
+            });
         }
+        Ok(vec![
+            U5::from_low_bits((size / 32) as u8),
+            U5::from_low_bits((size % 32) as u8),
+        ])
+    }
+
+    /// Description tag, failing with `Error::TagTooLong` if `description` is longer than
+    /// `MAX_DESCRIPTION_LENGTH` bytes, in which case a description hash must be used.
+    pub fn description(description: String) -> Result<Tag, Error> {
+        Tag::Description { description }.checked()
+    }
+
+    /// Metadata tag, failing with `Error::TagTooLong` if `bytes` is longer than
+    /// `MAX_DESCRIPTION_LENGTH` bytes.
+    pub fn metadata(bytes: Vec<u8>) -> Result<Tag, Error> {
+        Tag::Metadata { bytes }.checked()
+    }
+
+    /// Routing info tag, failing with `Error::TagTooLong` if `path` has more than
+    /// `MAX_ROUTE_HOPS` hops; longer routes must be split over several tags.
+    pub fn routing_info(path: Vec<ExtraHop>) -> Result<Tag, Error> {
+        Tag::RoutingInfo { path }.checked()
+    }
+
+    /// Tag unknown to this crate, failing with `Error::TagTooLong` if `bytes` is longer than
+    /// `MAX_FIELD_LENGTH` words.
+    pub fn unknown(tag: U5, bytes: Vec<U5>) -> Result<Tag, Error> {
+        Tag::UnknownTag { tag, bytes }.checked()
+    }
+
+    // Return this tag if it can be encoded.
+    fn checked(self) -> Result<Tag, Error> {
+        self.to_vec_u5()?;
+        Ok(self)
+    }
+
+    // Read the data length declared in a tag header.
+    fn data_length(header: &[U5]) -> usize {
+        header[0].to_u8() as usize * 32 + header[1].to_u8() as usize
     }
 }
 
 impl Tag {
-    /// Parse a Tag from a u5 vector.
-    pub fn parse(input: &Vec<U5>) -> Result<Tag, Error> {
+    /// Parse a Tag from u5 words. Fails with `Error::InvalidLength` if `input` is shorter than
+    /// the tag header or than the declared data length. Words past the declared length are
+    /// ignored.
+    pub fn parse(input: &[U5]) -> Result<Tag, Error> {
         let tag = *input
             .get(0)
             .ok_or(Error::InvalidLength("invalid vector length".to_owned()))?;
         // declared data length
-        let len = input.as_slice().get(1..3)
-            .map(|v| (v[0] * 32 + v[1]) as usize)
-            // check if the vector has the declared lenght
-            .and_then(|len| if len <= input.len() + 3 {Some(len)} else {None})
+        let len = input
+            .get(1..TAG_HEADER_LENGTH)
+            .map(Tag::data_length)
+            .ok_or(Error::InvalidLength("missing tag data length".to_owned()))?;
+        // check if the vector has the declared length
+        let data = input
+            .get(TAG_HEADER_LENGTH..TAG_HEADER_LENGTH + len)
             .ok_or(Error::InvalidLength("invalid declared length".to_owned()))?;
 
-        match tag {
-            p if p == BECH32_ALPHABET[&'p'] => {
-                let hash_result = input[3..55].to_vec().to_u8_vec(false);
-                hash_result.map(|hash| Tag::PaymentHash { hash })
-            }
-            d if d == BECH32_ALPHABET[&'d'] => {
-                let description_result = input[3..len + 3].to_vec().to_u8_vec(false);
-                description_result
-                    .and_then(|v| String::from_utf8(v).map_err(Error::FromUTF8Err))
-                    .map(|description| Tag::Description { description })
-            }
-            h if h == BECH32_ALPHABET[&'h'] => {
-                let hash_result = input[3..len + 3].to_vec().to_u8_vec(false);
-                hash_result.map(|hash| Tag::DescriptionHash { hash })
+        match u5_to_char(tag) {
+            // BOLT #11: a reader MUST skip `p` fields that do not have a data_length of 52
+            TAG_PAYMENT_HASH => match len {
+                HASH_FIELD_LENGTH => {
+                    let hash = data.to_u8_vec(false);
+                    Ok(Tag::PaymentHash { hash })
+                }
+                _ => Ok(Tag::UnknownTag {
+                    tag,
+                    bytes: data.to_vec(),
+                }),
+            },
+            // BOLT #11: a reader MUST skip `s` fields that do not have a data_length of 52
+            TAG_PAYMENT_SECRET => match len {
+                HASH_FIELD_LENGTH => {
+                    let bytes = data.to_u8_vec(false);
+                    let mut secret = [0u8; 32];
+                    secret.copy_from_slice(&bytes[..32]);
+                    Ok(Tag::PaymentSecret { secret })
+                }
+                _ => Ok(Tag::UnknownTag {
+                    tag,
+                    bytes: data.to_vec(),
+                }),
+            },
+            // BOLT #11: a reader MUST skip `n` fields that do not have a data_length of 53
+            TAG_PAYEE_NODE_ID => match len {
+                PUBKEY_FIELD_LENGTH => {
+                    let bytes = data.to_u8_vec(false);
+                    let pubkey = ExtraHop::parse_pub_key(&bytes)?;
+                    Ok(Tag::PayeeNodeId { pubkey })
+                }
+                _ => Ok(Tag::UnknownTag {
+                    tag,
+                    bytes: data.to_vec(),
+                }),
+            },
+            TAG_DESCRIPTION => {
+                let description = String::from_utf8(data.to_u8_vec(false))?;
+                Ok(Tag::Description { description })
             }
-            f if f == BECH32_ALPHABET[&'f'] => {
-                let version = input[3];
-                let hash_result = input[4..len + 3].to_vec().to_u8_vec(false);
+            // BOLT #11: a reader MUST skip `h` fields that do not have a data_length of 52
+            TAG_DESCRIPTION_HASH => match len {
+                HASH_FIELD_LENGTH => {
+                    let hash = data.to_u8_vec(false);
+                    Ok(Tag::DescriptionHash { hash })
+                }
+                _ => Ok(Tag::UnknownTag {
+                    tag,
+                    bytes: data.to_vec(),
+                }),
+            },
+            TAG_FALLBACK_ADDRESS => {
+                let version = data
+                    .get(0)
+                    .ok_or(Error::InvalidLength("missing fallback address version".to_owned()))?
+                    .to_u8();
                 match version {
                     v if v <= 18u8 => {
-                        hash_result.map(|hash| Tag::FallbackAddress { version, hash })
+                        let hash = data[1..].to_u8_vec(false);
+                        Ok(Tag::FallbackAddress { version, hash })
                     }
                     _ => Ok(Tag::UnknownTag {
                         tag,
-                        bytes: input[3..len + 3].to_vec(),
+                        bytes: data.to_vec(),
                     }),
                 }
             }
-            r if r == BECH32_ALPHABET[&'r'] => {
-                let data_result = input[3..len + 3].to_vec().to_u8_vec(false);
-                data_result
-                    .map(ExtraHop::parse_all)
-                    .map(|path| Tag::RoutingInfo { path })
+            TAG_ROUTING_INFO => {
+                let path = ExtraHop::parse_all(data.to_u8_vec(false))?;
+                Ok(Tag::RoutingInfo { path })
             }
-            x if x == BECH32_ALPHABET[&'x'] => {
-                let seconds = input[3..len + 3].to_vec().u5_vec_to_u64(len);
+            TAG_EXPIRY => {
+                let seconds = Tag::parse_u64(data)?;
                 Ok(Tag::Expiry { seconds })
             }
-            c if c == BECH32_ALPHABET[&'c'] => {
-                let blocks = input[3..len + 3].to_vec().u5_vec_to_u64(len);
+            TAG_MIN_FINAL_CLTV_EXPIRY => {
+                let blocks = Tag::parse_u64(data)?;
                 Ok(Tag::MinFinalCltvExpiry { blocks })
             }
+            TAG_METADATA => {
+                let bytes = data.to_u8_vec(false);
+                Ok(Tag::Metadata { bytes })
+            }
+            TAG_FEATURES => {
+                let features = Features::from_u5(data);
+                Ok(Tag::Features { features })
+            }
             _ => Ok(Tag::UnknownTag {
                 tag,
-                bytes: input[3..len + 3].to_vec(),
+                bytes: data.to_vec(),
             }),
         }
     }
-    /// Parse multiple tags from a u5 vector.
-    pub fn parse_all(input: &Vec<U5>) -> Result<Vec<Tag>, Error> {
-        let mut raw_tags = Vec::<Vec<U5>>::new();
-        let mut data = &input[..];
-        // iterate over the input getting each tag
-        // the second and third byte declare the tag length
-        while data.len() > 3 {
-            // get the declared length of the tag
-            let len = (data[1] * 32 + data[2] + 3) as usize;
-            let tag: &[U5] = data.get(..len)
-                .ok_or(Error::InvalidLength("invalid tag length".to_owned()))?;
-            // store the tag
-            raw_tags.push(tag.to_vec());
-            // continue processing the vector
-            data = &data[len..]
+
+    // Read a big-endian integer, failing if it doesn't fit in 64 bits.
+    fn parse_u64(data: &[U5]) -> Result<u64, Error> {
+        data.iter().fold(Ok(0u64), |acc, word| {
+            acc.and_then(|acc| {
+                acc.checked_mul(32)
+                    .and_then(|acc| acc.checked_add(u64::from(word.to_u8())))
+                    .ok_or(Error::InvalidLength("integer field exceeds 64 bits".to_owned()))
+            })
+        })
+    }
+    /// Parse multiple tags from u5 words. Fails on the first tag that can't be parsed with
+    /// `Error::InvalidTag`, or with `Error::InvalidTagLength` if it runs past the end of the
+    /// input. Offsets are counted in 5-bit words from the start of `input`.
+    pub fn parse_all(input: &[U5]) -> Result<Vec<Tag>, Error> {
+        Tag::parse_all_at(input, 0)
+    }
+
+    /// Parse multiple tags from `input`, reporting offsets as if it started `offset` words into
+    /// the data part.
+    pub(crate) fn parse_all_at(input: &[U5], offset: usize) -> Result<Vec<Tag>, Error> {
+        TagReader::at(input, offset).collect()
+    }
+}
+
+/// Reads tags one at a time from u5 words, so a stream of payment requests can be processed
+/// tag by tag without collecting the tags of each request first.
+///
+/// Yields the same errors as `Tag::parse_all`, after which it stops.
+#[derive(Debug, Clone)]
+pub struct TagReader<'a> {
+    cursor: Cursor<'a>,
+    offset: usize,
+    index: usize,
+    failed: bool,
+}
+
+impl<'a> TagReader<'a> {
+    /// Read the tags of `input`.
+    pub fn new(input: &'a [U5]) -> TagReader<'a> {
+        TagReader::at(input, 0)
+    }
+
+    /// Read the tags of `input`, reporting offsets as if it started `offset` words into the
+    /// data part.
+    pub(crate) fn at(input: &'a [U5], offset: usize) -> TagReader<'a> {
+        TagReader {
+            cursor: Cursor::new(input),
+            offset,
+            index: 0,
+            failed: false,
+        }
+    }
+
+    /// Number of words consumed by the tags read so far, i.e. the position of the next tag.
+    pub fn words_consumed(&self) -> usize {
+        self.cursor.position()
+    }
+}
+
+impl<'a> Iterator for TagReader<'a> {
+    type Item = Result<Tag, Error>;
+
+    fn next(&mut self) -> Option<Result<Tag, Error>> {
+        if self.failed {
+            return None;
+        }
+        let field = self.cursor.next_field()?;
+        let offset = self.offset + self.cursor.position();
+        let index = self.index;
+        let parsed = field
+            .map_err(|tag| Error::InvalidTagLength { tag, offset })
+            .and_then(|(tag, raw_tag)| {
+                Tag::parse(raw_tag)
+                    .map(|parsed| (parsed, raw_tag.len()))
+                    .map_err(|error| Error::InvalidTag {
+                        index,
+                        tag,
+                        offset,
+                        error: Box::new(error),
+                    })
+            });
+        match parsed {
+            Ok((tag, len)) => {
+                self.cursor.advance(len);
+                self.index += 1;
+                Some(Ok(tag))
+            }
+            Err(error) => {
+                self.failed = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl<'a> FusedIterator for TagReader<'a> {}
+
+/// Position in a sequence of tagged fields, handing out each field as a sub-slice of the input
+/// so they can be parsed without copying.
+#[derive(Debug, Clone)]
+pub(crate) struct Cursor<'a> {
+    words: &'a [U5],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Start at the beginning of `words`.
+    pub(crate) fn new(words: &'a [U5]) -> Cursor<'a> {
+        Cursor { words, position: 0 }
+    }
+
+    /// Number of words consumed so far.
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Consume `len` words.
+    pub(crate) fn advance(&mut self, len: usize) {
+        self.position = (self.position + len).min(self.words.len());
+    }
+
+    /// Return the character and the words, header included, of the field at the current
+    /// position without consuming them, or only the character if the field runs past the end.
+    /// `None` once all words were consumed.
+    pub(crate) fn next_field(&self) -> Option<Result<(char, &'a [U5]), char>> {
+        let rest = &self.words[self.position..];
+        let tag = u5_to_char(*rest.first()?);
+        // the second and third words declare the data length
+        let field = rest.get(1..TAG_HEADER_LENGTH)
+            .map(|header| TAG_HEADER_LENGTH + Tag::data_length(header))
+            .and_then(|len| rest.get(..len));
+        Some(field.map(|field| (tag, field)).ok_or(tag))
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Tag::PaymentHash { ref hash } => write!(f, "payment hash: {}", to_hex(hash)),
+            Tag::PaymentSecret { ref secret } => write!(f, "payment secret: {}", to_hex(secret)),
+            Tag::PayeeNodeId { ref pubkey } => {
+                write!(f, "payee node id: {}", to_hex(&pubkey.serialize_compressed()))
+            }
+            Tag::Description { ref description } => write!(f, "description: {}", description),
+            Tag::DescriptionHash { ref hash } => {
+                write!(f, "description hash: {}", to_hex(hash))
+            }
+            Tag::FallbackAddress { version, ref hash } => write!(
+                f,
+                "fallback address: version {}, hash {}",
+                version,
+                to_hex(hash)
+            ),
+            Tag::RoutingInfo { ref path } => {
+                write!(f, "routing info:")?;
+                for (index, hop) in path.iter().enumerate() {
+                    write!(f, "{} {}", if index == 0 { "" } else { ";" }, hop)?;
+                }
+                Ok(())
+            }
+            Tag::Expiry { seconds } => write!(f, "expiry: {}s", seconds),
+            Tag::MinFinalCltvExpiry { blocks } => {
+                write!(f, "min final cltv expiry: {} blocks", blocks)
+            }
+            Tag::Metadata { ref bytes } => write!(f, "metadata: {}", to_hex(bytes)),
+            Tag::Features { ref features } => {
+                let bits = features.iter().map(|bit| bit.to_string()).collect::<Vec<_>>();
+                if bits.is_empty() {
+                    write!(f, "features: none")
+                } else {
+                    write!(f, "features: {}", bits.join(", "))
+                }
+            }
+            Tag::UnknownTag { ref bytes, .. } => {
+                write!(f, "unknown tag '{}': {} words", self.tag_char(), bytes.len())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "bitcoin")]
+impl Tag {
+    /// Return the address of a `FallbackAddress` tag on `network`, `None` for other tags or
+    /// unknown address versions.
+    pub fn to_address(&self, network: Network) -> Option<Address> {
+        let payload = match *self {
+            Tag::FallbackAddress {
+                version: 17,
+                ref hash,
+            } => PubkeyHash::from_slice(hash).ok().map(Payload::PubkeyHash),
+            Tag::FallbackAddress {
+                version: 18,
+                ref hash,
+            } => ScriptHash::from_slice(hash).ok().map(Payload::ScriptHash),
+            Tag::FallbackAddress { version, ref hash } => WitnessVersion::try_from(version)
+                .ok()
+                .map(|version| Payload::WitnessProgram {
+                    version,
+                    program: hash.to_owned(),
+                }),
+            _ => None,
+        };
+        payload.map(|payload| Address { payload, network })
+    }
+
+    /// Create a `FallbackAddress` tag from an address.
+    pub fn from_address(address: &Address) -> Result<Tag, Error> {
+        match address.payload {
+            Payload::PubkeyHash(ref hash) => Ok(Tag::FallbackAddress {
+                version: 17,
+                hash: hash[..].to_vec(),
+            }),
+            Payload::ScriptHash(ref hash) => Ok(Tag::FallbackAddress {
+                version: 18,
+                hash: hash[..].to_vec(),
+            }),
+            Payload::WitnessProgram {
+                version,
+                ref program,
+            } => Ok(Tag::FallbackAddress {
+                version: version.to_num(),
+                hash: program.to_owned(),
+            }),
         }
-        let tags = raw_tags.iter().flat_map(Tag::parse).collect_vec();
-        Ok(tags)
     }
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Entries containing extra routing information for a private route.
 pub struct ExtraHop {
-    /// Public key (264 bits).
-    pub pub_key: Vec<u8>,
+    /// Public key of the node forwarding along this channel.
+    #[cfg_attr(feature = "schemars", schemars(with = "String"))]
+    #[cfg_attr(feature = "serde", serde(with = "::hex_serde::pub_key"))]
+    pub pub_key: PublicKey,
     /// Channel ID of the channel.
-    pub short_channel_id: u64,
-    /// Base fee in millisatoshis required for routing along this channel.
-    pub fee_base_msat: u32,
+    pub short_channel_id: ShortChannelId,
+    /// Base fee required for routing along this channel, at most `u32::MAX` millisatoshis.
+    pub fee_base_msat: MilliSatoshi,
     /// Proportional fee in millionths of a satoshi required for routing along this channel.
     pub fee_proportional_millionths: u32,
     /// Is this channel's cltv expiry delta.
@@ -263,42 +732,103 @@ pub struct ExtraHop {
 
 impl ExtraHop {
     /// 33 + 8 + 4 + 4 + 2
-    const CHUNK_LENGTH: usize = 51;
+    pub const CHUNK_LENGTH: usize = EXTRA_HOP_LENGTH;
+
+    /// Create an ExtraHop from raw values.
+    /// # Params
+    /// `pub_key` Compressed public key (264 bits).
+    /// `short_channel_id` Channel ID of the channel.
+    /// `fee_base_msat` Base fee in millisatoshis.
+    /// `fee_proportional_millionths` Proportional fee in millionths of a satoshi.
+    /// `cltv_expiry_delta` Cltv expiry delta of the channel.
+    pub fn from_raw(
+        pub_key: &[u8],
+        short_channel_id: u64,
+        fee_base_msat: u32,
+        fee_proportional_millionths: u32,
+        cltv_expiry_delta: u16,
+    ) -> Result<ExtraHop, Error> {
+        Ok(ExtraHop {
+            pub_key: ExtraHop::parse_pub_key(pub_key)?,
+            short_channel_id: ShortChannelId::from(short_channel_id),
+            fee_base_msat: MilliSatoshi::from(u64::from(fee_base_msat)),
+            fee_proportional_millionths,
+            cltv_expiry_delta,
+        })
+    }
+
+    /// Return the compressed public key (264 bits).
+    pub fn pub_key_bytes(&self) -> Vec<u8> {
+        self.pub_key.serialize_compressed().to_vec()
+    }
 
     /// Pack into Vec<u8>.
     pub fn pack(&self) -> Result<Vec<u8>, Error> {
-        let mut wtr: Vec<u8> = vec![];
-        wtr.write_u64::<BigEndian>(self.short_channel_id)?;
-        wtr.write_u32::<BigEndian>(self.fee_base_msat)?;
-        wtr.write_u32::<BigEndian>(self.fee_proportional_millionths)?;
-        wtr.write_u16::<BigEndian>(self.cltv_expiry_delta)?;
-        Ok([self.pub_key.to_owned(), wtr].concat())
+        let fee_base_msat = self.fee_base_msat.as_u64();
+        if fee_base_msat > u64::from(u32::max_value()) {
+            return Err(Error::InvalidValue(format!(
+                "base fee {} doesn't fit in a route hint",
+                self.fee_base_msat
+            )));
+        }
+        let mut wtr: Vec<u8> = self.pub_key_bytes();
+        wtr.extend_from_slice(&self.short_channel_id.as_u64().to_be_bytes());
+        wtr.extend_from_slice(&(fee_base_msat as u32).to_be_bytes());
+        wtr.extend_from_slice(&self.fee_proportional_millionths.to_be_bytes());
+        wtr.extend_from_slice(&self.cltv_expiry_delta.to_be_bytes());
+        Ok(wtr)
     }
 
     /// Parse a u8 slice into an ExtraHop.
-    pub fn parse(data: &[u8]) -> ExtraHop {
-        let pub_key = data[0..33].to_owned();
-        let short_channel_id = BigEndian::read_u64(&data[33..41]);
-        let fee_base_msat = BigEndian::read_u32(&data[41..45]);
-        let fee_proportional_millionths = BigEndian::read_u32(&data[45..49]);
-        let cltv_expiry_delta = BigEndian::read_u16(&data[49..ExtraHop::CHUNK_LENGTH]);
-        ExtraHop {
-            pub_key,
-            short_channel_id,
-            fee_base_msat,
-            fee_proportional_millionths,
-            cltv_expiry_delta,
+    pub fn parse(data: &[u8]) -> Result<ExtraHop, Error> {
+        if data.len() < ExtraHop::CHUNK_LENGTH {
+            return Err(Error::InvalidLength("invalid extra hop length".to_owned()));
         }
+        ExtraHop::from_raw(
+            &data[0..33],
+            BigEndian::read_u64(&data[33..41]),
+            BigEndian::read_u32(&data[41..45]),
+            BigEndian::read_u32(&data[45..49]),
+            BigEndian::read_u16(&data[49..ExtraHop::CHUNK_LENGTH]),
+        )
     }
 
     /// Parse a vec<u8> into a vec<ExtraHop>.
-    pub fn parse_all(data: Vec<u8>) -> Vec<ExtraHop> {
+    pub fn parse_all(data: Vec<u8>) -> Result<Vec<ExtraHop>, Error> {
         data
             .chunks(ExtraHop::CHUNK_LENGTH)
             // the last chunk may be shorter if there's not enough elements
             .filter(|c| c.len() == ExtraHop::CHUNK_LENGTH)
             .map(ExtraHop::parse)
-            .collect_vec()
+            .collect()
+    }
+
+    /// Parse a compressed public key.
+    pub(crate) fn parse_pub_key(bytes: &[u8]) -> Result<PublicKey, Error> {
+        if bytes.len() != 33 {
+            return Err(Error::InvalidLength(
+                "public key must be 33 bytes long".to_owned(),
+            ));
+        }
+        let mut compressed = [0u8; 33];
+        compressed.copy_from_slice(bytes);
+        PublicKey::parse_compressed(&compressed).map_err(Error::SignatureError)
+    }
+}
+
+impl fmt::Display for ExtraHop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pub_key = to_hex(&self.pub_key_bytes());
+        write!(
+            f,
+            "hop via {}…{}, scid {}, fee {}msat + {}ppm, cltv Δ{}",
+            &pub_key[..4],
+            &pub_key[pub_key.len() - 4..],
+            self.short_channel_id,
+            self.fee_base_msat.as_u64(),
+            self.fee_proportional_millionths,
+            self.cltv_expiry_delta
+        )
     }
 }
 
@@ -307,13 +837,188 @@ mod test {
     use super::*;
     use utils::from_hex;
 
+    fn pub_key(hex: &str) -> PublicKey {
+        ExtraHop::parse_pub_key(&from_hex(hex).unwrap()).unwrap()
+    }
+
+    fn u5s(values: &[u8]) -> Vec<U5> {
+        U5::from_slice(values).unwrap()
+    }
+
+    #[test]
+    fn truncated_tags() {
+        let tags = vec![
+            Tag::PaymentHash { hash: vec![1u8; 32] },
+            Tag::PaymentSecret { secret: [2u8; 32] },
+            Tag::PayeeNodeId {
+                pubkey: pub_key(
+                    "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad",
+                ),
+            },
+            Tag::Description {
+                description: "1 cup coffee".to_owned(),
+            },
+            Tag::DescriptionHash { hash: vec![3u8; 32] },
+            Tag::FallbackAddress {
+                version: 17,
+                hash: vec![4u8; 20],
+            },
+            Tag::Expiry { seconds: 3600 },
+            Tag::MinFinalCltvExpiry { blocks: 9 },
+            Tag::Metadata {
+                bytes: vec![5u8; 3],
+            },
+            Tag::Features {
+                features: Features::from_bits(vec![8, 14]),
+            },
+        ];
+        for tag in tags {
+            let words = tag.to_vec_u5().unwrap();
+            assert_eq!(Tag::parse(&words).unwrap(), tag);
+            for end in 0..words.len() {
+                match Tag::parse(&words[..end]) {
+                    Err(Error::InvalidLength(_)) => (),
+                    other => panic!("{:?} truncated to {} words: {:?}", tag, end, other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_tags() {
+        // every tag, declaring every length up to two words more than its data
+        for tag in 0u8..32 {
+            for data_len in 0..4usize {
+                for declared in 0..data_len + 2 {
+                    let mut words = u5s(&[tag, (declared / 32) as u8, (declared % 32) as u8]);
+                    words.extend(vec![U5::MAX; data_len]);
+                    let _ = Tag::parse(&words);
+                }
+            }
+        }
+        // long length fields
+        assert!(Tag::parse(&u5s(&[6, 31, 31])).is_err());
+        assert!(Tag::parse(&[u5s(&[6, 0, 14]), vec![U5::MAX; 14]].concat()).is_err());
+        assert!(Tag::parse(&u5s(&[9, 0, 0])).is_err());
+        assert_eq!(
+            Tag::parse(&[u5s(&[6, 0, 13, 0]), vec![U5::MAX; 12]].concat()).unwrap(),
+            Tag::Expiry {
+                seconds: (1 << 60) - 1,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_all() {
+        let expiry = Tag::Expiry { seconds: 60 };
+        let description = Tag::Description {
+            description: String::new(),
+        };
+        let words = [expiry.to_vec_u5().unwrap(), description.to_vec_u5().unwrap()].concat();
+        assert_eq!(
+            Tag::parse_all(&words).unwrap(),
+            vec![expiry.clone(), description]
+        );
+        assert_eq!(Tag::parse_all(&vec![]).unwrap(), vec![]);
+
+        // invalid UTF-8 description
+        let invalid = [expiry.to_vec_u5().unwrap(), u5s(&[13, 0, 2, 31, 31])].concat();
+        match Tag::parse_all(&invalid) {
+            Err(Error::InvalidTag {
+                index: 1,
+                tag: 'd',
+                offset: 5,
+                error,
+            }) => match *error {
+                Error::FromUTF8Err(_) => (),
+                other => panic!("unexpected error {:?}", other),
+            },
+            other => panic!("unexpected result {:?}", other),
+        }
+        match Tag::parse_all_at(&invalid, 7) {
+            Err(Error::InvalidTag { offset: 12, .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        // truncated last tag
+        for end in words.len() - 2..words.len() {
+            match Tag::parse_all(&words[..end]) {
+                Err(Error::InvalidTagLength {
+                    tag: 'd',
+                    offset: 5,
+                }) => (),
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        match Tag::parse_all(&[expiry.to_vec_u5().unwrap(), u5s(&[3, 1, 0])].concat()) {
+            Err(Error::InvalidTagLength {
+                tag: 'r',
+                offset: 5,
+            }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_slices() {
+        let expiry = Tag::Expiry { seconds: 60 };
+        let words = expiry.to_vec_u5().unwrap();
+        let mut buffer = [U5::ZERO; 16];
+        buffer[..words.len()].copy_from_slice(&words);
+        // words past the declared length are ignored
+        assert_eq!(Tag::parse(&buffer).unwrap(), expiry);
+        assert_eq!(Tag::parse_all(&buffer[..words.len()]).unwrap(), vec![expiry]);
+
+        let mut cursor = Cursor::new(&buffer);
+        match cursor.next_field() {
+            Some(Ok(('x', field))) => assert_eq!(field, &words[..]),
+            _ => panic!("expected the expiry field"),
+        }
+        cursor.advance(words.len());
+        assert_eq!(cursor.position(), words.len());
+        // the zero words that follow are empty `q` fields
+        assert!(cursor.next_field().unwrap().is_ok());
+        cursor.advance(buffer.len());
+        assert!(cursor.next_field().is_none());
+    }
+
+    #[test]
+    fn tag_reader() {
+        let expiry = Tag::Expiry { seconds: 60 };
+        let description = Tag::Description {
+            description: "coffee".to_owned(),
+        };
+        let first = expiry.to_vec_u5().unwrap();
+        let words = [first.clone(), description.to_vec_u5().unwrap()].concat();
+
+        let mut reader = TagReader::new(&words);
+        assert_eq!(reader.words_consumed(), 0);
+        assert_eq!(reader.next().unwrap().unwrap(), expiry);
+        assert_eq!(reader.words_consumed(), first.len());
+        assert_eq!(reader.next().unwrap().unwrap(), description);
+        assert_eq!(reader.words_consumed(), words.len());
+        assert!(reader.next().is_none());
+
+        // a truncated tag ends the stream
+        let mut reader = TagReader::new(&words[..words.len() - 1]);
+        assert!(reader.next().unwrap().is_ok());
+        match reader.next() {
+            Some(Err(Error::InvalidTagLength {
+                tag: 'd',
+                offset: 5,
+            })) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(reader.next().is_none());
+        assert_eq!(reader.words_consumed(), first.len());
+    }
+
     #[test]
     fn payment_hash_tag() {
-        let u5_payment_hash_tag = vec![
-            1u8, 1, 20, 0, 0, 0, 16, 4, 0, 24, 4, 0, 20, 3, 0, 14, 2, 0, 9, 0, 0, 0, 16, 4, 0, 24,
+        let u5_payment_hash_tag = u5s(&[
+            1, 1, 20, 0, 0, 0, 16, 4, 0, 24, 4, 0, 20, 3, 0, 14, 2, 0, 9, 0, 0, 0, 16, 4, 0, 24,
             4, 0, 20, 3, 0, 14, 2, 0, 9, 0, 0, 0, 16, 4, 0, 24, 4, 0, 20, 3, 0, 14, 2, 0, 9, 0, 4,
             1, 0,
-        ];
+        ]);
         assert_eq!(
             Tag::parse(&u5_payment_hash_tag).unwrap(),
             Tag::PaymentHash {
@@ -324,13 +1029,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn payment_secret_tag() {
+        let tag = Tag::PaymentSecret { secret: [0x11; 32] };
+        let u5_payment_secret_tag = tag.to_vec_u5().unwrap();
+
+        assert_eq!(u5_payment_secret_tag[..3], u5s(&[16, 1, 20])[..]);
+        assert_eq!(Tag::parse(&u5_payment_secret_tag).unwrap(), tag);
+
+        let mut short_tag = u5_payment_secret_tag[..54].to_vec();
+        short_tag[2] = U5::new(19).unwrap();
+        assert_eq!(
+            Tag::parse(&short_tag).unwrap(),
+            Tag::UnknownTag {
+                tag: U5::new(16).unwrap(),
+                bytes: short_tag[3..].to_vec(),
+            }
+        );
+    }
+
+    #[test]
+    fn payee_node_id_tag() {
+        let tag = Tag::PayeeNodeId {
+            pubkey: pub_key("03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"),
+        };
+        let u5_payee_node_id_tag = tag.to_vec_u5().unwrap();
+
+        assert_eq!(u5_payee_node_id_tag[..3], u5s(&[19, 1, 21])[..]);
+        assert_eq!(Tag::parse(&u5_payee_node_id_tag).unwrap(), tag);
+    }
+
     #[test]
     fn description_tag() {
-        let u5_description_tag = vec![
-            13u8, 1, 31, 10, 1, 22, 6, 10, 24, 11, 19, 12, 20, 16, 6, 6, 27, 27, 14, 14, 13, 20,
+        let u5_description_tag = u5s(&[
+            13, 1, 31, 10, 1, 22, 6, 10, 24, 11, 19, 12, 20, 16, 6, 6, 27, 27, 14, 14, 13, 20,
             22, 8, 25, 11, 18, 4, 1, 25, 23, 10, 28, 3, 16, 13, 29, 25, 7, 8, 26, 11, 14, 12, 28,
             16, 7, 8, 26, 3, 9, 14, 12, 16, 7, 0, 28, 19, 15, 13, 9, 18, 22, 6, 29, 0,
-        ];
+        ]);
 
         assert_eq!(
             Tag::parse(&u5_description_tag).unwrap(),
@@ -342,11 +1077,11 @@ mod test {
 
     #[test]
     fn description_hash_tag() {
-        let u5_description_hash_tag = vec![
-            23u8, 1, 20, 7, 4, 18, 27, 13, 29, 19, 30, 5, 16, 26, 0, 0, 13, 23, 13, 2, 8, 4, 19,
+        let u5_description_hash_tag = u5s(&[
+            23, 1, 20, 7, 4, 18, 27, 13, 29, 19, 30, 5, 16, 26, 0, 0, 13, 23, 13, 2, 8, 4, 19,
             27, 21, 2, 14, 0, 13, 20, 13, 30, 6, 27, 14, 20, 9, 22, 5, 7, 22, 31, 4, 16, 4, 15, 21,
             17, 31, 10, 29, 23, 3, 0, 16,
-        ];
+        ]);
 
         assert_eq!(
             Tag::parse(&u5_description_hash_tag).unwrap(),
@@ -359,10 +1094,10 @@ mod test {
 
     #[test]
     fn fallback_address_tag() {
-        let u5_fallback_address_tag = vec![
-            9u8, 1, 1, 17, 6, 5, 25, 11, 10, 25, 10, 15, 12, 26, 1, 28, 17, 30, 24, 20, 13, 5, 12,
+        let u5_fallback_address_tag = u5s(&[
+            9, 1, 1, 17, 6, 5, 25, 11, 10, 25, 10, 15, 12, 26, 1, 28, 17, 30, 24, 20, 13, 5, 12,
             29, 6, 17, 30, 14, 6, 0, 30, 10, 28, 19, 5, 7,
-        ];
+        ]);
 
         assert_eq!(
             Tag::parse(&u5_fallback_address_tag).unwrap(),
@@ -371,11 +1106,17 @@ mod test {
                 hash: from_hex("3172b5654f6683c8fb146959d347ce303cae4ca7").unwrap(),
             }
         );
+
+        let invalid = Tag::FallbackAddress {
+            version: 32,
+            hash: vec![0; 20],
+        };
+        assert!(invalid.to_vec_u5().is_err());
     }
 
     #[test]
     fn expiry_tag() {
-        let u5_expiry_tag = vec![6u8, 0, 2, 1, 28];
+        let u5_expiry_tag = u5s(&[6, 0, 2, 1, 28]);
         assert_eq!(
             Tag::parse(&u5_expiry_tag).unwrap(),
             Tag::Expiry { seconds: 60 }
@@ -384,7 +1125,7 @@ mod test {
 
     #[test]
     fn min_final_cltv_expiry_tag() {
-        let u5_min_final_cltv_expiry_tag = vec![24u8, 0, 1, 12];
+        let u5_min_final_cltv_expiry_tag = u5s(&[24, 0, 1, 12]);
 
         assert_eq!(
             Tag::parse(&u5_min_final_cltv_expiry_tag).unwrap(),
@@ -392,37 +1133,59 @@ mod test {
         )
     }
 
+    #[test]
+    fn metadata_tag() {
+        let tag = Tag::Metadata {
+            bytes: from_hex("01fafaf0").unwrap(),
+        };
+        let u5_metadata_tag = tag.to_vec_u5().unwrap();
+
+        assert_eq!(u5_metadata_tag[..3], u5s(&[27, 0, 7])[..]);
+        assert_eq!(Tag::parse(&u5_metadata_tag).unwrap(), tag);
+    }
+
+    #[test]
+    fn features_tag() {
+        let u5_features_tag = u5s(&[5, 0, 4, 0, 16, 8, 0]);
+        let tag = Tag::Features {
+            features: Features::from_bits(vec![8, 14]),
+        };
+
+        assert_eq!(Tag::parse(&u5_features_tag).unwrap(), tag);
+        assert_eq!(tag.to_vec_u5().unwrap(), u5s(&[5, 0, 3, 16, 8, 0]));
+    }
+
     #[test]
     fn routing_info_tag() {
-        let u5_routing_info_tag = vec![
-            3u8, 5, 4, 0, 10, 15, 0, 7, 10, 8, 1, 23, 1, 10, 19, 9, 31, 24, 30, 18, 11, 2, 3, 24,
+        let u5_routing_info_tag = u5s(&[
+            3, 5, 4, 0, 10, 15, 0, 7, 10, 8, 1, 23, 1, 10, 19, 9, 31, 24, 30, 18, 11, 2, 3, 24,
             29, 2, 3, 3, 29, 30, 14, 14, 8, 2, 6, 0, 24, 7, 28, 30, 30, 20, 21, 24, 13, 31, 1, 9,
             3, 27, 24, 24, 29, 25, 5, 10, 0, 8, 2, 0, 12, 2, 0, 10, 1, 16, 7, 1, 0, 0, 0, 0, 0, 0,
             1, 0, 0, 0, 0, 0, 5, 0, 0, 0, 12, 1, 25, 28, 0, 29, 9, 0, 6, 28, 5, 10, 13, 7, 31, 3,
             26, 9, 12, 8, 15, 3, 20, 8, 12, 15, 23, 25, 25, 25, 0, 8, 24, 3, 0, 31, 19, 27, 26, 18,
             23, 1, 23, 28, 5, 4, 15, 15, 3, 3, 23, 4, 21, 8, 3, 0, 16, 2, 16, 12, 1, 24, 8, 1, 4,
             5, 0, 0, 0, 0, 0, 0, 8, 0, 0, 0, 0, 0, 30, 0, 0, 2, 0,
-        ];
+        ]);
 
         assert_eq!(
             Tag::parse(&u5_routing_info_tag).unwrap(),
             Tag::RoutingInfo {
                 path: vec![
                     ExtraHop {
-                        pub_key: from_hex(
+                        pub_key: pub_key(
                             "029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                        ).unwrap(),
-                        short_channel_id: 72623859790382856,
-                        fee_base_msat: 1,
+                        ),
+                        short_channel_id: ShortChannelId::from(72623859790382856),
+                        fee_base_msat: MilliSatoshi::from(1),
                         fee_proportional_millionths: 20,
                         cltv_expiry_delta: 3,
                     },
                     ExtraHop {
-                        pub_key: from_hex(
+                        pub_key: pub_key(
                             "039e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255",
-                        ).unwrap(),
-                        short_channel_id: 217304205466536202,
-                        fee_base_msat: 2,
+                        ),
+                        short_channel_id: ShortChannelId::from(217304205466536202),
+                        fee_base_msat: MilliSatoshi::from(2),
                         fee_proportional_millionths: 30,
                         cltv_expiry_delta: 4,
                     },
@@ -430,4 +1193,173 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn checked_constructors() {
+        use consts::{MAX_DESCRIPTION_LENGTH, MAX_ROUTE_HOPS};
+
+        let longest = "a".repeat(MAX_DESCRIPTION_LENGTH);
+        assert_eq!(
+            Tag::description(longest.clone()).unwrap(),
+            Tag::Description {
+                description: longest.clone(),
+            }
+        );
+        match Tag::description(longest + "a") {
+            Err(Error::TagTooLong { tag: 'd', len: 1024 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(Tag::metadata(vec![0u8; MAX_DESCRIPTION_LENGTH]).is_ok());
+        assert!(Tag::metadata(vec![0u8; MAX_DESCRIPTION_LENGTH + 1]).is_err());
+
+        let hop = ExtraHop {
+            pub_key: pub_key("029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"),
+            short_channel_id: ShortChannelId::from(1),
+            fee_base_msat: MilliSatoshi::from(1),
+            fee_proportional_millionths: 1,
+            cltv_expiry_delta: 1,
+        };
+        assert!(Tag::routing_info(vec![hop.clone(); MAX_ROUTE_HOPS]).is_ok());
+        assert!(Tag::routing_info(vec![hop; MAX_ROUTE_HOPS + 1]).is_err());
+
+        assert!(Tag::unknown(U5::ZERO, vec![U5::MAX; MAX_FIELD_LENGTH]).is_ok());
+        match Tag::unknown(U5::ZERO, vec![U5::MAX; MAX_FIELD_LENGTH + 1]) {
+            Err(Error::TagTooLong { tag: 'q', len }) => assert_eq!(len, MAX_FIELD_LENGTH + 1),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn extra_hop_raw_conversions() {
+        let pub_key =
+            from_hex("029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255").unwrap();
+        let hop = ExtraHop::from_raw(&pub_key, 72623859790382856, 1, 20, 3).unwrap();
+        assert_eq!(hop.pub_key_bytes(), pub_key);
+        assert_eq!(hop.short_channel_id.as_u64(), 72623859790382856);
+        assert_eq!(hop.fee_base_msat.as_u64(), 1);
+        assert_eq!(ExtraHop::parse(&hop.pack().unwrap()).unwrap(), hop);
+
+        assert!(ExtraHop::from_raw(&pub_key[1..], 1, 1, 1, 1).is_err());
+        assert!(ExtraHop::from_raw(&[4u8; 33], 1, 1, 1, 1).is_err());
+        assert!(ExtraHop::parse(&hop.pack().unwrap()[1..]).is_err());
+
+        let expensive = ExtraHop {
+            fee_base_msat: MilliSatoshi::from(1 << 32),
+            ..hop
+        };
+        assert!(expensive.pack().is_err());
+    }
+
+    #[test]
+    fn display() {
+        let hop = ExtraHop {
+            pub_key: pub_key("029e03a901b85534ff1e92c43c74431f7ce72046060fcf7a95c37e148f78c77255"),
+            short_channel_id: ShortChannelId::from(123 << 40 | 456 << 16 | 7),
+            fee_base_msat: MilliSatoshi::from(1),
+            fee_proportional_millionths: 20,
+            cltv_expiry_delta: 3,
+        };
+        assert_eq!(
+            hop.to_string(),
+            "hop via 029e…7255, scid 123x456x7, fee 1msat + 20ppm, cltv Δ3"
+        );
+        assert_eq!(
+            Tag::RoutingInfo {
+                path: vec![hop.clone(), hop],
+            }.to_string(),
+            "routing info: hop via 029e…7255, scid 123x456x7, fee 1msat + 20ppm, cltv Δ3; \
+             hop via 029e…7255, scid 123x456x7, fee 1msat + 20ppm, cltv Δ3"
+        );
+        assert_eq!(Tag::Expiry { seconds: 60 }.to_string(), "expiry: 60s");
+        assert_eq!(
+            Tag::MinFinalCltvExpiry { blocks: 9 }.to_string(),
+            "min final cltv expiry: 9 blocks"
+        );
+        assert_eq!(
+            Tag::Description {
+                description: "1 cup coffee".to_owned(),
+            }.to_string(),
+            "description: 1 cup coffee"
+        );
+        assert_eq!(
+            Tag::PaymentHash { hash: vec![0, 1, 255] }.to_string(),
+            "payment hash: 0001ff"
+        );
+        assert_eq!(
+            Tag::Features {
+                features: Features::from_bits(vec![8, 14]),
+            }.to_string(),
+            "features: 8, 14"
+        );
+        assert_eq!(
+            Tag::UnknownTag {
+                tag: U5::new(2).unwrap(),
+                bytes: u5s(&[1, 2]),
+            }.to_string(),
+            "unknown tag 'z': 2 words"
+        );
+    }
+
+    #[test]
+    fn fallback_address_string() {
+        let pubkey_hash = Tag::FallbackAddress {
+            version: 17,
+            hash: from_hex("04b61f7dc1ea0dc99424464cc4064dc564d91e89").unwrap(),
+        };
+        assert_eq!(
+            pubkey_hash.fallback_address_string(Currency::Bitcoin),
+            Some("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T".to_owned())
+        );
+        let witness_pubkey_hash = Tag::FallbackAddress {
+            version: 0,
+            hash: from_hex("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap(),
+        };
+        assert_eq!(
+            witness_pubkey_hash.fallback_address_string(Currency::Bitcoin),
+            Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_owned())
+        );
+        assert_eq!(
+            witness_pubkey_hash.fallback_address_string(Currency::BitcoinSimnet),
+            None
+        );
+        assert_eq!(
+            Tag::Expiry { seconds: 60 }.fallback_address_string(Currency::Bitcoin),
+            None
+        );
+        for version in vec![17, 18] {
+            let truncated = Tag::FallbackAddress {
+                version,
+                hash: from_hex("04b61f7dc1ea0dc99424464cc4064dc564d91e").unwrap(),
+            };
+            assert_eq!(truncated.fallback_address_string(Currency::Bitcoin), None);
+        }
+    }
+
+    #[cfg(feature = "bitcoin")]
+    #[test]
+    fn fallback_address_conversions() {
+        use std::str::FromStr;
+
+        let addresses = vec![
+            (Network::Bitcoin, "1RustyRX2oai4EYYDpQGWvEL62BBGqN9T", 17),
+            (Network::Bitcoin, "3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX", 18),
+            (Network::Bitcoin, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", 0),
+            (Network::Testnet, "mk2QpYatsKicvFVuTAQLBryyccRXMUaGHP", 17),
+        ];
+        for (network, encoded, version) in addresses {
+            let address = Address::from_str(encoded).unwrap();
+            let tag = Tag::from_address(&address).unwrap();
+            match tag {
+                Tag::FallbackAddress { version: v, .. } => assert_eq!(v, version),
+                ref other => panic!("unexpected tag {:?}", other),
+            }
+            assert_eq!(tag.to_address(network).unwrap().to_string(), encoded);
+        }
+        assert_eq!(Tag::Expiry { seconds: 60 }.to_address(Network::Bitcoin), None);
+        let unknown = Tag::FallbackAddress {
+            version: 19,
+            hash: vec![0; 20],
+        };
+        assert_eq!(unknown.to_address(Network::Bitcoin), None);
+    }
 }
@@ -0,0 +1,208 @@
+//! Tag collections checked against the BOLT11 rules.
+
+use consts::{TAG_DESCRIPTION, TAG_DESCRIPTION_HASH, TAG_EXPIRY, TAG_FEATURES, TAG_METADATA,
+             TAG_MIN_FINAL_CLTV_EXPIRY, TAG_PAYEE_NODE_ID, TAG_PAYMENT_HASH,
+             TAG_PAYMENT_SECRET};
+use tag::Tag;
+use types::Error;
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::iter::FromIterator;
+use core::slice;
+
+/// Tags that may appear at most once.
+const SINGLE_TAGS: &[char] = &[
+    TAG_PAYMENT_HASH,
+    TAG_PAYMENT_SECRET,
+    TAG_PAYEE_NODE_ID,
+    TAG_DESCRIPTION,
+    TAG_DESCRIPTION_HASH,
+    TAG_EXPIRY,
+    TAG_MIN_FINAL_CLTV_EXPIRY,
+    TAG_METADATA,
+    TAG_FEATURES,
+];
+
+/// The tags of a payment request, guaranteed to contain exactly one payment hash, exactly one
+/// of description and description hash, and no other duplicated field but fallback addresses,
+/// routing info and unknown tags. Tags are kept in their original order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagSet {
+    tags: Vec<Tag>,
+}
+
+impl TagSet {
+    /// Check `tags` and collect them into a set.
+    pub fn new(tags: Vec<Tag>) -> Result<TagSet, Error> {
+        TagSet::check(&tags)?;
+        Ok(TagSet { tags })
+    }
+
+    /// The tags, in their original order.
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    /// Iterate over the tags in their original order.
+    pub fn iter(&self) -> slice::Iter<'_, Tag> {
+        self.tags.iter()
+    }
+
+    /// Return the tags.
+    pub fn into_vec(self) -> Vec<Tag> {
+        self.tags
+    }
+
+    /// Return the payment hash, which is always present.
+    pub fn payment_hash(&self) -> &[u8] {
+        self.tags
+            .iter()
+            .filter_map(|t| match *t {
+                Tag::PaymentHash { ref hash } => Some(hash.as_slice()),
+                _ => None,
+            })
+            .next()
+            .expect("a tag set always has a payment hash")
+    }
+
+    fn check(tags: &[Tag]) -> Result<(), Error> {
+        // skipped fields, e.g. a `'p'` field of the wrong length, are unknown tags
+        let count = |c: char| {
+            tags.iter()
+                .filter(|t| match **t {
+                    Tag::UnknownTag { .. } => false,
+                    ref t => t.tag_char() == c,
+                })
+                .count()
+        };
+        if let Some(c) = SINGLE_TAGS.iter().find(|c| count(**c) > 1) {
+            return Err(Error::InvalidValue(format!("duplicate `{}` field", c)));
+        }
+        if count(TAG_PAYMENT_HASH) == 0 {
+            return Err(Error::InvalidValue("missing payment hash".to_owned()));
+        }
+        match count(TAG_DESCRIPTION) + count(TAG_DESCRIPTION_HASH) {
+            0 => Err(Error::InvalidValue(
+                "missing description or description hash".to_owned(),
+            )),
+            1 => Ok(()),
+            _ => Err(Error::InvalidValue(
+                "both description and description hash are present".to_owned(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<Vec<Tag>> for TagSet {
+    type Error = Error;
+
+    fn try_from(tags: Vec<Tag>) -> Result<TagSet, Error> {
+        TagSet::new(tags)
+    }
+}
+
+impl FromIterator<Tag> for Result<TagSet, Error> {
+    fn from_iter<I: IntoIterator<Item = Tag>>(iter: I) -> Result<TagSet, Error> {
+        TagSet::new(iter.into_iter().collect())
+    }
+}
+
+impl From<TagSet> for Vec<Tag> {
+    fn from(set: TagSet) -> Vec<Tag> {
+        set.tags
+    }
+}
+
+impl<'a> IntoIterator for &'a TagSet {
+    type Item = &'a Tag;
+    type IntoIter = slice::Iter<'a, Tag>;
+
+    fn into_iter(self) -> slice::Iter<'a, Tag> {
+        self.tags.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use u5::U5;
+
+    fn payment_hash() -> Tag {
+        Tag::PaymentHash {
+            hash: vec![1u8; 32],
+        }
+    }
+
+    fn description() -> Tag {
+        Tag::Description {
+            description: "coffee".to_owned(),
+        }
+    }
+
+    fn skipped_hash() -> Tag {
+        Tag::UnknownTag {
+            tag: U5::new(1).unwrap(),
+            bytes: vec![U5::ZERO; 10],
+        }
+    }
+
+    #[test]
+    fn valid() {
+        let tags = vec![
+            payment_hash(),
+            description(),
+            Tag::FallbackAddress {
+                version: 17,
+                hash: vec![0u8; 20],
+            },
+            Tag::FallbackAddress {
+                version: 18,
+                hash: vec![0u8; 20],
+            },
+            Tag::Expiry { seconds: 60 },
+        ];
+        let set = TagSet::try_from(tags.clone()).unwrap();
+        assert_eq!(set.tags(), tags.as_slice());
+        assert_eq!(set.payment_hash(), &[1u8; 32][..]);
+
+        let collected: Result<TagSet, Error> = tags.clone().into_iter().collect();
+        assert_eq!(collected.unwrap().into_vec(), tags);
+
+        let tags = vec![skipped_hash(), payment_hash(), description()];
+        let set = TagSet::new(tags.clone()).unwrap();
+        assert_eq!(set.tags(), tags.as_slice());
+        assert_eq!(set.payment_hash(), &[1u8; 32][..]);
+    }
+
+    #[test]
+    fn invalid() {
+        let hash = Tag::DescriptionHash {
+            hash: vec![0u8; 32],
+        };
+        let invalid = vec![
+            vec![description()],
+            vec![skipped_hash(), description()],
+            vec![payment_hash()],
+            vec![payment_hash(), payment_hash(), description()],
+            vec![payment_hash(), description(), hash.clone()],
+            vec![payment_hash(), hash.clone(), hash],
+            vec![
+                payment_hash(),
+                description(),
+                Tag::Expiry { seconds: 60 },
+                Tag::Expiry { seconds: 120 },
+            ],
+            vec![
+                payment_hash(),
+                description(),
+                Tag::PaymentSecret { secret: [0u8; 32] },
+                Tag::PaymentSecret { secret: [1u8; 32] },
+            ],
+        ];
+        for tags in invalid {
+            assert!(TagSet::try_from(tags.clone()).is_err());
+            assert!(tags.into_iter().collect::<Result<TagSet, Error>>().is_err());
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! BOLT11 test vectors.
+//!
+//! The valid and invalid payment requests of the
+//! [BOLT11 examples](https://github.com/lightning/bolts/blob/master/11-payment-encoding.md#examples),
+//! verbatim. All valid ones are signed with `PRIVATE_KEY`. Forks and bindings can run
+//! `validate_implementation` as part of their own tests.
+
+use codec::DecodeOptions;
+use payment_request::PaymentRequest;
+use secp256k1::{PublicKey, SecretKey};
+use types::Error;
+use utils::from_hex;
+
+/// Private key of the node signing all the test vectors.
+pub const PRIVATE_KEY: &str = "e126f68f7eafcc8b74f54d269fe206be715000f94dac067d1c04a8ca3b2db734";
+/// Payment hash of most test vectors.
+pub const PAYMENT_HASH: &str = "0001020304050607080900010203040506070809000102030405060708090102";
+/// Timestamp of most test vectors.
+pub const TIMESTAMP: u64 = 1_496_314_658;
+
+/// A valid payment request of the BOLT11 examples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestVector {
+    /// What the payment request is about.
+    pub description: &'static str,
+    /// The encoded payment request.
+    pub encoded: &'static str,
+    /// Amount in millisatoshis.
+    pub amount: Option<u64>,
+    /// Fallback on-chain address.
+    pub fallback_address: Option<&'static str>,
+    /// Hex encoded payment hash.
+    pub payment_hash: &'static str,
+    /// Creation time, in seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// The valid test vectors.
+pub const VALID: &[TestVector] = &[
+    TestVector {
+        description: "donation of any amount",
+        encoded: "lnbc1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5r\
+            qwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5kgetjypeh2ursdae8g6twvu\
+            s8g6rfwvs8qun0dfjkxaq9qrsgq357wnc5r2ueh7ck6q93dj32dlqnls087fxdwk8qakdyafkq3yap9us6v52v\
+            jjsrvywa6rt52cm9r9zqt8r2t7mlcwspyetp5h2tztugp9lfyql",
+        amount: None,
+        fallback_address: None,
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "cup of coffee",
+        encoded: "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyq\
+            cyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl7\
+            7nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven\
+            09sam30g4vgpfna3rh",
+        amount: Some(250_000_000),
+        fallback_address: None,
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "cup of nonsense",
+        encoded: "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyq\
+            cyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpquwpc4curk03c9wlrswe78q4eyqc7d8d0xqzpu\
+            9qrsgqhtjpauu9ur7fw2thcl4y9vfvh4m9wlfyz2gem29g5ghe2aak2pm3ps8fdhtceqsaagty2vph7utlgj48\
+            u0ged6a337aewvraedendscp573dxr",
+        amount: Some(250_000_000),
+        fallback_address: None,
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "list of things (hashed)",
+        encoded: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+            q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98kl\
+            ysy043l2ahrqs9qrsgq7ea976txfraylvgzuxs8kgcw23ezlrszfnh8r6qtfpr6cxga50aj6txm9rxrydzd06d\
+            feawfk6swupvz4erwnyutnjq7x39ymw6j38gp7ynn44",
+        amount: Some(2_000_000_000),
+        fallback_address: None,
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "testnet with a fallback address",
+        encoded: "lntb20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygshp58yjmdan7\
+            9s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqc\
+            yq5rqwzqfqypqfpp3x9et2e20v6pu37c5d9vax37wxq72un989qrsgqdj545axuxtnfemtpwkc45hx9d2ft7x0\
+            4mt8q7y6t0k2dge9e7h8kpy9p34ytyslj3yu569aalz2xdk8xkd7ltxqld94u8h2esmsmacgpghe9k8",
+        amount: Some(2_000_000_000),
+        fallback_address: Some("mk2QpYatsKicvFVuTAQLBryyccRXMUaGHP"),
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "fallback address and extra routing info",
+        encoded: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcy\
+            q5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98kl\
+            ysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc\
+            8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqafqxu92d8lr6fvg0r5gv0heeeqgcrqlnm6\
+            jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzq9qrsgqdfjcdk6w3ak5pca9hwfwfh63zrrz06wwfya0\
+            ydlzpgzxkn5xagsqz7x9j4jwe7yj7vaf2k9lqsdk45kts2fd0fkr28am0u4w95tt2nsq76cqw0",
+        amount: Some(2_000_000_000),
+        fallback_address: Some("1RustyRX2oai4EYYDpQGWvEL62BBGqN9T"),
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "P2SH fallback address",
+        encoded: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygshp58yjmdan7\
+            9s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqc\
+            yq5rqwzqfqypqfppj3a24vwu6r8ejrss3axul8rxldph2q7z99qrsgqz6qsgww34xlatfj6e3sngrwfy3ytkt2\
+            9d2qttr8qz2mnedfqysuqypgqex4haa2h8fx3wnypranf3pdwyluftwe680jjcfp438u82xqphf75ym",
+        amount: Some(2_000_000_000),
+        fallback_address: Some("3EktnHQD7RiAE6uzMj2ZifT9YgRrkSgzQX"),
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "P2WPKH fallback address",
+        encoded: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygshp58yjmdan7\
+            9s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqc\
+            yq5rqwzqfqypqfppqw508d6qejxtdg4y5r3zarvary0c5xw7k9qrsgqt29a0wturnys2hhxpner2e3plp6jyj8\
+            qx7548zr2z7ptgjjc7hljm98xhjym0dg52sdrvqamxdezkmqg4gdrvwwnf0kv2jdfnl4xatsqmrnsse",
+        amount: Some(2_000_000_000),
+        fallback_address: Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "P2WSH fallback address",
+        encoded: "lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygshp58yjmdan7\
+            9s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqc\
+            yq5rqwzqfqypqfp4qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q9qrsgq9vlvyj8cqvq\
+            6ggvpwd53jncp9nwc47xlrsnenq2zp70fq83qlgesn4u3uyf4tesfkkwwfg3qs54qe426hp3tz7z6sweqdjg05\
+            axsrjqp9yrrwc",
+        amount: Some(2_000_000_000),
+        fallback_address: Some("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3"),
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "features 8, 14 and 99",
+        encoded: "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9\
+            v5sxyetpdeessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q5sqqqqqqqqqqqqqqq\
+            qsgq2a25dxl5hrntdtn6zvydt7d66hyzsyhqs4wdynavys42xgl6sgx9c4g7me86a27t07mdtfry458rtjr0v9\
+            2cnmswpsjscgt2vcse3sgpz3uapa",
+        amount: Some(2_500_000_000),
+        fallback_address: None,
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "features 8, 14 and 99, upper case",
+        encoded: "LNBC25M1PVJLUEZPP5QQQSYQCYQ5RQWZQFQQQSYQCYQ5RQWZQFQQQSYQCYQ5RQWZQFQYPQDQ5VDHKVEN9\
+            V5SXYETPDEESSP5ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYG3ZYGS9Q5SQQQQQQQQQQQQQQQ\
+            QSGQ2A25DXL5HRNTDTN6ZVYDT7D66HYZSYHQS4WDYNAVYS42XGL6SGX9C4G7ME86A27T07MDTFRY458RTJR0V9\
+            2CNMSWPSJSCGT2VCSE3SGPZ3UAPA",
+        amount: Some(2_500_000_000),
+        fallback_address: None,
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+    TestVector {
+        description: "amount in pico-BTC",
+        encoded: "lnbc9678785340p1pwmna7lpp5gc3xfm08u9qy06djf8dfflhugl6p7lgza6dsjxq454gxhj9t7a0sd8d\
+            gfkx7cmtwd68yetpd5s9xar0wfjn5gpc8qhrsdfq24f5ggrxdaezqsnvda3kkum5wfjkzmfqf3jkgem9wgsyuc\
+            twdus9xgrcyqcjcgpzgfskx6eqf9hzqnteypzxz7fzypfhg6trddjhygrcyqezcgpzfysywmm5ypxxjemgw3hx\
+            jmn8yptk7untd9hxwg3q2d6xjcmtv4ezq7pqxgsxzmnyyqcjqmt0wfjjq6t5v4khxsp5zyg3zyg3zyg3zyg3zy\
+            g3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygsxqyjw5qcqp2rzjq0gxwkzc8w6323m55m4jyxcjwmy7stt9hwkwe2\
+            qxmy8zpsgg7jcuwz87fcqqeuqqqyqqqqlgqqqqn3qq9q9qrsgqrvgkpnmps664wgkp43l22qsgdw4ve24aca4n\
+            ymnxddlnp8vh9v2sdxlu5ywdxefsfvm0fq3sesf08uf6q9a2ke0hc9j6z6wlxg5z5kqpu2v9wz",
+        amount: Some(967_878_534),
+        fallback_address: None,
+        payment_hash: "462264ede7e14047e9b249da94fefc47f41f7d02ee9b091815a5506bc8abf75f",
+        timestamp: 1_572_468_703,
+    },
+    TestVector {
+        description: "payment metadata",
+        encoded: "lnbc10m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdp9wpshjmt9\
+            de6zqmt9w3skgct5vysxjmnnd9jx2mq8q8a04uqsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3\
+            zyg3zygs9q2gqqqqqqsgq7hf8he7ecf7n4ffphs6awl9t6676rrclv9ckg3d3ncn7fct63p6s365duk5wrk202\
+            cfy3aj5xnnp5gs3vrdvruverwwq7yzhkf5a3xqpd05wjc",
+        amount: Some(1_000_000_000),
+        fallback_address: None,
+        payment_hash: PAYMENT_HASH,
+        timestamp: TIMESTAMP,
+    },
+];
+
+/// Payment requests every implementation must reject, paired with the reason.
+pub const INVALID: &[(&str, &str)] = &[
+    (
+        "unknown required feature 100",
+        "lnbc25m1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5vdhkven9v5sxyetpd\
+        eessp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygs9q4psqqqqqqqqqqqqqqqqsgqtqyx5vgg\
+        fcsll4wu246hz02kp85x4katwsk9639we5n5yngc3yhqkm35jnjw4len8vrnqnf5ejh0mzj9n3vz2px97evektfm2l\
+        6wqccp3y7372",
+    ),
+    (
+        "invalid checksum",
+        "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpquwpc4curk03c9wl\
+        rswe78q4eyqc7d8d0xqzpuyk0sg5g70me25alkluzd2x62aysf2pyy8edtjeevuv4p2d5p76r4zkmneet7uvyakky2\
+        zr4cusd45tftc9c5fh0nnqpnl2jfll544esqchsrnt",
+    ),
+    (
+        "missing separator",
+        "pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpquwpc4curk03c9wlrswe78q4ey\
+        qc7d8d0xqzpuyk0sg5g70me25alkluzd2x62aysf2pyy8edtjeevuv4p2d5p76r4zkmneet7uvyakky2zr4cusd45t\
+        ftc9c5fh0nnqpnl2jfll544esqchsrny",
+    ),
+    (
+        "mixed case",
+        "LNBC2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpquwpc4curk03c9wl\
+        rswe78q4eyqc7d8d0xqzpuyk0sg5g70me25alkluzd2x62aysf2pyy8edtjeevuv4p2d5p76r4zkmneet7uvyakky2\
+        zr4cusd45tftc9c5fh0nnqpnl2jfll544esqchsrny",
+    ),
+    (
+        "unrecoverable signature",
+        "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7en\
+        xv4jsxqzpuaxtrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfhq77w3ls4evs3ch9zw97j2\
+        5emudupq63nyw24cg27h2rspk28uwq",
+    ),
+    (
+        "too short",
+        "lnbc1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdpl2pkx2ctnv5sxxmmwwd5k\
+        getjypeh2ursdae8g6na6hlh",
+    ),
+    (
+        "invalid multiplier",
+        "lnbc2500x1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7en\
+        xv4jsxqzpujr6jxr9gq9pv6g46y7d20jfkegkg4gljz2ea2a3m9lmvvr95tq2s0kvu70u3axgelz3kyvtp2ywwt0y8\
+        hkx2869zq5dll9nelr83zzqqpgl2zg",
+    ),
+    (
+        "sub-millisatoshi precision",
+        "lnbc2500000001p1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsy\
+        p3k7enxv4jsxqzpu7hqtk93pkf7sw55rdv4k9z2vj050rxdr6za9ekfs3nlt5lr89jqpdmxsmlj9urqumg0h9wzpqe\
+        cw7th56tdms40p2ny9q4ddvjsedzcplva53s",
+    ),
+];
+
+/// Check this implementation against the test vectors: valid payment requests must decode
+/// strictly to the expected fields and re-encode and re-sign to the same string, invalid ones
+/// must be rejected. Fails with `Error::InvalidValue` naming the first test vector that doesn't
+/// pass.
+pub fn validate_implementation() -> Result<(), Error> {
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&from_hex(PRIVATE_KEY)?);
+    let secret_key = SecretKey::parse(&key)?;
+    let node_id = PublicKey::from_secret_key(&secret_key);
+    let options = DecodeOptions::strict();
+
+    for vector in VALID {
+        let fail = |reason: String| {
+            Error::InvalidValue(format!("test vector `{}`: {}", vector.description, reason))
+        };
+        let decoded = PaymentRequest::decode_with_options(vector.encoded, &options)
+            .map_err(|e| fail(e.to_string()))?;
+        let encoded = decoded.encode().map_err(|e| fail(e.to_string()))?;
+        let signed = decoded
+            .sign(&secret_key)
+            .and_then(|signed| signed.encode())
+            .map_err(|e| fail(e.to_string()))?;
+        // encoders write lower case, the upper case test vector must decode all the same
+        let expected = vector.encoded.to_lowercase();
+        let mismatch = if decoded.node_id != node_id {
+            Some("node id")
+        } else if decoded.payment_hash() != Some(from_hex(vector.payment_hash)?) {
+            Some("payment hash")
+        } else if decoded.timestamp != vector.timestamp {
+            Some("timestamp")
+        } else if decoded.amount.map(u64::from) != vector.amount {
+            Some("amount")
+        } else if decoded.fallback_address() != vector.fallback_address.map(str::to_owned) {
+            Some("fallback address")
+        } else if encoded != expected {
+            Some("encoding")
+        } else if signed != expected {
+            Some("signature")
+        } else {
+            None
+        };
+        if let Some(field) = mismatch {
+            return Err(fail(format!("{} mismatch", field)));
+        }
+    }
+    for &(reason, encoded) in INVALID {
+        if PaymentRequest::decode_with_options(encoded, &options).is_ok() {
+            return Err(Error::InvalidValue(format!(
+                "accepted payment request with {}",
+                reason
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        validate_implementation().unwrap();
+    }
+}
@@ -1,24 +1,42 @@
-use utils::U5;
+//! Payment request timestamps.
+
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use consts::TIMESTAMP_LENGTH;
+use types::Error;
+use u5::U5;
 
 /// seconds-since-1970 (35 bits, big-endian)
 pub struct Timestamp;
 
 impl Timestamp {
-    /// decode timestamp from u5 vector
-    pub fn decode(data: &Vec<U5>) -> u64 {
-        data.iter().take(7).fold(0, |a, b| a * 32u64 + *b as u64)
+    /// Largest timestamp that fits in 35 bits.
+    pub const MAX: u64 = (1 << 35) - 1;
+
+    /// Decode a timestamp from the first 7 words of `data`, failing if it is shorter.
+    pub fn decode(data: &[U5]) -> Result<u64, Error> {
+        data.get(..TIMESTAMP_LENGTH)
+            .map(|words| words.iter().fold(0, |a, b| a * 32 + u64::from(b.to_u8())))
+            .ok_or_else(|| Error::InvalidLength("data is too short for a timestamp".to_owned()))
     }
-    /// encode timestamp
-    pub fn encode(timestamp: u64) -> Vec<U5> {
+
+    /// Encode a timestamp, failing if it is larger than `Timestamp::MAX`.
+    pub fn encode(timestamp: u64) -> Result<Vec<U5>, Error> {
+        if timestamp > Timestamp::MAX {
+            return Err(Error::InvalidValue(format!(
+                "timestamp {} exceeds 35 bits",
+                timestamp
+            )));
+        }
         let mut acc: Vec<U5> = Vec::new();
         let mut time_acc = timestamp;
         // 35 bits, big-endian
-        while acc.len() < 7 {
-            acc.push((time_acc % 32) as U5);
+        while acc.len() < TIMESTAMP_LENGTH {
+            acc.push(U5::from_low_bits((time_acc % 32) as u8));
             time_acc /= 32;
         }
         acc.reverse();
-        acc
+        Ok(acc)
     }
 }
 
@@ -28,10 +46,19 @@ mod test {
 
     #[test]
     fn timestamp() {
-        let data: Vec<U5> = vec![1, 12, 18, 31, 28, 25, 2];
+        let data = U5::from_slice(&[1, 12, 18, 31, 28, 25, 2]).unwrap();
         let timestamp = 1496314658;
 
-        assert_eq!(Timestamp::decode(&data), timestamp);
-        assert!(data.eq(&Timestamp::encode(timestamp)));
+        assert_eq!(Timestamp::decode(&data).unwrap(), timestamp);
+        assert!(data.eq(&Timestamp::encode(timestamp).unwrap()));
+    }
+
+    #[test]
+    fn bounds() {
+        let max = Timestamp::encode(Timestamp::MAX).unwrap();
+        assert_eq!(max, vec![U5::MAX; TIMESTAMP_LENGTH]);
+        assert_eq!(Timestamp::decode(&max).unwrap(), Timestamp::MAX);
+        assert!(Timestamp::encode(Timestamp::MAX + 1).is_err());
+        assert!(Timestamp::decode(&max[..TIMESTAMP_LENGTH - 1]).is_err());
     }
 }
@@ -0,0 +1,151 @@
+//! Type-length-value records for the payment metadata (`'m'`) field.
+//!
+//! Records use the TLV format of
+//! [BOLT #1](https://github.com/lightningnetwork/lightning-rfc/blob/master/01-messaging.md):
+//! `type` and `length` are `BigSize` integers and records are sorted by strictly increasing type.
+
+use alloc::borrow::ToOwned;
+use alloc::vec::Vec;
+use types::Error;
+use byteorder::{BigEndian, ByteOrder};
+
+/// A single typed metadata record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlvRecord {
+    /// Record type.
+    pub record_type: u64,
+    /// Record value.
+    pub value: Vec<u8>,
+}
+
+impl TlvRecord {
+    /// Create a new record.
+    /// # Params
+    /// `record_type` The record type.
+    /// `value` The record value.
+    pub fn new(record_type: u64, value: Vec<u8>) -> TlvRecord {
+        TlvRecord { record_type, value }
+    }
+
+    /// Encode a TLV stream. Records must be sorted by strictly increasing type.
+    pub fn encode_all(records: &[TlvRecord]) -> Result<Vec<u8>, Error> {
+        let mut wtr = Vec::new();
+        let mut last_type = None;
+        for record in records {
+            if last_type.map_or(false, |t| record.record_type <= t) {
+                return Err(Error::InvalidValue(
+                    "TLV records must be sorted by strictly increasing type".to_owned(),
+                ));
+            }
+            last_type = Some(record.record_type);
+            write_big_size(&mut wtr, record.record_type);
+            write_big_size(&mut wtr, record.value.len() as u64);
+            wtr.extend(&record.value);
+        }
+        Ok(wtr)
+    }
+
+    /// Decode a TLV stream.
+    pub fn decode_all(data: &[u8]) -> Result<Vec<TlvRecord>, Error> {
+        let mut records: Vec<TlvRecord> = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            let (record_type, after_type) = read_big_size(rest)?;
+            let (len, after_len) = read_big_size(after_type)?;
+            if records
+                .last()
+                .map_or(false, |r| record_type <= r.record_type)
+            {
+                return Err(Error::InvalidValue(
+                    "TLV records must be sorted by strictly increasing type".to_owned(),
+                ));
+            }
+            if (after_len.len() as u64) < len {
+                return Err(Error::InvalidLength(
+                    "TLV record is shorter than its declared length".to_owned(),
+                ));
+            }
+            let (value, next) = after_len.split_at(len as usize);
+            records.push(TlvRecord::new(record_type, value.to_vec()));
+            rest = next;
+        }
+        Ok(records)
+    }
+}
+
+/// Write a `BigSize` integer.
+fn write_big_size(wtr: &mut Vec<u8>, value: u64) {
+    match value {
+        v if v < 0xfd => wtr.push(v as u8),
+        v if v <= 0xffff => {
+            wtr.push(0xfd);
+            wtr.extend_from_slice(&(v as u16).to_be_bytes())
+        }
+        v if v <= 0xffff_ffff => {
+            wtr.push(0xfe);
+            wtr.extend_from_slice(&(v as u32).to_be_bytes())
+        }
+        v => {
+            wtr.push(0xff);
+            wtr.extend_from_slice(&v.to_be_bytes())
+        }
+    }
+}
+
+/// Read a minimally encoded `BigSize` integer, returning it along with the remaining input.
+fn read_big_size(data: &[u8]) -> Result<(u64, &[u8]), Error> {
+    let truncated = || Error::InvalidLength("truncated BigSize integer".to_owned());
+    let (value, size, min) = match data.first() {
+        None => return Err(truncated()),
+        Some(&0xfd) => data.get(1..3)
+            .map(|b| (BigEndian::read_u16(b) as u64, 3, 0xfd))
+            .ok_or_else(truncated)?,
+        Some(&0xfe) => data.get(1..5)
+            .map(|b| (BigEndian::read_u32(b) as u64, 5, 0x1_0000))
+            .ok_or_else(truncated)?,
+        Some(&0xff) => data.get(1..9)
+            .map(|b| (BigEndian::read_u64(b), 9, 0x1_0000_0000))
+            .ok_or_else(truncated)?,
+        Some(&b) => (b as u64, 1, 0),
+    };
+    if value < min {
+        return Err(Error::InvalidValue(
+            "BigSize integer is not minimally encoded".to_owned(),
+        ));
+    }
+    Ok((value, &data[size..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let records = vec![
+            TlvRecord::new(1, b"order-42".to_vec()),
+            TlvRecord::new(300, vec![]),
+            TlvRecord::new(70_000, vec![0xab; 300]),
+        ];
+        let encoded = TlvRecord::encode_all(&records).unwrap();
+
+        assert_eq!(&encoded[..10], &[1u8, 8, b'o', b'r', b'd', b'e', b'r', b'-', b'4', b'2']);
+        assert_eq!(&encoded[10..14], &[0xfdu8, 0x01, 0x2c, 0]);
+        assert_eq!(TlvRecord::decode_all(&encoded).unwrap(), records);
+        assert_eq!(TlvRecord::decode_all(&[]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn invalid_streams() {
+        let unordered = vec![TlvRecord::new(2, vec![]), TlvRecord::new(1, vec![])];
+        assert!(TlvRecord::encode_all(&unordered).is_err());
+        // duplicate type
+        assert!(TlvRecord::decode_all(&[1, 0, 1, 0]).is_err());
+        // value shorter than declared
+        assert!(TlvRecord::decode_all(&[1, 2, 0]).is_err());
+        // non-minimal BigSize
+        assert!(TlvRecord::decode_all(&[0xfd, 0, 1, 0]).is_err());
+        // truncated BigSize
+        assert!(TlvRecord::decode_all(&[0xfe, 0, 1]).is_err());
+    }
+}
@@ -1,10 +1,14 @@
 //! Error types
 
-use std::{error, fmt};
-use std::io;
-use std::num;
-use std::string;
+use alloc::boxed::Box;
+use alloc::string::{self, String};
+use alloc::vec::Vec;
+use core::{fmt, num};
+#[cfg(feature = "std")]
+use std::{error, io};
 use bech32;
+use consts::MAX_FIELD_LENGTH;
+use hex::HexError;
 use secp256k1;
 
 /// Result of vector base conversion
@@ -24,6 +28,7 @@ pub enum Error {
     /// Invalid input length.
     InvalidLength(String),
     /// Wraps an io error produced when reading or writing.
+    #[cfg(feature = "std")]
     IOErr(io::Error),
     /// Wraps parse float error.
     ParseFloatErr(num::ParseFloatError),
@@ -31,10 +36,51 @@ pub enum Error {
     ParseIntErr(num::ParseIntError),
     /// Wraps string from utf8 error.
     FromUTF8Err(string::FromUtf8Error),
+    /// Wraps hex decoding error.
+    HexErr(HexError),
     /// Wraps bech32 error.
     Bech32Err(bech32::Error),
     /// Wraps secp256k1 error.
     SignatureError(secp256k1::Error),
+    /// A tagged field couldn't be parsed.
+    InvalidTag {
+        /// Position of the tag among the tagged fields.
+        index: usize,
+        /// Tag character.
+        tag: char,
+        /// Offset of the tag in 5-bit words into the data part.
+        offset: usize,
+        /// Why the tag couldn't be parsed.
+        error: Box<Error>,
+    },
+    /// An amount isn't a whole number of millisatoshis, e.g. a `p` amount not ending with 0.
+    SubMillisatoshiAmount(String),
+    /// A payment request has both a description and a description hash, or neither.
+    ConflictingDescription,
+    /// A tagged field declares more data than is left in the data part.
+    InvalidTagLength {
+        /// Tag character.
+        tag: char,
+        /// Offset of the tag in 5-bit words into the data part.
+        offset: usize,
+    },
+    /// A signature's `s` value is more than half the curve order, see `signature::is_low_s`.
+    HighS,
+    /// A compact signature isn't 65 bytes long.
+    InvalidSignatureLength(usize),
+    /// A signature's recovery id isn't between 0 and 3.
+    InvalidRecoveryId(u8),
+    /// A payment request requires a feature through an even bit this crate doesn't know.
+    UnknownRequiredFeature(usize),
+    /// A payment request advertises the `payment_secret` feature without an `'s'` field.
+    MissingPaymentSecret,
+    /// A tagged field holds more than `MAX_FIELD_LENGTH` 5-bit words of data.
+    TagTooLong {
+        /// Tag character.
+        tag: char,
+        /// Length of the data in 5-bit words.
+        len: usize,
+    },
 }
 
 impl fmt::Display for Error {
@@ -45,16 +91,62 @@ impl fmt::Display for Error {
             Error::InvalidValue(ref e) => write!(f, "{}", e),
             Error::InvalidPadding => write!(f, "invalid padding"),
             Error::InvalidLength(ref e) => write!(f, "{}", e),
+            #[cfg(feature = "std")]
             Error::IOErr(ref e) => write!(f, "{}", e),
             Error::ParseFloatErr(ref e) => write!(f, "{}", e),
             Error::ParseIntErr(ref e) => write!(f, "{}", e),
             Error::FromUTF8Err(ref e) => write!(f, "{}", e),
+            Error::HexErr(ref e) => write!(f, "{}", e),
             Error::Bech32Err(ref e) => write!(f, "{}", e),
             Error::SignatureError(ref e) => write!(f, "{:?}", e),
+            Error::InvalidTag {
+                index,
+                tag,
+                offset,
+                ref error,
+            } => write!(
+                f,
+                "invalid tag #{} '{}' at offset {}: {}",
+                index, tag, offset, error
+            ),
+            Error::SubMillisatoshiAmount(ref amount) => write!(
+                f,
+                "amount `{}` is not a whole number of millisatoshis",
+                amount
+            ),
+            Error::ConflictingDescription => write!(
+                f,
+                "exactly one of description and description hash is required"
+            ),
+            Error::InvalidTagLength { tag, offset } => write!(
+                f,
+                "tag '{}' at offset {} exceeds the data part",
+                tag, offset
+            ),
+            Error::HighS => write!(f, "signature has a high S value"),
+            Error::InvalidSignatureLength(length) => write!(
+                f,
+                "signature is {} bytes long instead of 65",
+                length
+            ),
+            Error::InvalidRecoveryId(id) => write!(f, "invalid recovery id {}", id),
+            Error::UnknownRequiredFeature(bit) => {
+                write!(f, "unknown required feature bit {}", bit)
+            }
+            Error::MissingPaymentSecret => write!(
+                f,
+                "payment_secret feature is set without a payment secret"
+            ),
+            Error::TagTooLong { tag, len } => write!(
+                f,
+                "tag '{}' holds {} 5-bit words, more than the {} a tagged field can hold",
+                tag, len, MAX_FIELD_LENGTH
+            ),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
@@ -67,6 +159,7 @@ impl error::Error for Error {
             Error::ParseFloatErr(ref e) => error::Error::description(e),
             Error::ParseIntErr(ref e) => error::Error::description(e),
             Error::FromUTF8Err(ref e) => error::Error::description(e),
+            Error::HexErr(_) => "invalid hex",
             Error::Bech32Err(ref e) => error::Error::description(e),
             Error::SignatureError(ref e) => match *e {
                 secp256k1::Error::InvalidSignature => "invalid signature",
@@ -75,19 +168,33 @@ impl error::Error for Error {
                 secp256k1::Error::InvalidRecoveryId => "invalid recovery id",
                 secp256k1::Error::InvalidMessage => "invalid message",
             },
+            Error::InvalidTag { .. } => "invalid tag",
+            Error::InvalidTagLength { .. } => "invalid tag length",
+            Error::ConflictingDescription => "conflicting description",
+            Error::SubMillisatoshiAmount(_) => "sub-millisatoshi amount",
+            Error::HighS => "high S signature",
+            Error::InvalidSignatureLength(_) => "invalid signature length",
+            Error::InvalidRecoveryId(_) => "invalid recovery id",
+            Error::UnknownRequiredFeature(_) => "unknown required feature",
+            Error::MissingPaymentSecret => "missing payment secret",
+            Error::TagTooLong { .. } => "tag too long",
         }
     }
-    fn cause(&self) -> Option<&error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Error::IOErr(ref e) => Some(e),
             Error::ParseFloatErr(ref e) => Some(e),
             Error::ParseIntErr(ref e) => Some(e),
             Error::FromUTF8Err(ref e) => Some(e),
+            Error::HexErr(ref e) => Some(e),
+            Error::Bech32Err(ref e) => Some(e),
+            Error::InvalidTag { ref error, .. } => Some(&**error),
             _ => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Error {
         Error::IOErr(e)
@@ -112,6 +219,12 @@ impl From<string::FromUtf8Error> for Error {
     }
 }
 
+impl From<HexError> for Error {
+    fn from(e: HexError) -> Error {
+        Error::HexErr(e)
+    }
+}
+
 impl From<bech32::Error> for Error {
     fn from(e: bech32::Error) -> Error {
         Error::Bech32Err(e)
@@ -123,3 +236,35 @@ impl From<secp256k1::Error> for Error {
         Error::SignatureError(e)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn source() {
+        let utf8 = String::from_utf8(vec![0xff]).unwrap_err();
+        let error = Error::InvalidTag {
+            index: 2,
+            tag: 'd',
+            offset: 31,
+            error: Box::new(Error::from(utf8)),
+        };
+        let source = error.source().unwrap();
+        assert!(source.to_string().starts_with("invalid utf-8"));
+        assert!(source.source().unwrap().is::<string::FromUtf8Error>());
+        assert!(Error::InvalidPadding.source().is_none());
+    }
+
+    #[test]
+    fn boxed() {
+        fn parse(input: &str) -> Result<u64, Box<dyn StdError + Send + Sync>> {
+            Ok(input.parse::<u64>().map_err(Error::from)?)
+        }
+        let error = parse("x").unwrap_err();
+        assert!(error.to_string().starts_with("invalid digit"));
+        assert!(error.downcast_ref::<Error>().is_some());
+        assert_eq!(parse("42").unwrap(), 42);
+    }
+}
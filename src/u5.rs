@@ -0,0 +1,123 @@
+//! 5-bit words of the data part of a payment request.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use types::Error;
+
+/// A 5-bit word, i.e. a value below 32. Words can only be created from valid values, so
+/// vectors of words never need to be checked again before they are encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u8", into = "u8"))]
+pub struct U5(u8);
+
+impl U5 {
+    /// The zero word.
+    pub const ZERO: U5 = U5(0);
+    /// The largest word, 31.
+    pub const MAX: U5 = U5(31);
+
+    /// Create a word, failing with `Error::InvalidInputValue` if `value` is 32 or more.
+    pub fn new(value: u8) -> Result<U5, Error> {
+        if value > U5::MAX.0 {
+            return Err(Error::InvalidInputValue(value));
+        }
+        Ok(U5(value))
+    }
+
+    /// Create a word from the 5 low bits of `value`.
+    pub(crate) const fn from_low_bits(value: u8) -> U5 {
+        U5(value & U5::MAX.0)
+    }
+
+    /// Create words from `values`, failing on the first one that is 32 or more.
+    pub fn from_slice(values: &[u8]) -> Result<Vec<U5>, Error> {
+        values.iter().cloned().checked_u5().collect()
+    }
+
+    /// Return the value of this word.
+    pub const fn to_u8(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for U5 {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<U5, Error> {
+        U5::new(value)
+    }
+}
+
+impl From<U5> for u8 {
+    fn from(word: U5) -> u8 {
+        word.0
+    }
+}
+
+impl fmt::Display for U5 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Iterator adapters between raw values and words.
+pub trait U5Iterator: Iterator<Item = u8> + Sized {
+    /// Turn each value into a word, yielding `Error::InvalidInputValue` for values that are 32
+    /// or more.
+    fn checked_u5(self) -> CheckedU5<Self> {
+        CheckedU5 { iter: self }
+    }
+}
+
+impl<I: Iterator<Item = u8>> U5Iterator for I {}
+
+/// Iterator returned by `U5Iterator::checked_u5`.
+#[derive(Debug, Clone)]
+pub struct CheckedU5<I> {
+    iter: I,
+}
+
+impl<I: Iterator<Item = u8>> Iterator for CheckedU5<I> {
+    type Item = Result<U5, Error>;
+
+    fn next(&mut self) -> Option<Result<U5, Error>> {
+        self.iter.next().map(U5::new)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bounds() {
+        assert_eq!(U5::new(0).unwrap(), U5::ZERO);
+        assert_eq!(U5::new(31).unwrap(), U5::MAX);
+        assert_eq!(u8::from(U5::MAX), 31);
+        match U5::new(32) {
+            Err(Error::InvalidInputValue(32)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(U5::try_from(255).is_err());
+        assert_eq!(U5::from_low_bits(33), U5::new(1).unwrap());
+    }
+
+    #[test]
+    fn adapters() {
+        let words = U5::from_slice(&[1, 2, 31]).unwrap();
+        assert_eq!(words.iter().map(|w| w.to_u8()).collect::<Vec<_>>(), vec![1, 2, 31]);
+        assert!(U5::from_slice(&[1, 32, 2]).is_err());
+
+        let mut checked = vec![3u8, 40].into_iter().checked_u5();
+        assert_eq!(checked.next().unwrap().unwrap(), U5::new(3).unwrap());
+        assert!(checked.next().unwrap().is_err());
+        assert!(checked.next().is_none());
+    }
+}
@@ -0,0 +1,268 @@
+//! `lightning:` URIs and BIP-21 `bitcoin:` URIs carrying a payment request in their
+//! `lightning` parameter, as shown in unified QR codes.
+
+use msat::{MilliSatoshi, Rounding};
+use payment_request::PaymentRequest;
+use scan::{normalize_scanned, strip_scheme, BITCOIN_SCHEME, LIGHTNING_PARAM, LIGHTNING_SCHEME};
+use core::str::FromStr;
+use types::Error;
+
+/// A payment URI: a lightning payment request, an on-chain address, or both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentUri {
+    /// On-chain address, `None` for `lightning:` URIs.
+    pub address: Option<String>,
+    /// Amount to pay, from the `amount` parameter or the payment request.
+    pub amount: Option<MilliSatoshi>,
+    /// Label of the recipient (`label` parameter).
+    pub label: Option<String>,
+    /// Message describing the payment (`message` parameter).
+    pub message: Option<String>,
+    /// Lightning payment request.
+    pub payment_request: Option<PaymentRequest>,
+}
+
+impl PaymentUri {
+    /// URI of a lightning payment request alone.
+    pub fn lightning(payment_request: PaymentRequest) -> PaymentUri {
+        PaymentUri {
+            address: None,
+            amount: payment_request.amount_msat(),
+            label: None,
+            message: None,
+            payment_request: Some(payment_request),
+        }
+    }
+
+    /// BIP-21 URI offering to pay `payment_request` or, for wallets without lightning
+    /// support, to pay its amount to `address`.
+    pub fn unified(address: String, payment_request: PaymentRequest) -> PaymentUri {
+        PaymentUri {
+            address: Some(address),
+            ..PaymentUri::lightning(payment_request)
+        }
+    }
+
+    /// Parse a `lightning:` or `bitcoin:` URI, decoding the payment request it carries. Fails
+    /// if the amount of a BIP-21 URI doesn't match the one of its payment request, or on
+    /// unknown required (`req-`) parameters.
+    /// # Params
+    /// `uri` The URI, as scanned.
+    pub fn parse(uri: &str) -> Result<PaymentUri, Error> {
+        let uri = uri.trim();
+        if strip_scheme(uri, LIGHTNING_SCHEME).is_some() {
+            let payment_request = PaymentRequest::decode(&normalize_scanned(uri)?)?;
+            return Ok(PaymentUri::lightning(payment_request));
+        }
+        let rest = strip_scheme(uri, BITCOIN_SCHEME)
+            .ok_or_else(|| Error::InvalidValue("not a lightning or bitcoin URI".to_owned()))?;
+        let mut parts = rest.splitn(2, '?');
+        let address = parts.next().unwrap_or_default();
+        let mut payment_uri = PaymentUri {
+            address: if address.is_empty() {
+                None
+            } else {
+                Some(address.to_owned())
+            },
+            amount: None,
+            label: None,
+            message: None,
+            payment_request: None,
+        };
+        for param in parts.next().unwrap_or_default().split('&') {
+            let mut kv = param.splitn(2, '=');
+            let (key, value) = (kv.next().unwrap_or_default(), kv.next().unwrap_or_default());
+            match key.to_lowercase().as_str() {
+                "" => {}
+                "amount" => payment_uri.amount = Some(MilliSatoshi::from_btc_str(value)?),
+                "label" => payment_uri.label = Some(percent_decode(value)?),
+                "message" => payment_uri.message = Some(percent_decode(value)?),
+                LIGHTNING_PARAM => {
+                    let payment_request = normalize_scanned(&percent_decode(value)?)?;
+                    payment_uri.payment_request = Some(PaymentRequest::decode(&payment_request)?)
+                }
+                key if key.starts_with("req-") => {
+                    return Err(Error::InvalidValue(format!(
+                        "unsupported required parameter `{}`",
+                        key
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        let invoice_amount = payment_uri
+            .payment_request
+            .as_ref()
+            .and_then(PaymentRequest::amount_msat);
+        match (payment_uri.amount, invoice_amount) {
+            (Some(amount), Some(invoice_amount)) if amount != invoice_amount => {
+                Err(Error::InvalidValue(format!(
+                    "URI amount of {} differs from the payment request amount of {}",
+                    amount, invoice_amount
+                )))
+            }
+            (None, Some(_)) => Ok(PaymentUri {
+                amount: invoice_amount,
+                ..payment_uri
+            }),
+            _ if payment_uri.address.is_none() && payment_uri.payment_request.is_none() => Err(
+                Error::InvalidValue("bitcoin URI has neither address nor lightning".to_owned()),
+            ),
+            _ => Ok(payment_uri),
+        }
+    }
+
+    /// Write this URI: a BIP-21 URI if there is an on-chain address, a `lightning:` URI
+    /// otherwise. Fails if the payment request can't be encoded. BIP-21 amounts have at most 8
+    /// decimals, so an amount that isn't a whole number of satoshis is left out.
+    pub fn encode(&self) -> Result<String, Error> {
+        let payment_request = match self.payment_request {
+            Some(ref payment_request) => Some(payment_request.encode()?),
+            None => None,
+        };
+        let address = match self.address {
+            Some(ref address) => address,
+            None => {
+                return payment_request
+                    .map(|payment_request| LIGHTNING_SCHEME.to_owned() + &payment_request)
+                    .ok_or_else(|| Error::InvalidValue("URI has nothing to pay".to_owned()))
+            }
+        };
+        let params = vec![
+            self.amount
+                .filter(|amount| amount.to_sat(Rounding::Exact).is_ok())
+                .map(|amount| format!("amount={}", amount.to_btc_string())),
+            self.label.as_ref().map(|label| format!("label={}", percent_encode(label))),
+            self.message
+                .as_ref()
+                .map(|message| format!("message={}", percent_encode(message))),
+            payment_request.map(|invoice| format!("{}={}", LIGHTNING_PARAM, invoice)),
+        ].into_iter()
+            .flatten()
+            .collect::<Vec<String>>();
+        if params.is_empty() {
+            Ok(format!("{}{}", BITCOIN_SCHEME, address))
+        } else {
+            Ok(format!("{}{}?{}", BITCOIN_SCHEME, address, params.join("&")))
+        }
+    }
+}
+
+impl FromStr for PaymentUri {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<PaymentUri, Error> {
+        PaymentUri::parse(uri)
+    }
+}
+
+/// Decode `%XX` escapes.
+pub(crate) fn percent_decode(value: &str) -> Result<String, Error> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            // `from_str_radix` alone would accept a sign, e.g. `%+f`
+            let byte = value
+                .get(index + 1..index + 3)
+                .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| Error::InvalidValue(format!("invalid escape in `{}`", value)))?;
+            decoded.push(byte);
+            index += 3;
+        } else {
+            decoded.push(bytes[index]);
+            index += 1;
+        }
+    }
+    Ok(String::from_utf8(decoded)?)
+}
+
+/// Escape everything but unreserved characters.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INVOICE: &str = "lnbc2500u1pvjluezpp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqd\
+        q5xysxxatsyp3k7enxv4jsxqzpuaztrnwngzn3kdzw5hydlzf03qdgm2hdq27cqv3agm2awhz5se903vruatfh\
+        q77w3ls4evs3ch9zw97j25emudupq63nyw24cg27h2rspfj9srp";
+    const ADDRESS: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+
+    #[test]
+    fn lightning_uri() {
+        let payment_request = PaymentRequest::decode(INVOICE).unwrap();
+        let uri = PaymentUri::parse(&format!("LIGHTNING:{}", INVOICE.to_uppercase())).unwrap();
+        assert_eq!(uri, PaymentUri::lightning(payment_request));
+        assert_eq!(uri.amount, Some(MilliSatoshi::new(250_000_000)));
+        assert_eq!(uri.encode().unwrap(), format!("lightning:{}", INVOICE));
+    }
+
+    #[test]
+    fn unified_uri() {
+        let payment_request = PaymentRequest::decode(INVOICE).unwrap();
+        let mut uri = PaymentUri::unified(ADDRESS.to_owned(), payment_request);
+        uri.label = Some("Café & Co".to_owned());
+        let encoded = uri.encode().unwrap();
+        assert_eq!(
+            encoded,
+            format!(
+                "bitcoin:{}?amount=0.0025&label=Caf%C3%A9%20%26%20Co&lightning={}",
+                ADDRESS, INVOICE
+            )
+        );
+        assert_eq!(encoded.parse::<PaymentUri>().unwrap(), uri);
+
+        // the amount is taken from the payment request when the URI doesn't give one
+        let parsed =
+            PaymentUri::parse(&format!("bitcoin:{}?lightning={}", ADDRESS, INVOICE)).unwrap();
+        assert_eq!(parsed.amount, Some(MilliSatoshi::new(250_000_000)));
+
+        let onchain = PaymentUri::parse(&format!("bitcoin:{}?amount=0.1&message=hi", ADDRESS))
+            .unwrap();
+        assert_eq!(onchain.address, Some(ADDRESS.to_owned()));
+        assert_eq!(onchain.amount, Some(MilliSatoshi::new(10_000_000_000)));
+        assert_eq!(onchain.message, Some("hi".to_owned()));
+        assert_eq!(onchain.payment_request, None);
+        assert_eq!(
+            onchain.encode().unwrap(),
+            format!("bitcoin:{}?amount=0.1&message=hi", ADDRESS)
+        );
+    }
+
+    #[test]
+    fn sub_satoshi_amount() {
+        let mut uri = PaymentUri::parse(&format!("bitcoin:{}?amount=0.001", ADDRESS)).unwrap();
+        uri.amount = Some(MilliSatoshi::new(100_000_001));
+        assert_eq!(uri.encode().unwrap(), format!("bitcoin:{}", ADDRESS));
+        uri.amount = Some(MilliSatoshi::new(100_001_000));
+        assert_eq!(
+            uri.encode().unwrap(),
+            format!("bitcoin:{}?amount=0.00100001", ADDRESS)
+        );
+    }
+
+    #[test]
+    fn invalid_uris() {
+        let mismatch = format!("bitcoin:{}?amount=0.1&lightning={}", ADDRESS, INVOICE);
+        assert!(PaymentUri::parse(&mismatch).is_err());
+        assert!(PaymentUri::parse(&format!("bitcoin:{}?req-pop=x", ADDRESS)).is_err());
+        assert!(PaymentUri::parse(&format!("bitcoin:{}?label=%E", ADDRESS)).is_err());
+        assert!(PaymentUri::parse(&format!("bitcoin:{}?label=%+f", ADDRESS)).is_err());
+        assert!(PaymentUri::parse("bitcoin:").is_err());
+        assert!(PaymentUri::parse(INVOICE).is_err());
+    }
+}
@@ -1,91 +1,36 @@
 //! utils
 
-use std::fmt::Write;
-use std::num;
-use types::{ConvertResult, Error};
-use num::bigint::BigUint;
-
-/// Alias for u8 that contains 5-bit values
-pub type U5 = u8;
+use alloc::string::String;
+use alloc::vec::Vec;
+use hex;
+use hex::HexError;
+use sha2::{Digest, Sha256};
+use u5::U5;
 
 pub trait U5Conversions {
-    /// convert a vector of 5-bit values to hex-string
-    fn u5_to_hex(&self) -> String;
-    /// Convert a vector containing u5 values to u8
-    fn to_u8_vec(&self, padding: bool) -> ConvertResult;
-    /// Convert a vector of u5 values to u64
-    fn u5_vec_to_u64(&self, length: usize) -> u64;
+    /// Convert u5 values to u8
+    fn to_u8_vec(&self, padding: bool) -> Vec<u8>;
 }
 
-impl U5Conversions for Vec<U5> {
-    /// convert a vector of 5-bit values to hex-string
-    fn u5_to_hex(&self) -> String {
-        let u5 = self.iter().fold(BigUint::from(0u64), |mut s, b| {
-            s <<= 5;
-            s |= BigUint::from(*b);
-            s
-        });
-        u5.to_str_radix(16)
-    }
-    /// Convert a vector containing u5 values to u8
-    fn to_u8_vec(&self, padding: bool) -> ConvertResult {
-        convert_bits(self, 5, 8, padding)
-    }
-    /// Convert a vector of u5 values to u64
-    fn u5_vec_to_u64(&self, length: usize) -> u64 {
-        self.iter()
-            .take(length)
-            .fold(0u64, |acc, i| acc * 32u64 + *i as u64)
+impl U5Conversions for [U5] {
+    /// Convert u5 values to u8
+    fn to_u8_vec(&self, padding: bool) -> Vec<u8> {
+        regroup(self.iter().map(|word| word.to_u8()), 5, 8, padding)
     }
 }
 
 pub trait U8Conversions {
-    /// Convert a vector containing u8 values to u5
-    fn to_u5_vec(&self, padding: bool) -> ConvertResult;
-    /// Convert a vector of u8 to hex-string
-    fn to_hex_string(&self) -> String;
+    /// Convert u8 values to u5
+    fn to_u5_vec(&self, padding: bool) -> Vec<U5>;
 }
 
-impl U8Conversions for Vec<u8> {
-    /// Convert a vector containing u8 values to u5
-    fn to_u5_vec(&self, padding: bool) -> ConvertResult {
-        convert_bits(self, 8, 5, padding)
-    }
-    /// Convert a vector of u8 to hex-string
-    fn to_hex_string(&self) -> String {
-        self.iter().fold(String::new(), |mut acc: String, b: &u8| {
-            write!(&mut acc, "{:02x}", b).expect("Unable to write");
-            acc
-        })
-    }
-}
-
-pub trait StringConversions {
-    /// Convert a hex string to bytes
-    fn hex_to_bytes(&self) -> Result<Vec<u8>, num::ParseIntError>;
-}
-
-impl StringConversions for String {
-    fn hex_to_bytes(&self) -> Result<Vec<u8>, num::ParseIntError> {
-        /// split a string in chunks, the length of the string must be even
-        fn split_n(s: &str, n: usize) -> Vec<&str> {
-            (0..(s.len() - n + 1) / 2 + 1)
-                .map(|i| &s[2 * i..2 * i + n])
-                .collect()
-        }
-
-        let padded: String = if self.len() % 2 != 0 {
-            let mut s = String::from("0");
-            s.push_str(self);
-            s
-        } else {
-            self.to_owned()
-        };
-
-        split_n(&padded.trim()[..], 2)
-            .iter()
-            .map(|b| u8::from_str_radix(b, 16))
-            .collect::<Result<Vec<u8>, _>>()
+impl U8Conversions for [u8] {
+    /// Convert u8 values to u5
+    fn to_u5_vec(&self, padding: bool) -> Vec<U5> {
+        regroup(self.iter().cloned(), 8, 5, padding)
+            .into_iter()
+            .map(U5::from_low_bits)
+            .collect()
     }
 }
 
@@ -100,7 +45,7 @@ impl U64VecU5Conversions for u64 {
         let mut acc = Vec::<U5>::new();
         let mut val = *self;
         while val > 0 {
-            acc.push((val % 32) as U5);
+            acc.push(U5::from_low_bits((val % 32) as u8));
             val /= 32;
         }
         acc.reverse();
@@ -109,51 +54,35 @@ impl U64VecU5Conversions for u64 {
 }
 /// convert vec u8 to hex-string
 pub fn to_hex(bytes: &[u8]) -> String {
-    bytes.iter().fold(String::new(), |mut acc: String, b: &u8| {
-        write!(&mut acc, "{:02x}", b).expect("Unable to write");
-        acc
-    })
+    hex::encode(bytes)
 }
 
-/// Decode a hex string into bytes.
-pub fn from_hex(hex_str: &str) -> Result<Vec<u8>, num::ParseIntError> {
-    let padded: String = if hex_str.len() % 2 != 0 {
-        let mut s = String::from("0");
-        s.push_str(hex_str);
-        s
-    } else {
-        hex_str.to_owned()
-    };
-    split_n(&padded.trim()[..], 2)
-        .iter()
-        .map(|b| u8::from_str_radix(b, 16))
-        .collect::<Result<Vec<u8>, _>>()
+/// Decode a hex string into bytes, see `hex::decode`.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub fn from_hex(hex_str: &str) -> Result<Vec<u8>, HexError> {
+    hex::decode(hex_str)
 }
-/// split a string in chunks, the length of the string must be even
-fn split_n(s: &str, n: usize) -> Vec<&str> {
-    (0..(s.len() - n + 1) / 2 + 1)
-        .map(|i| &s[2 * i..2 * i + n])
-        .collect()
+
+/// SHA256 hash of `bytes`.
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&Sha256::digest(bytes));
+    hash
 }
 
-/// Convert between bit sizes
-pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> ConvertResult {
-    if from > 8 || to > 8 {
-        return Err(Error::InvalidParameter(
-            "convert_bits `from` and `to` parameters greater than 8".to_owned(),
-        ));
-    }
+/// Compare two byte slices in time independent of their contents.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Convert between bit sizes, `values` having at most `from` bits each.
+fn regroup<I: Iterator<Item = u8>>(values: I, from: u32, to: u32, pad: bool) -> Vec<u8> {
     let mut acc: u32 = 0;
     let mut bits: u32 = 0;
     let mut ret: Vec<u8> = Vec::new();
     let maxv: u32 = (1 << to) - 1;
-    for value in data {
-        let v: u32 = *value as u32;
-        if (v >> from) != 0 {
-            // Input value exceeds `from` bit size
-            return Err(Error::InvalidInputValue(v as u8));
-        }
-        acc = (acc << from) | v;
+    for value in values {
+        acc = (acc << from) | u32::from(value);
         bits += from;
         while bits >= to {
             bits -= to;
@@ -165,7 +94,7 @@ pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> ConvertResult
             ret.push(((acc << (to - bits)) & maxv) as u8);
         }
     }
-    Ok(ret)
+    ret
 }
 
 #[cfg(test)]
@@ -184,20 +113,32 @@ mod test {
         assert_eq!(from_hex("00000000").unwrap(), vec![0, 0, 0, 0]);
         assert_eq!(from_hex("0a0b0c0d").unwrap(), vec![10, 11, 12, 13]);
         assert_eq!(from_hex("000000ff").unwrap(), vec![0, 0, 0, 255]);
+        assert_eq!(from_hex("fff"), Err(HexError::OddLength(3)));
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            to_hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert!(constant_time_eq(&sha256(b"a"), &sha256(b"a")));
+        assert!(!constant_time_eq(&sha256(b"a"), &sha256(b"b")));
+        assert!(!constant_time_eq(&[1, 2], &[1, 2, 3]));
     }
 
     #[test]
     fn u5_test() {
-        let u5_vec = vec![
+        let u5_vec = U5::from_slice(&[
             14, 20, 15, 7, 13, 26, 0, 25, 18, 6, 11, 13, 8, 21, 4, 20, 3, 17, 2, 29, 3, 12, 29, 3,
             4, 15, 24, 20, 6, 14, 30, 22,
-        ];
-        let u8_vec = vec![
+        ]).unwrap();
+        let u8_vec: Vec<u8> = vec![
             117, 30, 118, 232, 25, 145, 150, 212, 84, 148, 28, 69, 209, 179, 163, 35, 241, 67, 59,
             214,
         ];
 
-        assert!(u5_vec.to_u8_vec(false).unwrap().eq(&u8_vec));
-        assert!(u8_vec.to_u5_vec(true).unwrap().eq(&u5_vec));
+        assert_eq!(u5_vec.to_u8_vec(false), u8_vec);
+        assert_eq!(u8_vec.to_u5_vec(true), u5_vec);
     }
 }
@@ -0,0 +1,64 @@
+//! JavaScript bindings, letting web wallets decode and encode payment requests client-side.
+//!
+//! Payment requests are exchanged as plain objects mirroring the serde representation of
+//! `PaymentRequest`, 64-bit integers being `BigInt`s so that amounts and short channel ids
+//! don't lose precision.
+
+use payment_request::PaymentRequest;
+use serde::Serialize;
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPESCRIPT_DEFINITIONS: &'static str = r#"
+export type AmountEncoding = "Shortest" | { Multiplier: string | null };
+
+export interface ExtraHop {
+  pub_key: string;
+  short_channel_id: bigint;
+  fee_base_msat: bigint;
+  fee_proportional_millionths: number;
+  cltv_expiry_delta: number;
+}
+
+export type Tag =
+  | { PaymentHash: { hash: string } }
+  | { PaymentSecret: { secret: string } }
+  | { PayeeNodeId: { pubkey: string } }
+  | { Description: { description: string } }
+  | { DescriptionHash: { hash: string } }
+  | { FallbackAddress: { version: number; hash: string } }
+  | { Expiry: { seconds: bigint } }
+  | { MinFinalCltvExpiry: { blocks: bigint } }
+  | { RoutingInfo: { path: ExtraHop[] } }
+  | { Metadata: { bytes: string } }
+  | { Features: { features: bigint[] } }
+  | { UnknownTag: { tag: number; bytes: number[] } };
+
+export interface Invoice {
+  prefix: string;
+  amount: bigint | null;
+  amount_encoding: AmountEncoding;
+  timestamp: bigint;
+  node_id: string;
+  tags: Tag[];
+  signature: string;
+}
+
+export function decode_invoice(s: string): Invoice;
+export function encode_invoice(obj: Invoice): string;
+"#;
+
+/// Decode a payment request into an `Invoice` object, throwing if it is invalid.
+#[wasm_bindgen(skip_typescript)]
+pub fn decode_invoice(s: &str) -> Result<JsValue, JsError> {
+    let serializer = Serializer::json_compatible().serialize_large_number_types_as_bigints(true);
+    Ok(PaymentRequest::decode(s)?.serialize(&serializer)?)
+}
+
+/// Encode a signed `Invoice` object, throwing if it is malformed or can't be encoded.
+#[wasm_bindgen(skip_typescript)]
+pub fn encode_invoice(obj: JsValue) -> Result<String, JsError> {
+    let pay_request: PaymentRequest = serde_wasm_bindgen::from_value(obj)?;
+    Ok(pay_request.encode()?)
+}